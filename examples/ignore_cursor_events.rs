@@ -0,0 +1,54 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+extern crate tao;
+
+use tao::{
+  event::{ElementState, Event, KeyEvent, WindowEvent},
+  event_loop::{ControlFlow, EventLoop},
+  keyboard::Key,
+  window::WindowBuilder,
+};
+
+#[allow(clippy::single_match)]
+fn main() {
+  env_logger::init();
+  let event_loop = EventLoop::new();
+
+  let window = WindowBuilder::new()
+    .with_title("Press 'i' to toggle ignoring the cursor")
+    .with_always_on_top(true)
+    .build(&event_loop)
+    .unwrap();
+
+  let mut ignore = false;
+
+  event_loop.run(move |event, _, control_flow| {
+    *control_flow = ControlFlow::Wait;
+
+    match event {
+      Event::WindowEvent { event, .. } => match event {
+        WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+        // The keyboard input still reaches the focused window while the cursor is ignored.
+        WindowEvent::KeyboardInput {
+          event:
+            KeyEvent {
+              logical_key: Key::Character("i"),
+              state: ElementState::Released,
+              ..
+            },
+          ..
+        } => {
+          ignore = !ignore;
+          println!("Ignoring the cursor: {}", ignore);
+          window.set_ignore_cursor_events(ignore).unwrap();
+        }
+        WindowEvent::MouseInput { state, button, .. } => {
+          println!("Mouse input: {:?} {:?}", button, state);
+        }
+        _ => (),
+      },
+      _ => (),
+    }
+  });
+}
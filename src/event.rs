@@ -44,6 +44,7 @@ use crate::{
   dpi::{PhysicalPosition, PhysicalSize},
   keyboard::{self, ModifiersState},
   menu::{MenuId, MenuType},
+  monitor::MonitorHandle,
   platform_impl,
   window::{Theme, WindowId},
 };
@@ -107,6 +108,59 @@ pub enum Event<'a, T: 'static> {
   /// - **iOS / Android:** Unsupported.
   GlobalShortcutEvent(AcceleratorId),
 
+  /// Emitted when a system power setting has changed, e.g. the laptop lid was closed or the
+  /// machine switched to battery power.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux / macOS / iOS / Android:** Unsupported.
+  PowerSettingChanged(PowerSetting),
+
+  /// Emitted when the application gained (`true`) or lost (`false`) activation, i.e. when one of
+  /// its windows became the active window while another application's window was active, or
+  /// the other way around.
+  ///
+  /// Unlike [`WindowEvent::Focused`] and [`WindowEvent::Activated`], this is not emitted when
+  /// the activation merely moves between the application's own windows.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux / macOS / iOS / Android:** Unsupported.
+  ApplicationActivated(bool),
+
+  /// Emitted when the keyboard layout changed, e.g. when the user switched from a QWERTY to an
+  /// AZERTY layout. Characters produced by keys may differ from then on.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** Emitted when GDK reports that the keymap or its text direction changed.
+  ///   Switching between two layouts of the same keymap written in the same direction, e.g.
+  ///   between the Xkb groups of two latin layouts on X11, isn't reported.
+  /// - **macOS / iOS / Android:** Unsupported.
+  KeyboardLayoutChanged,
+
+  /// Emitted when a monitor was connected, with the handle of the new monitor.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Emitted when the display configuration changed, by comparing the monitors
+  ///   with the ones known before the change.
+  /// - **Linux:** Emitted when GDK reports that a monitor was added.
+  /// - **macOS / iOS / Android:** Unsupported.
+  MonitorConnected(MonitorHandle),
+
+  /// Emitted when a monitor was disconnected, with the handle the monitor had.
+  ///
+  /// The handle may return stale values, as the monitor can't be queried anymore.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Emitted when the display configuration changed, by comparing the monitors
+  ///   with the ones known before the change.
+  /// - **Linux:** Emitted when GDK reports that a monitor was removed.
+  /// - **macOS / iOS / Android:** Unsupported.
+  MonitorDisconnected(MonitorHandle),
+
   /// Emitted when the application has been suspended.
   Suspended,
 
@@ -203,6 +257,11 @@ impl<T: Clone> Clone for Event<'static, T> {
         position: *position,
       },
       GlobalShortcutEvent(accelerator_id) => GlobalShortcutEvent(*accelerator_id),
+      PowerSettingChanged(setting) => PowerSettingChanged(*setting),
+      ApplicationActivated(active) => ApplicationActivated(*active),
+      KeyboardLayoutChanged => KeyboardLayoutChanged,
+      MonitorConnected(monitor) => MonitorConnected(monitor.clone()),
+      MonitorDisconnected(monitor) => MonitorDisconnected(monitor.clone()),
     }
   }
 }
@@ -240,6 +299,11 @@ impl<'a, T> Event<'a, T> {
         position,
       }),
       GlobalShortcutEvent(accelerator_id) => Ok(GlobalShortcutEvent(accelerator_id)),
+      PowerSettingChanged(setting) => Ok(PowerSettingChanged(setting)),
+      ApplicationActivated(active) => Ok(ApplicationActivated(active)),
+      KeyboardLayoutChanged => Ok(KeyboardLayoutChanged),
+      MonitorConnected(monitor) => Ok(MonitorConnected(monitor)),
+      MonitorDisconnected(monitor) => Ok(MonitorDisconnected(monitor)),
     }
   }
 
@@ -279,6 +343,11 @@ impl<'a, T> Event<'a, T> {
         position,
       }),
       GlobalShortcutEvent(accelerator_id) => Some(GlobalShortcutEvent(accelerator_id)),
+      PowerSettingChanged(setting) => Some(PowerSettingChanged(setting)),
+      ApplicationActivated(active) => Some(ApplicationActivated(active)),
+      KeyboardLayoutChanged => Some(KeyboardLayoutChanged),
+      MonitorConnected(monitor) => Some(MonitorConnected(monitor)),
+      MonitorDisconnected(monitor) => Some(MonitorDisconnected(monitor)),
     }
   }
 }
@@ -317,11 +386,31 @@ pub enum StartCause {
 #[derive(Debug, PartialEq)]
 pub enum WindowEvent<'a> {
   /// The size of the window has changed. Contains the client area's new dimensions.
+  ///
+  /// When a single change both moves and resizes the window, [`WindowEvent::Moved`] is emitted
+  /// before `Resized`, and both are delivered before the next [`Event::MainEventsCleared`], so
+  /// layout can be deferred to that point to run once per geometry change. Other window events,
+  /// e.g. [`WindowEvent::Minimized`], may be emitted in between.
   Resized(PhysicalSize<u32>),
 
   /// The position of the window has changed. Contains the window's new position.
+  ///
+  /// See [`WindowEvent::Resized`] for the ordering of the two events when both change at once.
   Moved(PhysicalPosition<i32>),
 
+  /// The size of the drawable area of the window has changed, i.e. the size a rendering surface
+  /// should be recreated with.
+  ///
+  /// Unlike [`WindowEvent::Resized`], this is only emitted when the size differs from the last
+  /// one reported, and never with a zero size, e.g. while the window is minimized. It follows
+  /// the `Resized` event it results from, after any [`WindowEvent::ScaleFactorChanged`] and
+  /// once a maximize or restore transition is over.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS / iOS / Android:** Unsupported.
+  SurfaceResized(PhysicalSize<u32>),
+
   /// The window has been requested to close.
   CloseRequested,
 
@@ -346,14 +435,37 @@ pub enum WindowEvent<'a> {
   /// hovered.
   HoveredFileCancelled,
 
-  /// The window received a unicode character.
-  ReceivedImeText(String),
+  /// An event from the input method.
+  ///
+  /// Characters typed outside of a composition, e.g. from the emoji panel, are also reported as
+  /// an [`Ime::Commit`].
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux / macOS:** Only [`Ime::Commit`] is emitted.
+  /// - **iOS / Android:** Unsupported.
+  Ime(Ime),
 
   /// The window gained or lost focus.
   ///
   /// The parameter is true if the window has gained focus, and false if it has lost focus.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** An initial `Focused` is sent once the window is created, after the events
+  ///   emitted while it is set up, telling whether it started out focused.
   Focused(bool),
 
+  /// The window was activated or deactivated.
+  ///
+  /// The active window is the top-level window the user is working with. It usually has the
+  /// focus, but keeps being active while the focus is on one of its child windows.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux / macOS / iOS / Android:** Unsupported.
+  Activated(Activation),
+
   /// An event from the keyboard has been received.
   ///
   /// ## Platform-specific
@@ -396,6 +508,10 @@ pub enum WindowEvent<'a> {
   CursorEntered { device_id: DeviceId },
 
   /// The cursor has left the window.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Also emitted when the window is hidden or destroyed while the cursor is in it.
   CursorLeft { device_id: DeviceId },
 
   /// A mouse wheel movement or touchpad scroll occurred.
@@ -412,6 +528,11 @@ pub enum WindowEvent<'a> {
     device_id: DeviceId,
     state: ElementState,
     button: MouseButton,
+    /// The number of consecutive clicks the press is part of, `1` for a single click and `2` for a
+    /// double click. Releases report the count of their press.
+    ///
+    /// Clicks are consecutive when they happen within the system's double-click time and distance.
+    click_count: u32,
     #[deprecated = "Deprecated in favor of WindowEvent::ModifiersChanged"]
     modifiers: ModifiersState,
   },
@@ -450,6 +571,15 @@ pub enum WindowEvent<'a> {
   /// by the OS, but it can be changed to any value.
   ///
   /// For more information about DPI in general, see the [`dpi`](crate::dpi) module.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** The scale factor is the integer one GTK applies to the window, which GDK
+  ///   derives from the monitor the window is on and the `GDK_SCALE` environment variable. The
+  ///   fractional scaling set with `Xft.dpi`, e.g. by the text scaling of the desktop, isn't
+  ///   part of it. As the event borrows the new size, it doesn't go through the event queue: it
+  ///   is dispatched before the other pending events of the iteration, which may have happened
+  ///   before the scale factor changed.
   ScaleFactorChanged {
     scale_factor: f64,
     new_inner_size: &'a mut PhysicalSize<u32>,
@@ -460,8 +590,46 @@ pub enum WindowEvent<'a> {
   /// Applications might wish to react to this to change the theme of the content of the window
   /// when the system changes the window theme.
   ///
-  /// At the moment this is only supported on Windows.
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** Emitted when the theme preferred by the desktop changes, see
+  ///   [`Window::theme`](crate::window::Window::theme).
+  /// - **macOS / iOS / Android:** Unsupported.
   ThemeChanged(Theme),
+
+  /// The title of the window changed, either through [`Window::set_title`] or by another
+  /// party, e.g. a library setting the window text directly.
+  ///
+  /// [`Window::set_title`]: crate::window::Window::set_title
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux / macOS / iOS / Android:** Unsupported.
+  TitleChanged(String),
+
+  /// The decorations of the window were turned on or off with [`Window::set_decorations`]. The
+  /// value is whether the window is now decorated, like [`Window::is_decorated`].
+  ///
+  /// [`Window::set_decorations`]: crate::window::Window::set_decorations
+  /// [`Window::is_decorated`]: crate::window::Window::is_decorated
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux / macOS / iOS / Android:** Unsupported.
+  DecorationsChanged(bool),
+
+  /// The window has been minimized or restored from being minimized. The value is whether the
+  /// window is now minimized, like [`Window::is_minimized`].
+  ///
+  /// It's emitted whether the user or [`Window::set_minimized`] changed the state.
+  ///
+  /// [`Window::is_minimized`]: crate::window::Window::is_minimized
+  /// [`Window::set_minimized`]: crate::window::Window::set_minimized
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **iOS / Android:** Unsupported.
+  Minimized(bool),
 }
 
 impl Clone for WindowEvent<'static> {
@@ -469,13 +637,14 @@ impl Clone for WindowEvent<'static> {
     use self::WindowEvent::*;
     return match self {
       Resized(size) => Resized(*size),
+      SurfaceResized(size) => SurfaceResized(*size),
       Moved(pos) => Moved(*pos),
       CloseRequested => CloseRequested,
       Destroyed => Destroyed,
       DroppedFile(file) => DroppedFile(file.clone()),
       HoveredFile(file) => HoveredFile(file.clone()),
       HoveredFileCancelled => HoveredFileCancelled,
-      ReceivedImeText(c) => ReceivedImeText(c.clone()),
+      Ime(ime) => Ime(ime.clone()),
       Focused(f) => Focused(*f),
       KeyboardInput {
         device_id,
@@ -521,11 +690,13 @@ impl Clone for WindowEvent<'static> {
         device_id,
         state,
         button,
+        click_count,
         modifiers,
       } => MouseInput {
         device_id: *device_id,
         state: *state,
         button: *button,
+        click_count: *click_count,
         modifiers: *modifiers,
       },
       TouchpadPressure {
@@ -548,6 +719,10 @@ impl Clone for WindowEvent<'static> {
       },
       Touch(touch) => Touch(*touch),
       ThemeChanged(theme) => ThemeChanged(*theme),
+      Activated(activation) => Activated(*activation),
+      TitleChanged(title) => TitleChanged(title.clone()),
+      DecorationsChanged(decorated) => DecorationsChanged(*decorated),
+      Minimized(minimized) => Minimized(*minimized),
       ScaleFactorChanged { .. } => {
         unreachable!("Static event can't be about scale factor changing")
       }
@@ -560,13 +735,14 @@ impl<'a> WindowEvent<'a> {
     use self::WindowEvent::*;
     match self {
       Resized(size) => Some(Resized(size)),
+      SurfaceResized(size) => Some(SurfaceResized(size)),
       Moved(position) => Some(Moved(position)),
       CloseRequested => Some(CloseRequested),
       Destroyed => Some(Destroyed),
       DroppedFile(file) => Some(DroppedFile(file)),
       HoveredFile(file) => Some(HoveredFile(file)),
       HoveredFileCancelled => Some(HoveredFileCancelled),
-      ReceivedImeText(c) => Some(ReceivedImeText(c)),
+      Ime(ime) => Some(Ime(ime)),
       Focused(focused) => Some(Focused(focused)),
       KeyboardInput {
         device_id,
@@ -607,11 +783,13 @@ impl<'a> WindowEvent<'a> {
         device_id,
         state,
         button,
+        click_count,
         modifiers,
       } => Some(MouseInput {
         device_id,
         state,
         button,
+        click_count,
         modifiers,
       }),
       TouchpadPressure {
@@ -634,6 +812,10 @@ impl<'a> WindowEvent<'a> {
       }),
       Touch(touch) => Some(Touch(touch)),
       ThemeChanged(theme) => Some(ThemeChanged(theme)),
+      Activated(activation) => Some(Activated(activation)),
+      TitleChanged(title) => Some(TitleChanged(title)),
+      DecorationsChanged(decorated) => Some(DecorationsChanged(decorated)),
+      Minimized(minimized) => Some(Minimized(minimized)),
       ScaleFactorChanged { .. } => None,
     }
   }
@@ -805,6 +987,23 @@ impl KeyEvent {
   pub fn key_without_modifiers(&self) -> keyboard::Key<'static> {
     self.platform_specific.key_without_modifiers.clone()
   }
+
+  /// The platform scancode of the physical key, as accepted by [`KeyCode::from_scancode`].
+  ///
+  /// This is useful to match keys against external keymaps. Unlike `physical_key`, it's also
+  /// meaningful for keys `tao` doesn't know about.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Extended keys have the `0xE000` bit set, e.g. right <kbd>Ctrl</kbd> is
+  ///   `0xE01D`.
+  /// - **Linux:** The X11 keycode.
+  /// - **macOS:** The virtual key code.
+  ///
+  /// [`KeyCode::from_scancode`]: crate::keyboard::KeyCode::from_scancode
+  pub fn scancode(&self) -> u32 {
+    self.platform_specific.scancode
+  }
 }
 
 #[cfg(any(target_os = "android", target_os = "ios"))]
@@ -818,6 +1017,59 @@ impl KeyEvent {
   pub fn key_without_modifiers(&self) -> keyboard::Key<'static> {
     self.logical_key.clone()
   }
+
+  /// The platform scancode of `physical_key`, or `0` if it has none.
+  pub fn scancode(&self) -> u32 {
+    self.physical_key.to_scancode().unwrap_or(0)
+  }
+}
+
+/// Describes an event from the input method.
+///
+/// While composing, the input method sends [`Ime::Preedit`] updates with the text being
+/// composed, which the application should draw at the insertion point, and finally an
+/// [`Ime::Commit`] with the text to insert. [`Ime::Enabled`] and [`Ime::Disabled`] report
+/// whether the input method is allowed, see
+/// [`Window::set_ime_allowed`](crate::window::Window::set_ime_allowed).
+///
+/// ## Platform-specific
+///
+/// - **Linux:** There is no preedit.
+/// - **macOS:** Only [`Ime::Commit`] is sent.
+/// - **iOS / Android:** Unsupported.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Ime {
+  /// The input method was allowed again with
+  /// [`Window::set_ime_allowed`](crate::window::Window::set_ime_allowed).
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS / iOS / Android:** Never sent.
+  Enabled,
+
+  /// The text being composed changed.
+  ///
+  /// The cursor is a range of byte offsets into the text, equal bounds meaning a caret. It is
+  /// `None` when the cursor shouldn't be drawn. An empty text clears the composition, e.g. when
+  /// it is committed or cancelled with <kbd>Escape</kbd>.
+  Preedit {
+    text: String,
+    cursor: Option<(usize, usize)>,
+  },
+
+  /// The input method committed text, which should be inserted at the insertion point.
+  Commit(String),
+
+  /// The input method was disallowed with
+  /// [`Window::set_ime_allowed`](crate::window::Window::set_ime_allowed). No [`Ime::Preedit`]
+  /// or [`Ime::Commit`] follows until the next [`Ime::Enabled`].
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS / iOS / Android:** Never sent.
+  Disabled,
 }
 
 /// Describes touch-screen input state.
@@ -861,6 +1113,46 @@ pub enum TrayEvent {
   DoubleClick,
 }
 
+/// Describes how a window's activation changed.
+#[non_exhaustive]
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Activation {
+  /// The window was deactivated.
+  Inactive,
+  /// The window was activated by some other means than a mouse click, e.g. the keyboard or
+  /// programmatically.
+  Active,
+  /// The window was activated by a mouse click.
+  ClickActive,
+}
+
+/// Describes a change of a system power setting.
+#[non_exhaustive]
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PowerSetting {
+  /// The primary display was turned on (`true`) or off (`false`).
+  MonitorPower(bool),
+  /// The laptop lid was opened (`true`) or closed (`false`).
+  LidOpen(bool),
+  /// The system switched to a different power source.
+  PowerSource(PowerSource),
+}
+
+/// Describes the power source the system is currently running on.
+#[non_exhaustive]
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PowerSource {
+  /// AC power, e.g. a wall charger.
+  Ac,
+  /// Battery power.
+  Battery,
+  /// Short-term power, e.g. an UPS.
+  ShortTerm,
+}
+
 /// Describes a rectangle including position (x - y axis) and size.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Rectangle {
@@ -884,6 +1176,14 @@ pub struct Rectangle {
 /// A `Cancelled` event is emitted when the system has canceled tracking this
 /// touch, such as when the window loses focus, or on iOS if the user moves the
 /// device against their face.
+///
+/// ## Platform-specific
+///
+/// - **Windows:** A touch or pen contact is captured by the window it started in, so its events
+///   keep coming when it moves outside of the window, until it ends. There is no explicit
+///   capture API for contacts in Win32. If another window or the system takes the capture
+///   away, a `Cancelled` event is emitted at the last known location of the contact. Contacts
+///   cancelled by the system, e.g. by palm rejection, also end with `Cancelled`.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Touch {
   pub device_id: DeviceId,
@@ -896,6 +1196,21 @@ pub struct Touch {
   ///
   /// - Only available on **iOS** 9.0+ and **Windows** 8+.
   pub force: Option<Force>,
+  /// The tilt of a pen, in degrees from -90 to 90 along the X and Y axes. A positive X tilt is
+  /// to the right and a positive Y tilt is toward the user. `None` for touches and pens without
+  /// tilt sensors.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - Only available on **Windows** 8+.
+  pub tilt: Option<(f64, f64)>,
+  /// The clockwise rotation of a pen around its own axis, in degrees from 0 to 359. `None` for
+  /// touches and pens without rotation sensors.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - Only available on **Windows** 8+.
+  pub twist: Option<f64>,
   /// Unique identifier of a finger.
   pub id: u64,
 }
@@ -984,6 +1299,22 @@ pub enum MouseButton {
   Other(u16),
 }
 
+bitflags! {
+  /// A set of mouse buttons, as returned by [`Window::pressed_mouse_buttons`].
+  ///
+  /// [`Window::pressed_mouse_buttons`]: crate::window::Window::pressed_mouse_buttons
+  #[derive(Default)]
+  pub struct MouseButtons: u8 {
+    const LEFT = 1 << 0;
+    const RIGHT = 1 << 1;
+    const MIDDLE = 1 << 2;
+    /// The first side button, usually "back".
+    const BACK = 1 << 3;
+    /// The second side button, usually "forward".
+    const FORWARD = 1 << 4;
+  }
+}
+
 /// Describes a difference in the mouse scroll wheel state.
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -13,9 +13,15 @@
 //! [event_loop_proxy]: crate::event_loop::EventLoopProxy
 //! [send_event]: crate::event_loop::EventLoopProxy::send_event
 use instant::Instant;
-use std::{error, fmt, ops::Deref};
+use std::{
+  error, fmt,
+  ops::Deref,
+  sync::{Arc, Mutex},
+};
 
 use crate::{event::Event, monitor::MonitorHandle, platform_impl};
+#[cfg(feature = "testing")]
+use crate::{event::WindowEvent, window::WindowId};
 
 /// Provides a way to retrieve events from the system and from the windows that were registered to
 /// the events loop.
@@ -44,6 +50,11 @@ pub struct EventLoop<T: 'static> {
 #[derive(Clone)]
 pub struct EventLoopWindowTarget<T: 'static> {
   pub(crate) p: platform_impl::EventLoopWindowTarget<T>,
+  /// Set by [`EventLoopWindowTarget::set_control_flow`] and [`Window::set_control_flow`], applied
+  /// once the event handler returns.
+  ///
+  /// [`Window::set_control_flow`]: crate::window::Window::set_control_flow
+  pub(crate) pending_control_flow: Arc<Mutex<Option<ControlFlow>>>,
   pub(crate) _marker: ::std::marker::PhantomData<*mut ()>, // Not Send nor Sync
 }
 
@@ -115,6 +126,26 @@ impl Default for ControlFlow {
   }
 }
 
+/// Filters which [`DeviceEvent`]s are emitted, see
+/// [`EventLoopWindowTarget::set_device_event_filter`].
+///
+/// [`DeviceEvent`]: crate::event::DeviceEvent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceEventFilter {
+  /// Always emit device events.
+  Always,
+  /// Only emit device events while a window of the application has the focus.
+  WhenFocused,
+  /// Never emit device events.
+  Never,
+}
+
+impl Default for DeviceEventFilter {
+  fn default() -> Self {
+    Self::Always
+  }
+}
+
 impl EventLoop<()> {
   /// Builds a new event loop with a `()` as the user event type.
   ///
@@ -174,7 +205,11 @@ impl<T> EventLoop<T> {
   where
     F: 'static + FnMut(Event<'_, T>, &EventLoopWindowTarget<T>, &mut ControlFlow),
   {
-    self.event_loop.run(event_handler)
+    let mut event_handler = event_handler;
+    self.event_loop.run(move |event, target, control_flow| {
+      event_handler(event, target, control_flow);
+      target.apply_pending_control_flow(control_flow);
+    })
   }
 
   /// Creates an `EventLoopProxy` that can be used to dispatch user events to the main event loop.
@@ -193,7 +228,49 @@ impl<T> Deref for EventLoop<T> {
 }
 
 impl<T> EventLoopWindowTarget<T> {
+  /// Sets the control flow of the event loop, as if it was set through the `&mut ControlFlow`
+  /// given to the event handler.
+  ///
+  /// This is useful when the logic deciding the control flow lives away from the event handler.
+  /// The new control flow takes effect once the current call of the event handler returns, and
+  /// overrides the value written to its `&mut ControlFlow`. Like there, a
+  /// [`ControlFlow::ExitWithCode`] can't be unset.
+  #[inline]
+  pub fn set_control_flow(&self, control_flow: ControlFlow) {
+    *self.pending_control_flow.lock().unwrap() = Some(control_flow);
+  }
+
+  pub(crate) fn apply_pending_control_flow(&self, control_flow: &mut ControlFlow) {
+    if let Some(pending) = self.pending_control_flow.lock().unwrap().take() {
+      if !matches!(control_flow, ControlFlow::ExitWithCode(_)) {
+        *control_flow = pending;
+      }
+    }
+  }
+
+  /// Changes which device events are emitted. Defaults to [`DeviceEventFilter::Always`].
+  ///
+  /// Device events can be turned off while they aren't needed, e.g. outside of the mouse-look
+  /// mode of a game, which saves processing every raw input of the mice and keyboards.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The raw input of the mice and keyboards is only registered while device
+  ///   events are emitted.
+  /// - **Linux:** The raw input is still read, the filtered out events are dropped.
+  /// - **macOS / iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_device_event_filter(&self, filter: DeviceEventFilter) {
+    self.p.set_device_event_filter(filter);
+  }
+
   /// Returns the list of all the monitors available on the system.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The monitors are enumerated once and cached until the display configuration
+  ///   changes, which also applies to [`EventLoopWindowTarget::primary_monitor`]. The returned
+  ///   handles compare equal across calls.
   #[inline]
   pub fn available_monitors(&self) -> impl Iterator<Item = MonitorHandle> {
     self
@@ -205,11 +282,28 @@ impl<T> EventLoopWindowTarget<T> {
 
   /// Returns the primary monitor of the system.
   ///
-  /// Returns `None` if it can't identify any monitor as a primary one.
+  /// Returns `None` if it can't identify any monitor as a primary one, e.g. in a headless
+  /// session, so it shouldn't be unwrapped.
   #[inline]
   pub fn primary_monitor(&self) -> Option<MonitorHandle> {
     self.p.primary_monitor()
   }
+
+  /// Injects a `WindowEvent` for the given window into the event loop, as if it came from the
+  /// windowing system. It goes through the same path as the platform events, so it is delivered
+  /// to the event handler in order with them.
+  ///
+  /// This is meant for integration tests and is only available with the `testing` feature.
+  ///
+  /// **This API is unstable and may change or disappear in any release.**
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **iOS / Android:** Unsupported.
+  #[cfg(feature = "testing")]
+  pub fn __inject_window_event(&self, window_id: WindowId, event: WindowEvent<'static>) {
+    self.p.inject_window_event(window_id, event)
+  }
 }
 
 /// Used to send custom events to `EventLoop`.
@@ -231,6 +325,14 @@ impl<T: 'static> EventLoopProxy<T> {
   /// function.
   ///
   /// Returns an `Err` if the associated `EventLoop` no longer exists.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Events sent from a thread are delivered in order with the `Window` calls
+  ///   made from that thread which are forwarded to the event loop thread, such as
+  ///   `Window::set_title` or `Window::request_redraw`. An `Err` is also returned once the event
+  ///   loop has exited, until it is run again with `run_return`. The events that weren't
+  ///   received when the event loop exits are dropped.
   pub fn send_event(&self, event: T) -> Result<(), EventLoopClosed<T>> {
     self.event_loop_proxy.send_event(event)
   }
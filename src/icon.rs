@@ -32,6 +32,14 @@ pub enum BadIcon {
     width_x_height: usize,
     pixel_count: usize,
   },
+  /// Produced when the hotspot of a `CustomCursor` is outside of its image.
+  #[non_exhaustive]
+  HotspotOutOfBounds {
+    width: u32,
+    height: u32,
+    hotspot_x: u32,
+    hotspot_y: u32,
+  },
   /// Produced when underlying OS functionality failed to create the icon
   OsError(io::Error),
 }
@@ -52,6 +60,15 @@ impl fmt::Display for BadIcon {
                 "The specified dimensions ({:?}x{:?}) don't match the number of pixels supplied by the `rgba` argument ({:?}). For those dimensions, the expected pixel count is {:?}.",
                 width, height, pixel_count, width_x_height,
             ),
+            BadIcon::HotspotOutOfBounds {
+                width,
+                height,
+                hotspot_x,
+                hotspot_y,
+            } => write!(f,
+                "The hotspot ({:?}, {:?}) is outside of the {:?}x{:?} cursor image.",
+                hotspot_x, hotspot_y, width, height,
+            ),
             BadIcon::OsError(e) => write!(f, "OS error when instantiating the icon: {:?}", e),
         }
   }
@@ -139,3 +156,56 @@ impl Icon {
     })
   }
 }
+
+/// A cursor made from an image, shown over a window with
+/// [`Window::set_custom_cursor`](crate::window::Window::set_custom_cursor).
+///
+/// On Windows, it can also be loaded from a `.cur` or animated `.ani` file with
+/// `IconExtWindows::cursor_from_path`.
+#[derive(Debug, Clone)]
+pub struct CustomCursor {
+  pub(crate) inner: CustomCursorSource,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum CustomCursorSource {
+  Rgba {
+    image: RgbaIcon,
+    hotspot_x: u32,
+    hotspot_y: u32,
+  },
+  /// A cursor loaded by the platform, which brings its own hotspot and animation.
+  #[cfg(target_os = "windows")]
+  Platform(crate::platform_impl::PlatformCursor),
+}
+
+impl CustomCursor {
+  /// Creates a `CustomCursor` from 32bpp RGBA data, with its hotspot, the point of the image that
+  /// matches the cursor position, at (`hotspot_x`, `hotspot_y`) from the top left corner.
+  ///
+  /// The length of `rgba` must be divisible by 4, `width * height` must equal `rgba.len() / 4`,
+  /// and the hotspot must be inside of the image. Otherwise, this will return a `BadIcon` error.
+  pub fn from_rgba(
+    rgba: Vec<u8>,
+    width: u32,
+    height: u32,
+    hotspot_x: u32,
+    hotspot_y: u32,
+  ) -> Result<Self, BadIcon> {
+    if hotspot_x >= width || hotspot_y >= height {
+      return Err(BadIcon::HotspotOutOfBounds {
+        width,
+        height,
+        hotspot_x,
+        hotspot_y,
+      });
+    }
+    Ok(CustomCursor {
+      inner: CustomCursorSource::Rgba {
+        image: RgbaIcon::from_rgba(rgba, width, height)?,
+        hotspot_x,
+        hotspot_y,
+      },
+    })
+  }
+}
@@ -145,6 +145,8 @@ impl MonitorHandle {
   ///
   /// ## Platform-specific
   ///
+  /// - **Windows:** The effective DPI of the monitor, which is the scale factor a window created
+  ///   on it reports.
   /// - **Android:** Always returns 1.0.
   #[inline]
   pub fn scale_factor(&self) -> f64 {
@@ -44,6 +44,12 @@ impl<T> EventLoopExtRunReturn for EventLoop<T> {
   where
     F: FnMut(Event<'_, Self::UserEvent>, &EventLoopWindowTarget<Self::UserEvent>, &mut ControlFlow),
   {
-    self.event_loop.run_return(event_handler)
+    let mut event_handler = event_handler;
+    self
+      .event_loop
+      .run_return(move |event, target, control_flow| {
+        event_handler(event, target, control_flow);
+        target.apply_pending_control_flow(control_flow);
+      })
   }
 }
@@ -12,7 +12,7 @@
 pub use crate::platform_impl::{hit_test, EventLoop as UnixEventLoop};
 use crate::{
   event_loop::EventLoop,
-  window::{Window, WindowBuilder},
+  window::{Icon, Window, WindowBuilder},
 };
 
 /// Additional methods on `Window` that are specific to Unix.
@@ -22,6 +22,12 @@ pub trait WindowExtUnix {
 
   /// Whether to show the window icon in the taskbar or not.
   fn set_skip_taskbar(&self, skip: bool);
+
+  /// Sets the icon shown in the taskbar, usually a larger version of the window icon.
+  ///
+  /// Both icons are given to the window manager, which picks the one closest to the size it
+  /// needs in each place, so the taskbar icon isn't guaranteed to be used for the taskbar only.
+  fn set_taskbar_icon(&self, taskbar_icon: Option<Icon>);
 }
 
 impl WindowExtUnix for Window {
@@ -32,11 +38,18 @@ impl WindowExtUnix for Window {
   fn set_skip_taskbar(&self, skip: bool) {
     self.window.set_skip_taskbar(skip);
   }
+
+  fn set_taskbar_icon(&self, taskbar_icon: Option<Icon>) {
+    self.window.set_taskbar_icon(taskbar_icon);
+  }
 }
 
 pub trait WindowBuilderExtUnix {
   /// Whether to create the window icon with the taskbar icon or not.
   fn with_skip_taskbar(self, skip: bool) -> WindowBuilder;
+
+  /// Sets the icon shown in the taskbar, see [`WindowExtUnix::set_taskbar_icon`].
+  fn with_taskbar_icon(self, taskbar_icon: Option<Icon>) -> WindowBuilder;
 }
 
 impl WindowBuilderExtUnix for WindowBuilder {
@@ -44,6 +57,11 @@ impl WindowBuilderExtUnix for WindowBuilder {
     self.platform_specific.skip_taskbar = skip;
     self
   }
+
+  fn with_taskbar_icon(mut self, taskbar_icon: Option<Icon>) -> WindowBuilder {
+    self.platform_specific.taskbar_icon = taskbar_icon;
+    self
+  }
 }
 
 /// Additional methods on `EventLoop` that are specific to Unix.
@@ -3,21 +3,26 @@
 
 #![cfg(target_os = "windows")]
 
-use std::path::Path;
+use std::{
+  path::{Path, PathBuf},
+  time::Duration,
+};
 
-pub use crate::platform_impl::hit_test;
+pub use crate::platform_impl::{hit_test, DisplayOutput, DropEffect, MaximizeMode, WaitPrecision};
 use crate::{
   dpi::PhysicalSize,
+  error::ExternalError,
   event::DeviceId,
-  event_loop::EventLoop,
+  event_loop::{EventLoop, EventLoopWindowTarget},
+  icon::CustomCursorSource,
   monitor::MonitorHandle,
-  platform_impl::{EventLoop as WindowsEventLoop, Parent, WinIcon},
-  window::{BadIcon, Icon, Theme, Window, WindowBuilder},
+  platform_impl::{EventLoop as WindowsEventLoop, Parent, PlatformCursor, WinIcon},
+  window::{BadIcon, CustomCursor, Icon, Theme, Window, WindowBuilder},
 };
 use libc;
 use windows::Win32::{
   Foundation::HWND,
-  UI::{Input::KeyboardAndMouse::*, WindowsAndMessaging::*},
+  UI::{HiDpi::DPI_AWARENESS_CONTEXT, Input::KeyboardAndMouse::*, WindowsAndMessaging::*},
 };
 
 /// Additional methods on `EventLoop` that are specific to Windows.
@@ -73,11 +78,89 @@ impl<T> EventLoopExtWindows for EventLoop<T> {
   }
 }
 
+/// Additional methods on `EventLoopWindowTarget` that are specific to Windows.
+pub trait EventLoopWindowTargetExtWindows {
+  /// Returns the handle of the invisible window that receives the thread-level messages of
+  /// the event loop (user events, raw input, wake-ups...).
+  ///
+  /// It can be used as the target of OS notifications that aren't tied to a visible window,
+  /// e.g. `RegisterPowerSettingNotification` or `RegisterDeviceNotificationW`. Messages sent or
+  /// posted to it are handled on the event loop thread; the ones tao doesn't know about are
+  /// passed to the default window procedure.
+  ///
+  /// The handle is valid for as long as the event loop lives.
+  fn msg_hwnd(&self) -> *mut libc::c_void;
+
+  /// Sets the minimum time between two iterations of the event loop while the control flow is
+  /// [`ControlFlow::Poll`](crate::event_loop::ControlFlow::Poll). Defaults to `None`, where
+  /// the next iteration starts as soon as the previous one is over.
+  ///
+  /// With an interval, an iteration without new OS events waits for the interval to elapse
+  /// instead of spinning the CPU, while OS events still start one right away. The tradeoff is
+  /// latency: work done on `MainEventsCleared`, e.g. rendering, happens at most once per
+  /// interval when idle.
+  fn set_poll_interval(&self, interval: Option<Duration>);
+
+  /// Sets how precisely the event loop wakes up at the instant of
+  /// [`ControlFlow::WaitUntil`](crate::event_loop::ControlFlow::WaitUntil), or at the end of
+  /// the poll interval. Defaults to [`WaitPrecision::HighPrecision`].
+  ///
+  /// [`WaitPrecision::PowerSaving`] is meant for background apps where a few milliseconds of
+  /// jitter don't matter, but the power spent spinning does.
+  fn set_wait_precision(&self, precision: WaitPrecision);
+
+  /// Sets whether the raw input of the devices, reported as
+  /// [`DeviceEvent`](crate::event::DeviceEvent)s, is read in batches. Defaults to `false`.
+  ///
+  /// When a `WM_INPUT` message arrives, the input queued meanwhile is read at once instead of
+  /// waiting for its own messages, which reduces the overhead of high polling rate devices such
+  /// as gaming mice. This has no effect in 32-bit builds.
+  fn set_buffered_raw_input(&self, buffered: bool);
+
+  /// Returns the displays connected to every output of the display adapters, including the ones
+  /// disabled in the display settings and the ones mirroring another display.
+  ///
+  /// Unlike [`available_monitors`](EventLoopWindowTarget::available_monitors), which only
+  /// returns the monitors that are part of the desktop, this describes the full display
+  /// topology. A set of mirrored displays appears once in `available_monitors`.
+  fn all_outputs(&self) -> Vec<DisplayOutput>;
+}
+
+impl<T> EventLoopWindowTargetExtWindows for EventLoopWindowTarget<T> {
+  #[inline]
+  fn msg_hwnd(&self) -> *mut libc::c_void {
+    self.p.msg_hwnd().0 as _
+  }
+
+  #[inline]
+  fn set_poll_interval(&self, interval: Option<Duration>) {
+    self.p.set_poll_interval(interval);
+  }
+
+  #[inline]
+  fn set_wait_precision(&self, precision: WaitPrecision) {
+    self.p.set_wait_precision(precision);
+  }
+
+  #[inline]
+  fn set_buffered_raw_input(&self, buffered: bool) {
+    self.p.set_buffered_raw_input(buffered);
+  }
+
+  #[inline]
+  fn all_outputs(&self) -> Vec<DisplayOutput> {
+    self.p.all_outputs()
+  }
+}
+
 /// Additional methods on `Window` that are specific to Windows.
 pub trait WindowExtWindows {
-  /// Returns the HINSTANCE of the window
+  /// Returns the `HINSTANCE` of the module that registered the window class.
+  ///
+  /// Useful together with [`WindowExtWindows::hwnd`] to create a graphics context or to call
+  /// Win32 APIs directly.
   fn hinstance(&self) -> *mut libc::c_void;
-  /// Returns the native handle that is used by this window.
+  /// Returns the native handle that is used by this window, the `HWND`.
   ///
   /// The pointer will become invalid when the native window was destroyed.
   fn hwnd(&self) -> *mut libc::c_void;
@@ -115,6 +198,38 @@ pub trait WindowExtWindows {
 
   /// Whether to show the window icon in the taskbar or not.
   fn set_skip_taskbar(&self, skip: bool);
+
+  /// Sets the area a maximized borderless window covers. Defaults to [`MaximizeMode::WorkArea`].
+  ///
+  /// Has no effect on decorated windows.
+  fn set_maximize_mode(&self, mode: MaximizeMode);
+
+  /// Flashes the window and its taskbar button until the window gets the focus, which stops
+  /// the flashing automatically.
+  ///
+  /// This has no effect if the window already has the focus. See also
+  /// [`Window::request_user_attention`].
+  fn flash_until_focused(&self);
+
+  /// Sets whether `msg` is passed to `DefWindowProcW` instead of `DefSubclassProc` when tao
+  /// doesn't handle it.
+  ///
+  /// This only affects the default processing of messages tao leaves unhandled, the messages it
+  /// handles keep their behavior. These include `WM_CLOSE`, `WM_DESTROY`, `WM_NCDESTROY`,
+  /// `WM_PAINT`, `WM_WINDOWPOSCHANGING`, `WM_WINDOWPOSCHANGED`, `WM_SIZE`,
+  /// `WM_ENTERSIZEMOVE`, `WM_EXITSIZEMOVE`, `WM_GETMINMAXINFO`, `WM_DPICHANGED`,
+  /// `WM_NCCALCSIZE`, `WM_NCHITTEST`, `WM_SETCURSOR`, `WM_SETFOCUS`, `WM_KILLFOCUS`,
+  /// `WM_ACTIVATE`, `WM_MOUSEACTIVATE`, the keyboard, IME, mouse, touch and pointer messages,
+  /// and the messages registered by tao.
+  fn set_force_def_window_proc(&self, msg: u32, force: bool);
+
+  /// Starts dragging `paths` out of the window, e.g. into Explorer, and returns what the drop
+  /// target did with them once the drag ends.
+  ///
+  /// This should be called while the left or right mouse button is held down, typically on
+  /// `MouseInput` or `CursorMoved`. It blocks until the drag ends, but the event loop thread
+  /// keeps processing window messages in the meantime.
+  fn start_drag(&self, paths: Vec<PathBuf>) -> Result<DropEffect, ExternalError>;
 }
 
 impl WindowExtWindows for Window {
@@ -159,6 +274,26 @@ impl WindowExtWindows for Window {
   fn set_skip_taskbar(&self, skip: bool) {
     self.window.set_skip_taskbar(skip);
   }
+
+  #[inline]
+  fn set_maximize_mode(&self, mode: MaximizeMode) {
+    self.window.set_maximize_mode(mode);
+  }
+
+  #[inline]
+  fn flash_until_focused(&self) {
+    self.window.flash_until_focused();
+  }
+
+  #[inline]
+  fn set_force_def_window_proc(&self, msg: u32, force: bool) {
+    self.window.set_force_def_window_proc(msg, force);
+  }
+
+  #[inline]
+  fn start_drag(&self, paths: Vec<PathBuf>) -> Result<DropEffect, ExternalError> {
+    self.window.start_drag(paths)
+  }
 }
 
 /// Additional methods on `WindowBuilder` that are specific to Windows.
@@ -196,9 +331,28 @@ pub trait WindowBuilderExtWindows {
   /// This sets `ICON_BIG`. A good ceiling here is 256x256.
   fn with_taskbar_icon(self, taskbar_icon: Option<Icon>) -> WindowBuilder;
 
-  /// This sets `WS_EX_NOREDIRECTIONBITMAP`.
+  /// This sets `WS_EX_NOREDIRECTIONBITMAP`, so the DWM doesn't allocate a redirection surface
+  /// for the window. This is meant for windows whose content is entirely presented through
+  /// DirectComposition or a flip-model swapchain, e.g. transparent overlays.
+  ///
+  /// The style can only be set at creation. Such a window has no surface for GDI to draw on, so
+  /// GDI drawing to it, including painting the background, isn't displayed.
   fn with_no_redirection_bitmap(self, flag: bool) -> WindowBuilder;
 
+  /// Creates the window as a tool window, with `WS_EX_TOOLWINDOW`.
+  ///
+  /// A tool window has a thinner title bar with only a close button, and isn't shown on the
+  /// taskbar nor in <kbd>Alt</kbd>+<kbd>Tab</kbd>. This is meant for floating palettes.
+  fn with_tool_window(self, tool_window: bool) -> WindowBuilder;
+
+  /// Whether the window falls back to the icon embedded in the executable when no icon is set
+  /// with [`WindowBuilder::with_window_icon`] or [`WindowBuilderExtWindows::with_taskbar_icon`].
+  /// Enabled by default.
+  ///
+  /// The first icon of the executable is used, which is the one the shell shows for it. Without
+  /// it, or when disabled, the window has the generic application icon.
+  fn with_default_executable_icon(self, enabled: bool) -> WindowBuilder;
+
   /// Enables or disables drag and drop support (enabled by default). Will interfere with other crates
   /// that use multi-threaded COM API (`CoInitializeEx` with `COINIT_MULTITHREADED` instead of
   /// `COINIT_APARTMENTTHREADED`) on the same thread. Note that tao may still attempt to initialize
@@ -212,6 +366,31 @@ pub trait WindowBuilderExtWindows {
 
   /// Whether to create the window icon with the taskbar icon or not.
   fn with_skip_taskbar(self, skip: bool) -> WindowBuilder;
+
+  /// Creates the window under the given `DPI_AWARENESS_CONTEXT` (e.g. `DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2`)
+  /// without touching the process-wide DPI awareness. The thread's awareness is switched with
+  /// `SetThreadDpiAwarenessContext` only while the window is being created and restored afterwards.
+  ///
+  /// This is mostly useful when tao is embedded in a host process that manages its own DPI awareness,
+  /// typically in combination with [`EventLoopExtWindows::new_dpi_unaware`].
+  ///
+  /// Has no effect before Windows 10 Anniversary Update (1607).
+  fn with_dpi_awareness_context(self, context: DPI_AWARENESS_CONTEXT) -> WindowBuilder;
+
+  /// Receives touch input through the legacy `WM_TOUCH` messages instead of `WM_POINTER*`
+  /// (disabled by default).
+  ///
+  /// Touch is only ever reported through one of them, so each contact change produces exactly one
+  /// `Touch` event. Before Windows 8, where pointer messages aren't available, `WM_TOUCH` is
+  /// always used.
+  fn with_legacy_touch(self, legacy_touch: bool) -> WindowBuilder;
+
+  /// Sets the name of the window class the window is created with (`"Window Class"` by default),
+  /// e.g. for external automation tools matching windows by class.
+  ///
+  /// The class is registered by the first window using the name, so all windows sharing it also
+  /// share that window's class icons.
+  fn with_class_name<S: Into<String>>(self, class_name: S) -> WindowBuilder;
 }
 
 impl WindowBuilderExtWindows for WindowBuilder {
@@ -245,6 +424,18 @@ impl WindowBuilderExtWindows for WindowBuilder {
     self
   }
 
+  #[inline]
+  fn with_tool_window(mut self, tool_window: bool) -> WindowBuilder {
+    self.platform_specific.tool_window = tool_window;
+    self
+  }
+
+  #[inline]
+  fn with_default_executable_icon(mut self, enabled: bool) -> WindowBuilder {
+    self.platform_specific.executable_icon = enabled;
+    self
+  }
+
   #[inline]
   fn with_drag_and_drop(mut self, flag: bool) -> WindowBuilder {
     self.platform_specific.drag_and_drop = flag;
@@ -262,6 +453,24 @@ impl WindowBuilderExtWindows for WindowBuilder {
     self.platform_specific.skip_taskbar = skip;
     self
   }
+
+  #[inline]
+  fn with_dpi_awareness_context(mut self, context: DPI_AWARENESS_CONTEXT) -> WindowBuilder {
+    self.platform_specific.dpi_awareness_context = Some(context);
+    self
+  }
+
+  #[inline]
+  fn with_legacy_touch(mut self, legacy_touch: bool) -> WindowBuilder {
+    self.platform_specific.legacy_touch = legacy_touch;
+    self
+  }
+
+  #[inline]
+  fn with_class_name<S: Into<String>>(mut self, class_name: S) -> WindowBuilder {
+    self.platform_specific.class_name = class_name.into();
+    self
+  }
 }
 
 /// Additional methods on `MonitorHandle` that are specific to Windows.
@@ -319,6 +528,12 @@ pub trait IconExtWindows: Sized {
   /// In cases where the specified size does not exist in the file, Windows may perform scaling
   /// to get an icon of the desired size.
   fn from_resource(ordinal: u16, size: Option<PhysicalSize<u32>>) -> Result<Self, BadIcon>;
+
+  /// Loads a cursor from a `.cur` or animated `.ani` file, to be set with
+  /// [`Window::set_custom_cursor`]. Animated cursors are animated by the system.
+  ///
+  /// The cursor is destroyed when the last clone of the returned value is dropped.
+  fn cursor_from_path<P: AsRef<Path>>(path: P) -> Result<CustomCursor, BadIcon>;
 }
 
 impl IconExtWindows for Icon {
@@ -331,4 +546,11 @@ impl IconExtWindows for Icon {
     let win_icon = WinIcon::from_resource(ordinal, size)?;
     Ok(Icon { inner: win_icon })
   }
+
+  fn cursor_from_path<P: AsRef<Path>>(path: P) -> Result<CustomCursor, BadIcon> {
+    let cursor = PlatformCursor::from_path(path)?;
+    Ok(CustomCursor {
+      inner: CustomCursorSource::Platform(cursor),
+    })
+  }
 }
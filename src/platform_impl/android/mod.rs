@@ -132,6 +132,7 @@ impl<T: 'static> EventLoop<T> {
         p: EventLoopWindowTarget {
           _marker: std::marker::PhantomData,
         },
+        pending_control_flow: Default::default(),
         _marker: std::marker::PhantomData,
       },
       user_queue: Default::default(),
@@ -279,6 +280,8 @@ impl<T: 'static> EventLoop<T> {
                             location,
                             id: pointer.pointer_id() as u64,
                             force: None,
+                            tilt: None,
+                            twist: None,
                           }),
                         };
                         call_event_handler!(
@@ -470,6 +473,8 @@ pub struct EventLoopWindowTarget<T: 'static> {
 }
 
 impl<T: 'static> EventLoopWindowTarget<T> {
+  pub fn set_device_event_filter(&self, _filter: event_loop::DeviceEventFilter) {}
+
   pub fn primary_monitor(&self) -> Option<monitor::MonitorHandle> {
     Some(monitor::MonitorHandle {
       inner: MonitorHandle,
@@ -481,6 +486,15 @@ impl<T: 'static> EventLoopWindowTarget<T> {
     v.push_back(MonitorHandle);
     v
   }
+
+  #[cfg(feature = "testing")]
+  pub(crate) fn inject_window_event(
+    &self,
+    _window_id: window::WindowId,
+    _event: event::WindowEvent<'static>,
+  ) {
+    warn!("`EventLoopWindowTarget::__inject_window_event` is ignored on Android")
+  }
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -570,12 +584,22 @@ impl Window {
     MonitorHandle.size()
   }
 
+  pub fn content_size(&self) -> PhysicalSize<u32> {
+    self.inner_size()
+  }
+
+  pub fn set_content_size(&self, _size: Size) {}
+
   pub fn set_min_inner_size(&self, _: Option<Size>) {}
 
   pub fn set_max_inner_size(&self, _: Option<Size>) {}
 
   pub fn set_title(&self, _title: &str) {}
 
+  pub fn title(&self) -> String {
+    String::new()
+  }
+
   pub fn set_menu(&self, _menu: Option<Menu>) {}
 
   pub fn set_visible(&self, _visibility: bool) {}
@@ -587,6 +611,8 @@ impl Window {
 
   pub fn set_resizable(&self, _resizeable: bool) {}
 
+  pub fn set_resize_border_width(&self, _width: u32) {}
+
   pub fn set_minimized(&self, _minimized: bool) {}
 
   pub fn set_maximized(&self, _maximized: bool) {}
@@ -622,12 +648,24 @@ impl Window {
 
   pub fn set_always_on_top(&self, _always_on_top: bool) {}
 
+  pub fn set_activate_on_click(&self, _activate_on_click: bool) {}
+
+  pub fn set_transparent(&self, _transparent: bool) {}
+
   pub fn set_window_icon(&self, _window_icon: Option<crate::icon::Icon>) {}
 
   pub fn set_ime_position(&self, _position: Position) {}
 
+  pub fn set_ime_allowed(&self, _allowed: bool) {}
+
+  pub fn set_content_protected(&self, _protected: bool) {}
+
   pub fn request_user_attention(&self, _request_type: Option<window::UserAttentionType>) {}
 
+  pub fn set_badge_count(&self, _count: Option<i64>) {}
+
+  pub fn set_progress_bar(&self, _state: window::ProgressBarState) {}
+
   pub fn hide_menu(&self) {}
 
   pub fn show_menu(&self) {}
@@ -637,8 +675,25 @@ impl Window {
     false
   }
 
+  pub fn theme(&self) -> window::Theme {
+    warn!("`Window::theme` is ignored on Android");
+    window::Theme::Light
+  }
+
+  pub fn pressed_mouse_buttons(&self) -> event::MouseButtons {
+    event::MouseButtons::empty()
+  }
+
+  pub fn pressed_keys(&self) -> Vec<KeyCode> {
+    Vec::new()
+  }
+
   pub fn set_cursor_icon(&self, _: window::CursorIcon) {}
 
+  pub fn set_custom_cursor(&self, _: &crate::icon::CustomCursor) {}
+
+  pub fn set_busy_cursor(&self, _: bool) {}
+
   pub fn set_cursor_position(&self, _: Position) -> Result<(), error::ExternalError> {
     Err(error::ExternalError::NotSupported(
       error::NotSupportedError::new(),
@@ -653,12 +708,27 @@ impl Window {
 
   pub fn set_cursor_visible(&self, _: bool) {}
 
+  pub fn set_ignore_cursor_events(&self, _: bool) -> Result<(), error::ExternalError> {
+    Err(error::ExternalError::NotSupported(
+      error::NotSupportedError::new(),
+    ))
+  }
+
   pub fn drag_window(&self) -> Result<(), error::ExternalError> {
     Err(error::ExternalError::NotSupported(
       error::NotSupportedError::new(),
     ))
   }
 
+  pub fn drag_resize_window(
+    &self,
+    _direction: window::ResizeDirection,
+  ) -> Result<(), error::ExternalError> {
+    Err(error::ExternalError::NotSupported(
+      error::NotSupportedError::new(),
+    ))
+  }
+
   pub fn raw_window_handle(&self) -> RawWindowHandle {
     let mut handle = AndroidNdkHandle::empty();
     if let Some(native_window) = ndk_glue::native_window().as_ref() {
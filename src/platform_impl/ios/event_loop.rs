@@ -14,7 +14,10 @@ use crossbeam_channel::{self as channel, Receiver, Sender};
 use crate::{
   dpi::LogicalSize,
   event::Event,
-  event_loop::{ControlFlow, EventLoopClosed, EventLoopWindowTarget as RootEventLoopWindowTarget},
+  event_loop::{
+    ControlFlow, DeviceEventFilter, EventLoopClosed,
+    EventLoopWindowTarget as RootEventLoopWindowTarget,
+  },
   monitor::MonitorHandle as RootMonitorHandle,
   platform::ios::Idiom,
 };
@@ -61,12 +64,25 @@ impl<T: 'static> EventLoopWindowTarget<T> {
     unsafe { monitor::uiscreens() }
   }
 
+  pub fn set_device_event_filter(&self, _filter: DeviceEventFilter) {
+    warn!("`EventLoopWindowTarget::set_device_event_filter` is ignored on iOS")
+  }
+
   pub fn primary_monitor(&self) -> Option<RootMonitorHandle> {
     // guaranteed to be on main thread
     let monitor = unsafe { monitor::main_uiscreen() };
 
     Some(RootMonitorHandle { inner: monitor })
   }
+
+  #[cfg(feature = "testing")]
+  pub(crate) fn inject_window_event(
+    &self,
+    _window_id: crate::window::WindowId,
+    _event: crate::event::WindowEvent<'static>,
+  ) {
+    warn!("`EventLoopWindowTarget::__inject_window_event` is ignored on iOS")
+  }
 }
 
 pub struct EventLoop<T: 'static> {
@@ -98,6 +114,7 @@ impl<T: 'static> EventLoop<T> {
           receiver,
           sender_to_clone,
         },
+        pending_control_flow: Default::default(),
         _marker: PhantomData,
       },
     }
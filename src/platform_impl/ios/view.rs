@@ -274,6 +274,8 @@ unsafe fn get_view_class(root_view_class: &'static Class) -> &'static Class {
               id: touch_id,
               location: physical_location,
               force,
+              tilt: None,
+              twist: None,
               phase,
             }),
           }));
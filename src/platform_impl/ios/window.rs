@@ -12,8 +12,9 @@ use objc::runtime::{Class, Object, BOOL, NO, YES};
 use crate::{
   dpi::{self, LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize, Position, Size},
   error::{ExternalError, NotSupportedError, OsError as RootOsError},
-  event::{Event, WindowEvent},
-  icon::Icon,
+  event::{Event, MouseButtons, WindowEvent},
+  icon::{CustomCursor, Icon},
+  keyboard::KeyCode,
   monitor::MonitorHandle as RootMonitorHandle,
   platform::ios::{MonitorHandleExtIOS, ScreenEdge, ValidOrientations},
   platform_impl::platform::{
@@ -25,7 +26,10 @@ use crate::{
     },
     monitor, view, EventLoopWindowTarget, Menu, MonitorHandle,
   },
-  window::{CursorIcon, Fullscreen, UserAttentionType, WindowAttributes, WindowId as RootWindowId},
+  window::{
+    CursorIcon, Fullscreen, ProgressBarState, ResizeDirection, Theme, UserAttentionType,
+    WindowAttributes, WindowId as RootWindowId,
+  },
 };
 
 pub struct Inner {
@@ -50,6 +54,10 @@ impl Inner {
     debug!("`Window::set_title` is ignored on iOS")
   }
 
+  pub fn title(&self) -> String {
+    String::new()
+  }
+
   pub fn set_menu(&self, _menu: Option<Menu>) {
     debug!("`Window::set_menu` is ignored on iOS")
   }
@@ -157,6 +165,14 @@ impl Inner {
     warn!("not clear what `Window::set_inner_size` means on iOS");
   }
 
+  pub fn content_size(&self) -> PhysicalSize<u32> {
+    self.inner_size()
+  }
+
+  pub fn set_content_size(&self, _size: Size) {
+    warn!("`Window::set_content_size` is ignored on iOS")
+  }
+
   pub fn set_min_inner_size(&self, _dimensions: Option<Size>) {
     warn!("`Window::set_min_inner_size` is ignored on iOS")
   }
@@ -169,6 +185,10 @@ impl Inner {
     warn!("`Window::set_resizable` is ignored on iOS")
   }
 
+  pub fn set_resize_border_width(&self, _width: u32) {
+    warn!("`Window::set_resize_border_width` is ignored on iOS")
+  }
+
   pub fn scale_factor(&self) -> f64 {
     unsafe {
       let hidpi: CGFloat = msg_send![self.view, contentScaleFactor];
@@ -180,6 +200,14 @@ impl Inner {
     debug!("`Window::set_cursor_icon` ignored on iOS")
   }
 
+  pub fn set_custom_cursor(&self, _cursor: &CustomCursor) {
+    debug!("`Window::set_custom_cursor` is ignored on iOS")
+  }
+
+  pub fn set_busy_cursor(&self, _busy: bool) {
+    debug!("`Window::set_busy_cursor` ignored on iOS")
+  }
+
   pub fn set_cursor_position(&self, _position: Position) -> Result<(), ExternalError> {
     Err(ExternalError::NotSupported(NotSupportedError::new()))
   }
@@ -188,6 +216,10 @@ impl Inner {
     Err(ExternalError::NotSupported(NotSupportedError::new()))
   }
 
+  pub fn set_ignore_cursor_events(&self, _ignore: bool) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
   pub fn set_cursor_visible(&self, _visible: bool) {
     debug!("`Window::set_cursor_visible` is ignored on iOS")
   }
@@ -196,6 +228,10 @@ impl Inner {
     Err(ExternalError::NotSupported(NotSupportedError::new()))
   }
 
+  pub fn drag_resize_window(&self, _direction: ResizeDirection) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
   pub fn set_minimized(&self, _minimized: bool) {
     warn!("`Window::set_minimized` is ignored on iOS")
   }
@@ -288,6 +324,14 @@ impl Inner {
     warn!("`Window::set_always_on_top` is ignored on iOS")
   }
 
+  pub fn set_activate_on_click(&self, _activate_on_click: bool) {
+    warn!("`Window::set_activate_on_click` is ignored on iOS")
+  }
+
+  pub fn set_transparent(&self, _transparent: bool) {
+    warn!("`Window::set_transparent` is ignored on iOS")
+  }
+
   pub fn set_window_icon(&self, _icon: Option<Icon>) {
     warn!("`Window::set_window_icon` is ignored on iOS")
   }
@@ -296,10 +340,26 @@ impl Inner {
     warn!("`Window::set_ime_position` is ignored on iOS")
   }
 
+  pub fn set_ime_allowed(&self, _allowed: bool) {
+    warn!("`Window::set_ime_allowed` is ignored on iOS")
+  }
+
+  pub fn set_content_protected(&self, _protected: bool) {
+    warn!("`Window::set_content_protected` is ignored on iOS")
+  }
+
+  pub fn set_progress_bar(&self, _state: ProgressBarState) {
+    warn!("`Window::set_progress_bar` is ignored on iOS")
+  }
+
   pub fn request_user_attention(&self, _request_type: Option<UserAttentionType>) {
     warn!("`Window::request_user_attention` is ignored on iOS")
   }
 
+  pub fn set_badge_count(&self, _count: Option<i64>) {
+    warn!("`Window::set_badge_count` is ignored on iOS")
+  }
+
   pub fn hide_menu(&self) {
     warn!("`Window::hide_menu` is ignored on iOS")
   }
@@ -313,6 +373,21 @@ impl Inner {
     false
   }
 
+  pub fn theme(&self) -> Theme {
+    warn!("`Window::theme` is ignored on iOS");
+    Theme::Light
+  }
+
+  pub fn pressed_mouse_buttons(&self) -> MouseButtons {
+    warn!("`Window::pressed_mouse_buttons` is ignored on iOS");
+    MouseButtons::empty()
+  }
+
+  pub fn pressed_keys(&self) -> Vec<KeyCode> {
+    warn!("`Window::pressed_keys` is ignored on iOS");
+    Vec::new()
+  }
+
   // Allow directly accessing the current monitor internally without unwrapping.
   fn current_monitor_inner(&self) -> RootMonitorHandle {
     unsafe {
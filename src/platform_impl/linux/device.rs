@@ -0,0 +1,173 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+  os::raw::{c_int, c_uchar},
+  ptr, slice,
+};
+
+use glib::Sender;
+use x11_dl::{xinput2, xlib};
+
+use super::{keycode::keycode_from_scancode, DeviceId};
+use crate::event::{
+  DeviceEvent, DeviceId as RootDeviceId, ElementState, MouseScrollDelta, RawKeyEvent,
+};
+
+/// Spawns a thread listening to the XInput2 raw events of all devices and sending them as
+/// `DeviceEvent`s.
+///
+/// Raw events are delivered regardless of the focus and aren't affected by pointer
+/// acceleration. The thread exits when `device_tx` is disconnected.
+pub fn spawn(device_tx: Sender<(RootDeviceId, DeviceEvent)>) {
+  std::thread::spawn(move || unsafe {
+    let (xlib, xinput2) = match (xlib::Xlib::open(), xinput2::XInput2::open()) {
+      (Ok(xlib), Ok(xinput2)) => (xlib, xinput2),
+      _ => {
+        log::warn!("Failed to load Xlib or XInput2, device events are unavailable");
+        return;
+      }
+    };
+
+    let display = (xlib.XOpenDisplay)(ptr::null());
+    if display.is_null() {
+      return;
+    }
+
+    let mut opcode = 0;
+    let mut first_event = 0;
+    let mut first_error = 0;
+    if (xlib.XQueryExtension)(
+      display,
+      b"XInputExtension\0".as_ptr() as *const _,
+      &mut opcode,
+      &mut first_event,
+      &mut first_error,
+    ) == 0
+    {
+      log::warn!("XInput2 isn't supported by the X server, device events are unavailable");
+      (xlib.XCloseDisplay)(display);
+      return;
+    }
+
+    // The server only delivers XInput2 events to clients that announced the version they
+    // support.
+    let (mut major, mut minor) = (2, 0);
+    if (xinput2.XIQueryVersion)(display, &mut major, &mut minor) != xlib::Success as c_int {
+      log::warn!("XInput 2.0 isn't supported by the X server, device events are unavailable");
+      (xlib.XCloseDisplay)(display);
+      return;
+    }
+
+    let mut mask = [0 as c_uchar; ((xinput2::XI_LASTEVENT + 7) / 8) as usize];
+    for event in [
+      xinput2::XI_RawMotion,
+      xinput2::XI_RawButtonPress,
+      xinput2::XI_RawButtonRelease,
+      xinput2::XI_RawKeyPress,
+      xinput2::XI_RawKeyRelease,
+    ] {
+      xinput2::XISetMask(&mut mask, event);
+    }
+    let mut event_mask = xinput2::XIEventMask {
+      deviceid: xinput2::XIAllMasterDevices,
+      mask_len: mask.len() as c_int,
+      mask: mask.as_mut_ptr(),
+    };
+    (xinput2.XISelectEvents)(
+      display,
+      (xlib.XDefaultRootWindow)(display),
+      &mut event_mask,
+      1,
+    );
+
+    let mut event: xlib::XEvent = std::mem::zeroed();
+    loop {
+      (xlib.XNextEvent)(display, &mut event);
+
+      let mut cookie = event.generic_event_cookie;
+      if cookie.type_ != xlib::GenericEvent
+        || cookie.extension != opcode
+        || (xlib.XGetEventData)(display, &mut cookie) == 0
+      {
+        continue;
+      }
+
+      let raw = &*(cookie.data as *const xinput2::XIRawEvent);
+      // Events selected on the master devices carry the master as `deviceid`, the physical
+      // device they come from is `sourceid`.
+      let device_id = RootDeviceId(DeviceId(raw.sourceid as usize));
+      let sent = translate_raw_event(cookie.evtype, raw)
+        .into_iter()
+        .all(|event| device_tx.send((device_id, event)).is_ok());
+      (xlib.XFreeEventData)(display, &mut cookie);
+
+      if !sent {
+        break;
+      }
+    }
+
+    (xlib.XCloseDisplay)(display);
+  });
+}
+
+/// Translates an XInput2 raw event to the `DeviceEvent`s it results in.
+unsafe fn translate_raw_event(evtype: c_int, raw: &xinput2::XIRawEvent) -> Vec<DeviceEvent> {
+  let state = match evtype {
+    xinput2::XI_RawButtonPress | xinput2::XI_RawKeyPress => ElementState::Pressed,
+    _ => ElementState::Released,
+  };
+
+  match evtype {
+    xinput2::XI_RawMotion => {
+      let mut events = Vec::new();
+      let mut delta = (0.0, 0.0);
+      let mask = slice::from_raw_parts(raw.valuators.mask, raw.valuators.mask_len as usize);
+      // `raw_values` only holds the values of the valuators set in the mask.
+      let mut value = raw.raw_values;
+      for axis in 0..(mask.len() * 8) as i32 {
+        if !xinput2::XIMaskIsSet(mask, axis) {
+          continue;
+        }
+        match axis {
+          0 => delta.0 = *value,
+          1 => delta.1 = *value,
+          _ => (),
+        }
+        events.push(DeviceEvent::Motion {
+          axis: axis as u32,
+          value: *value,
+        });
+        value = value.offset(1);
+      }
+      if delta != (0.0, 0.0) {
+        events.push(DeviceEvent::MouseMotion { delta });
+      }
+      events
+    }
+    // Buttons 4 to 7 are the scroll wheel, reported once per notch on press.
+    xinput2::XI_RawButtonPress | xinput2::XI_RawButtonRelease if (4..=7).contains(&raw.detail) => {
+      if state == ElementState::Released {
+        return Vec::new();
+      }
+      let delta = match raw.detail {
+        4 => MouseScrollDelta::LineDelta(0.0, 1.0),
+        5 => MouseScrollDelta::LineDelta(0.0, -1.0),
+        6 => MouseScrollDelta::LineDelta(-1.0, 0.0),
+        _ => MouseScrollDelta::LineDelta(1.0, 0.0),
+      };
+      vec![DeviceEvent::MouseWheel { delta }]
+    }
+    xinput2::XI_RawButtonPress | xinput2::XI_RawButtonRelease => vec![DeviceEvent::Button {
+      button: raw.detail as u32,
+      state,
+    }],
+    xinput2::XI_RawKeyPress | xinput2::XI_RawKeyRelease => {
+      vec![DeviceEvent::Key(RawKeyEvent {
+        physical_key: keycode_from_scancode(raw.detail as u32),
+        state,
+      })]
+    }
+    _ => Vec::new(),
+  }
+}
@@ -2,8 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
-  cell::RefCell,
-  collections::{HashSet, VecDeque},
+  cell::{Cell, RefCell},
+  collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
   error::Error,
   process,
   rc::Rc,
@@ -18,20 +18,24 @@ use gtk::{prelude::*, AboutDialog, Inhibit};
 
 use crate::{
   accelerator::AcceleratorId,
-  dpi::{LogicalPosition, LogicalSize},
-  event::{ElementState, Event, MouseButton, StartCause, WindowEvent},
-  event_loop::{ControlFlow, EventLoopClosed, EventLoopWindowTarget as RootELW},
+  dpi::{LogicalPosition, LogicalSize, PhysicalSize},
+  event::{ElementState, Event, Ime, MouseButton, StartCause, WindowEvent},
+  event_loop::{ControlFlow, DeviceEventFilter, EventLoopClosed, EventLoopWindowTarget as RootELW},
+  icon::CustomCursorSource,
   keyboard::ModifiersState,
   menu::{MenuItem, MenuType},
   monitor::MonitorHandle as RootMonitorHandle,
-  platform_impl::platform::{window::hit_test, DEVICE_ID},
-  window::{CursorIcon, Fullscreen, WindowId as RootWindowId},
+  platform_impl::platform::{device, theme, window::hit_test_with_inset, DEVICE_ID},
+  window::{
+    CursorIcon, Fullscreen, ResizeDirection, Theme, WindowId as RootWindowId,
+    BORDERLESS_RESIZE_INSET,
+  },
 };
 
 use super::{
   keyboard,
   monitor::MonitorHandle,
-  window::{WindowId, WindowRequest},
+  window::{set_icons, PlatformIcon, WindowId, WindowRequest},
 };
 
 #[derive(Clone)]
@@ -42,8 +46,15 @@ pub struct EventLoopWindowTarget<T> {
   pub(crate) app: gtk::Application,
   /// Window Ids of the application
   pub(crate) windows: Rc<RefCell<HashSet<WindowId>>>,
+  /// Theme preferred by the desktop
+  pub(crate) theme: Rc<Cell<Theme>>,
+  /// Filter applied to the device events
+  pub(crate) device_event_filter: Rc<Cell<DeviceEventFilter>>,
   /// Window requests sender
   pub(crate) window_requests_tx: glib::Sender<(WindowId, WindowRequest)>,
+  /// Event sender, used to inject events in tests
+  #[cfg(feature = "testing")]
+  pub(crate) event_tx: crossbeam_channel::Sender<Event<'static, T>>,
   _marker: std::marker::PhantomData<T>,
 }
 
@@ -62,6 +73,11 @@ impl<T> EventLoopWindowTarget<T> {
     handles
   }
 
+  #[inline]
+  pub fn set_device_event_filter(&self, filter: DeviceEventFilter) {
+    self.device_event_filter.set(filter);
+  }
+
   #[inline]
   pub fn primary_monitor(&self) -> Option<RootMonitorHandle> {
     let screen = self.display.default_screen();
@@ -70,6 +86,16 @@ impl<T> EventLoopWindowTarget<T> {
     let handle = MonitorHandle::new(&self.display, number);
     Some(RootMonitorHandle { inner: handle })
   }
+
+  #[cfg(feature = "testing")]
+  pub(crate) fn inject_window_event(&self, window_id: RootWindowId, event: WindowEvent<'static>) {
+    if let Err(e) = self.event_tx.send(Event::WindowEvent { window_id, event }) {
+      log::warn!(
+        "Failed to send injected window event to event channel: {}",
+        e
+      );
+    }
+  }
 }
 
 pub struct EventLoop<T: 'static> {
@@ -81,6 +107,10 @@ pub struct EventLoop<T: 'static> {
   events: crossbeam_channel::Receiver<Event<'static, T>>,
   /// Draw queue of EventLoop
   draws: crossbeam_channel::Receiver<WindowId>,
+  /// Scale factor changes of the windows, with their inner size at the new scale factor.
+  /// `ScaleFactorChanged` borrows the new size mutably, so it can't go through the event queue.
+  /// They are dispatched ahead of `events`, as documented on `ScaleFactorChanged`.
+  scale_factor_changes: crossbeam_channel::Receiver<(WindowId, f64, PhysicalSize<u32>)>,
 }
 
 impl<T: 'static> EventLoop<T> {
@@ -106,6 +136,7 @@ impl<T: 'static> EventLoop<T> {
     // Send StartCause::Init event
     let (event_tx, event_rx) = crossbeam_channel::unbounded();
     let (draw_tx, draw_rx) = crossbeam_channel::unbounded();
+    let (scale_factor_tx, scale_factor_rx) = crossbeam_channel::unbounded();
     let event_tx_ = event_tx.clone();
     app.connect_activate(move |_| {
       if let Err(e) = event_tx_.send(Event::NewEvents(StartCause::Init)) {
@@ -118,11 +149,84 @@ impl<T: 'static> EventLoop<T> {
     let window_requests_tx_ = window_requests_tx.clone();
     let display = gdk::Display::default()
       .expect("GdkDisplay not found. This usually means `gkt_init` hasn't called yet.");
+
+    // The keymap is replaced when the layouts change, while switching between the layouts of a
+    // keymap only changes its direction if they are written in different directions.
+    if let Some(keymap) = gdk::Keymap::for_display(&display) {
+      let layout_changed = {
+        let event_tx = event_tx.clone();
+        move |_: &gdk::Keymap| {
+          if let Err(e) = event_tx.send(Event::KeyboardLayoutChanged) {
+            log::warn!(
+              "Failed to send keyboard layout changed event to event channel: {}",
+              e
+            );
+          }
+        }
+      };
+      keymap.connect_keys_changed(layout_changed.clone());
+      keymap.connect_direction_changed(layout_changed);
+    }
+
+    // The monitors known so far, as the number of a removed monitor can't be queried anymore.
+    let monitors = Rc::new(RefCell::new(display_monitors(&display)));
+    let event_tx_ = event_tx.clone();
+    let monitors_ = monitors.clone();
+    display.connect_monitor_added(move |display, monitor| {
+      let known = display_monitors(display);
+      let number = known.iter().position(|m| m == monitor).unwrap_or_default();
+      *monitors_.borrow_mut() = known;
+      let handle = MonitorHandle::from_monitor(monitor.clone(), number as i32);
+      if let Err(e) = event_tx_.send(Event::MonitorConnected(RootMonitorHandle { inner: handle })) {
+        log::warn!(
+          "Failed to send monitor connected event to event channel: {}",
+          e
+        );
+      }
+    });
+    let event_tx_ = event_tx.clone();
+    display.connect_monitor_removed(move |_, monitor| {
+      let number = monitors
+        .borrow()
+        .iter()
+        .position(|m| m == monitor)
+        .unwrap_or_default();
+      monitors.borrow_mut().retain(|m| m != monitor);
+      let handle = MonitorHandle::from_monitor(monitor.clone(), number as i32);
+      if let Err(e) = event_tx_.send(Event::MonitorDisconnected(RootMonitorHandle {
+        inner: handle,
+      })) {
+        log::warn!(
+          "Failed to send monitor disconnected event to event channel: {}",
+          e
+        );
+      }
+    });
+
+    // Theme changes apply to every window.
+    let windows = Rc::new(RefCell::new(HashSet::new()));
+    let windows_ = windows.clone();
+    let event_tx_ = event_tx.clone();
+    let theme = theme::watch(move |theme| {
+      for id in windows_.borrow().iter() {
+        if let Err(e) = event_tx_.send(Event::WindowEvent {
+          window_id: RootWindowId(*id),
+          event: WindowEvent::ThemeChanged(theme),
+        }) {
+          log::warn!("Failed to send theme changed event to event channel: {}", e);
+        }
+      }
+    });
+
     let window_target = EventLoopWindowTarget {
       display,
       app,
-      windows: Rc::new(RefCell::new(HashSet::new())),
+      windows,
+      theme,
+      device_event_filter: Default::default(),
       window_requests_tx,
+      #[cfg(feature = "testing")]
+      event_tx: event_tx.clone(),
       _marker: std::marker::PhantomData,
     };
 
@@ -136,9 +240,51 @@ impl<T: 'static> EventLoop<T> {
       Continue(true)
     });
 
+    // Create device event channel, fed with the XInput2 raw events on X11
+    if window_target.display.type_().name() == "GdkX11Display" {
+      let (device_tx, device_rx) = glib::MainContext::channel(Priority::default());
+      let event_tx_ = event_tx.clone();
+      let app_ = window_target.app.clone();
+      let filter = window_target.device_event_filter.clone();
+      device_rx.attach(Some(&context), move |(device_id, event)| {
+        let emit = match filter.get() {
+          DeviceEventFilter::Always => true,
+          DeviceEventFilter::WhenFocused => app_.windows().iter().any(|w| w.is_active()),
+          DeviceEventFilter::Never => false,
+        };
+        if !emit {
+          return Continue(true);
+        }
+        if let Err(e) = event_tx_.send(Event::DeviceEvent { device_id, event }) {
+          log::warn!("Failed to send device event to event channel: {}", e);
+        }
+        Continue(true)
+      });
+      device::spawn(device_tx);
+    }
+
+    // The input method contexts of the windows, with whether they are allowed, to place their
+    // candidate windows and turn them on and off.
+    let ime_contexts = Rc::new(RefCell::new(HashMap::new()));
+    // The resize border widths set by `Window::set_resize_border_width`, in logical pixels.
+    let resize_border_widths: Rc<RefCell<HashMap<WindowId, i32>>> =
+      Rc::new(RefCell::new(HashMap::new()));
+    // The windows showing the busy cursor set by `Window::set_busy_cursor`, with the cursor to
+    // restore once they aren't busy anymore.
+    let busy_cursors: Rc<RefCell<HashMap<WindowId, Option<Cursor>>>> =
+      Rc::new(RefCell::new(HashMap::new()));
+
     // Window Request
     window_requests_rx.attach(Some(&context), move |(id, request)| {
       if let Some(window) = app_.window_by_id(id.0) {
+        // The busy cursor takes precedence, a cursor set while busy is applied afterwards.
+        let set_cursor = |gdk_window: &gdk::Window, cursor: Option<Cursor>| match busy_cursors
+          .borrow_mut()
+          .get_mut(&id)
+        {
+          Some(restored_cursor) => *restored_cursor = cursor,
+          None => gdk_window.set_cursor(cursor.as_ref()),
+        };
         match request {
           WindowRequest::Title(title) => window.set_title(&title),
           WindowRequest::Position((x, y)) => window.move_(x, y),
@@ -219,6 +365,26 @@ impl<T: 'static> EventLoop<T> {
               window.begin_move_drag(1, x, y, 0);
             }
           }
+          WindowRequest::DragResizeWindow(direction) => {
+            let edge = match direction {
+              ResizeDirection::North => WindowEdge::North,
+              ResizeDirection::NorthEast => WindowEdge::NorthEast,
+              ResizeDirection::East => WindowEdge::East,
+              ResizeDirection::SouthEast => WindowEdge::SouthEast,
+              ResizeDirection::South => WindowEdge::South,
+              ResizeDirection::SouthWest => WindowEdge::SouthWest,
+              ResizeDirection::West => WindowEdge::West,
+              ResizeDirection::NorthWest => WindowEdge::NorthWest,
+            };
+            let display = window.display();
+            if let Some(cursor) = display
+              .default_seat()
+              .and_then(|device_manager| device_manager.pointer())
+            {
+              let (_, x, y) = cursor.position();
+              window.begin_resize_drag(edge, 1, x, y, 0);
+            }
+          }
           WindowRequest::Fullscreen(fullscreen) => match fullscreen {
             Some(f) => {
               if let Fullscreen::Borderless(Some(monitor)) = f {
@@ -231,11 +397,12 @@ impl<T: 'static> EventLoop<T> {
           },
           WindowRequest::Decorations(decorations) => window.set_decorated(decorations),
           WindowRequest::AlwaysOnTop(always_on_top) => window.set_keep_above(always_on_top),
-          WindowRequest::WindowIcon(window_icon) => {
-            if let Some(icon) = window_icon {
-              window.set_icon(Some(&icon.inner.into()));
-            }
+          // Only a hint to the window manager, which doesn't give the focus to the window when
+          // it's clicked.
+          WindowRequest::ActivateOnClick(activate_on_click) => {
+            window.set_accept_focus(activate_on_click)
           }
+          WindowRequest::Icons(icons) => set_icons(&window, icons),
           WindowRequest::UserAttention(request_type) => {
             window.set_urgency_hint(request_type.is_some())
           }
@@ -243,58 +410,126 @@ impl<T: 'static> EventLoop<T> {
             window.set_skip_taskbar_hint(skip);
             window.set_skip_pager_hint(skip)
           }
+          WindowRequest::BusyCursor(busy) => {
+            if let Some(gdk_window) = window.window() {
+              let mut busy_cursors = busy_cursors.borrow_mut();
+              if busy {
+                if let Entry::Vacant(entry) = busy_cursors.entry(id) {
+                  // The current cursor is kept to restore it afterwards.
+                  entry.insert(gdk_window.cursor());
+                  gdk_window.set_cursor(Cursor::from_name(&window.display(), "wait").as_ref());
+                }
+              } else if let Some(cursor) = busy_cursors.remove(&id) {
+                gdk_window.set_cursor(cursor.as_ref());
+              }
+            }
+          }
           WindowRequest::CursorIcon(cursor) => {
             if let Some(gdk_window) = window.window() {
               let display = window.display();
-              match cursor {
-                Some(cr) => gdk_window.set_cursor(
-                  Cursor::from_name(
-                    &display,
-                    match cr {
-                      CursorIcon::Crosshair => "crosshair",
-                      CursorIcon::Hand => "pointer",
-                      CursorIcon::Arrow => "crosshair",
-                      CursorIcon::Move => "move",
-                      CursorIcon::Text => "text",
-                      CursorIcon::Wait => "wait",
-                      CursorIcon::Help => "help",
-                      CursorIcon::Progress => "progress",
-                      CursorIcon::NotAllowed => "not-allowed",
-                      CursorIcon::ContextMenu => "context-menu",
-                      CursorIcon::Cell => "cell",
-                      CursorIcon::VerticalText => "vertical-text",
-                      CursorIcon::Alias => "alias",
-                      CursorIcon::Copy => "copy",
-                      CursorIcon::NoDrop => "no-drop",
-                      CursorIcon::Grab => "grab",
-                      CursorIcon::Grabbing => "grabbing",
-                      CursorIcon::AllScroll => "all-scroll",
-                      CursorIcon::ZoomIn => "zoom-in",
-                      CursorIcon::ZoomOut => "zoom-out",
-                      CursorIcon::EResize => "e-resize",
-                      CursorIcon::NResize => "n-resize",
-                      CursorIcon::NeResize => "ne-resize",
-                      CursorIcon::NwResize => "nw-resize",
-                      CursorIcon::SResize => "s-resize",
-                      CursorIcon::SeResize => "se-resize",
-                      CursorIcon::SwResize => "sw-resize",
-                      CursorIcon::WResize => "w-resize",
-                      CursorIcon::EwResize => "ew-resize",
-                      CursorIcon::NsResize => "ns-resize",
-                      CursorIcon::NeswResize => "nesw-resize",
-                      CursorIcon::NwseResize => "nwse-resize",
-                      CursorIcon::ColResize => "col-resize",
-                      CursorIcon::RowResize => "row-resize",
-                      CursorIcon::Default => "default",
-                    },
-                  )
-                  .as_ref(),
+              let cursor = match cursor {
+                Some(cr) => Cursor::from_name(
+                  &display,
+                  match cr {
+                    CursorIcon::Crosshair => "crosshair",
+                    CursorIcon::Hand => "pointer",
+                    CursorIcon::Arrow => "default",
+                    CursorIcon::Move => "move",
+                    CursorIcon::Text => "text",
+                    CursorIcon::Wait => "wait",
+                    CursorIcon::Help => "help",
+                    CursorIcon::Progress => "progress",
+                    CursorIcon::NotAllowed => "not-allowed",
+                    CursorIcon::ContextMenu => "context-menu",
+                    CursorIcon::Cell => "cell",
+                    CursorIcon::VerticalText => "vertical-text",
+                    CursorIcon::Alias => "alias",
+                    CursorIcon::Copy => "copy",
+                    CursorIcon::NoDrop => "no-drop",
+                    CursorIcon::Grab => "grab",
+                    CursorIcon::Grabbing => "grabbing",
+                    CursorIcon::AllScroll => "all-scroll",
+                    CursorIcon::ZoomIn => "zoom-in",
+                    CursorIcon::ZoomOut => "zoom-out",
+                    CursorIcon::EResize => "e-resize",
+                    CursorIcon::NResize => "n-resize",
+                    CursorIcon::NeResize => "ne-resize",
+                    CursorIcon::NwResize => "nw-resize",
+                    CursorIcon::SResize => "s-resize",
+                    CursorIcon::SeResize => "se-resize",
+                    CursorIcon::SwResize => "sw-resize",
+                    CursorIcon::WResize => "w-resize",
+                    CursorIcon::EwResize => "ew-resize",
+                    CursorIcon::NsResize => "ns-resize",
+                    CursorIcon::NeswResize => "nesw-resize",
+                    CursorIcon::NwseResize => "nwse-resize",
+                    CursorIcon::ColResize => "col-resize",
+                    CursorIcon::RowResize => "row-resize",
+                    CursorIcon::Default => "default",
+                    CursorIcon::None => "none",
+                  },
                 ),
-                None => gdk_window
-                  .set_cursor(Cursor::for_display(&display, CursorType::BlankCursor).as_ref()),
+                None => Cursor::for_display(&display, CursorType::BlankCursor),
+              };
+              set_cursor(&gdk_window, cursor);
+            };
+          }
+          WindowRequest::CustomCursor(cursor) => {
+            if let Some(gdk_window) = window.window() {
+              let CustomCursorSource::Rgba {
+                image,
+                hotspot_x,
+                hotspot_y,
+              } = cursor.inner;
+              if let Ok(icon) = PlatformIcon::from_rgba(image.rgba, image.width, image.height) {
+                let cursor = Cursor::from_pixbuf(
+                  &window.display(),
+                  &icon.into(),
+                  hotspot_x as i32,
+                  hotspot_y as i32,
+                );
+                set_cursor(&gdk_window, Some(cursor));
               }
+            }
+          }
+          WindowRequest::CursorIgnoreEvents(ignore) => {
+            // An empty input shape lets the input go through the window, no shape restores the
+            // whole window as the input shape.
+            if ignore {
+              let empty_region = cairo::Region::create();
+              window.input_shape_combine_region(Some(&empty_region));
+            } else {
+              window.input_shape_combine_region(None);
             };
           }
+          WindowRequest::ResizeBorderWidth(width) => {
+            resize_border_widths.borrow_mut().insert(id, width);
+          }
+          WindowRequest::ImePosition((x, y)) => {
+            if let Some((ime, _)) = ime_contexts.borrow().get(&id) {
+              ime.set_cursor_location(&gdk::Rectangle::new(x, y, 0, 0));
+            }
+          }
+          WindowRequest::ImeAllowed(allowed) => {
+            if let Some((ime, ime_allowed)) = ime_contexts.borrow().get(&id) {
+              if ime_allowed.replace(allowed) != allowed {
+                let event = if allowed {
+                  ime.focus_in();
+                  Ime::Enabled
+                } else {
+                  ime.focus_out();
+                  ime.reset();
+                  Ime::Disabled
+                };
+                if let Err(e) = event_tx.send(Event::WindowEvent {
+                  window_id: RootWindowId(id),
+                  event: WindowEvent::Ime(event),
+                }) {
+                  log::warn!("Failed to send IME event to event channel: {}", e);
+                }
+              }
+            }
+          }
           WindowRequest::WireUpEvents => {
             window.add_events(
               EventMask::POINTER_MOTION_MASK
@@ -306,11 +541,24 @@ impl<T: 'static> EventLoop<T> {
             );
 
             // Resizing `decorations: false` aka borderless
-            window.connect_motion_notify_event(|window, event| {
-              if !window.is_decorated() && window.is_resizable() {
+            let resize_border_width = {
+              let resize_border_widths = resize_border_widths.clone();
+              move || {
+                resize_border_widths
+                  .borrow()
+                  .get(&id)
+                  .copied()
+                  .unwrap_or(BORDERLESS_RESIZE_INSET)
+              }
+            };
+            let resize_border_width_ = resize_border_width.clone();
+            let busy_cursors_ = busy_cursors.clone();
+            window.connect_motion_notify_event(move |window, event| {
+              let busy = busy_cursors_.borrow().contains_key(&id);
+              if !busy && !window.is_decorated() && window.is_resizable() {
                 if let Some(window) = window.window() {
                   let (cx, cy) = event.root();
-                  let edge = hit_test(&window, cx, cy);
+                  let edge = hit_test_with_inset(&window, cx, cy, resize_border_width_());
                   window.set_cursor(
                     Cursor::from_name(
                       &window.display(),
@@ -332,11 +580,12 @@ impl<T: 'static> EventLoop<T> {
               }
               Inhibit(false)
             });
-            window.connect_button_press_event(|window, event| {
+            let resize_border_width_ = resize_border_width.clone();
+            window.connect_button_press_event(move |window, event| {
               if !window.is_decorated() && window.is_resizable() && event.button() == 1 {
                 if let Some(window) = window.window() {
                   let (cx, cy) = event.root();
-                  let result = hit_test(&window, cx, cy);
+                  let result = hit_test_with_inset(&window, cx, cy, resize_border_width_());
 
                   // Ignore the `__Unknown` variant so the window receives the click correctly if it is not on the edges.
                   match result {
@@ -351,12 +600,12 @@ impl<T: 'static> EventLoop<T> {
 
               Inhibit(false)
             });
-            window.connect_touch_event(|window, event| {
+            window.connect_touch_event(move |window, event| {
               if !window.is_decorated() && window.is_resizable() {
                 if let Some(window) = window.window() {
                   if let Some((cx, cy)) = event.root_coords() {
                     if let Some(device) = event.device() {
-                      let result = hit_test(&window, cx, cy);
+                      let result = hit_test_with_inset(&window, cx, cy, resize_border_width());
 
                       // Ignore the `__Unknown` variant so the window receives the click correctly if it is not on the edges.
                       match result {
@@ -389,6 +638,22 @@ impl<T: 'static> EventLoop<T> {
               Inhibit(true)
             });
 
+            let tx_clone = scale_factor_tx.clone();
+            window.connect_scale_factor_notify(move |window| {
+              let scale_factor = window.scale_factor() as f64;
+              let (w, h) = window.size();
+              if let Err(e) = tx_clone.send((
+                id,
+                scale_factor,
+                LogicalSize::new(w, h).to_physical(scale_factor),
+              )) {
+                log::warn!(
+                  "Failed to send scale factor change to scale factor channel: {}",
+                  e
+                );
+              }
+            });
+
             let tx_clone = event_tx.clone();
             window.connect_configure_event(move |window, event| {
               let scale_factor = window.scale_factor();
@@ -419,7 +684,9 @@ impl<T: 'static> EventLoop<T> {
             });
 
             let tx_clone = event_tx.clone();
-            window.connect_focus_in_event(move |_, _| {
+            window.connect_focus_in_event(move |window, _| {
+              // The urgency set by `request_user_attention` is kept until it is cleared.
+              window.set_urgency_hint(false);
               if let Err(e) = tx_clone.send(Event::WindowEvent {
                 window_id: RootWindowId(id),
                 event: WindowEvent::Focused(true),
@@ -447,7 +714,13 @@ impl<T: 'static> EventLoop<T> {
             });
 
             let tx_clone = event_tx.clone();
+            let ime_contexts_ = ime_contexts.clone();
+            let resize_border_widths_ = resize_border_widths.clone();
+            let busy_cursors_ = busy_cursors.clone();
             window.connect_destroy_event(move |_, _| {
+              ime_contexts_.borrow_mut().remove(&id);
+              resize_border_widths_.borrow_mut().remove(&id);
+              busy_cursors_.borrow_mut().remove(&id);
               if let Err(e) = tx_clone.send(Event::WindowEvent {
                 window_id: RootWindowId(id),
                 event: WindowEvent::Destroyed,
@@ -509,9 +782,19 @@ impl<T: 'static> EventLoop<T> {
               Inhibit(false)
             });
 
+            let last_click = Rc::new(Cell::new(None));
+
             let tx_clone = event_tx.clone();
+            let last_click_clone = last_click.clone();
             window.connect_button_press_event(move |_, event| {
+              // GDK follows the second and third press of a multi-click with an extra
+              // `2BUTTON_PRESS`/`3BUTTON_PRESS` event, which would report the press twice.
+              if event.event_type() != gdk::EventType::ButtonPress {
+                return Inhibit(false);
+              }
               let button = event.button();
+              let click_count =
+                register_press(&last_click_clone, button, event.time(), event.position());
               if let Err(e) = tx_clone.send(Event::WindowEvent {
                 window_id: RootWindowId(id),
                 event: WindowEvent::MouseInput {
@@ -522,6 +805,7 @@ impl<T: 'static> EventLoop<T> {
                     _ => MouseButton::Other(button as u16),
                   },
                   state: ElementState::Pressed,
+                  click_count,
                   device_id: DEVICE_ID,
                   // this field is depracted so it is fine to pass empty state
                   modifiers: ModifiersState::empty(),
@@ -538,6 +822,10 @@ impl<T: 'static> EventLoop<T> {
             let tx_clone = event_tx.clone();
             window.connect_button_release_event(move |_, event| {
               let button = event.button();
+              let click_count = match last_click.get() {
+                Some(click) if click.button == button => click.count,
+                _ => 1,
+              };
               if let Err(e) = tx_clone.send(Event::WindowEvent {
                 window_id: RootWindowId(id),
                 event: WindowEvent::MouseInput {
@@ -548,6 +836,7 @@ impl<T: 'static> EventLoop<T> {
                     _ => MouseButton::Other(button as u16),
                   },
                   state: ElementState::Released,
+                  click_count,
                   device_id: DEVICE_ID,
                   // this field is depracted so it is fine to pass empty state
                   modifiers: ModifiersState::empty(),
@@ -605,14 +894,19 @@ impl<T: 'static> EventLoop<T> {
             });
 
             let tx_clone = event_tx.clone();
-            // TODO Add actual IME from system
-            let ime = gtk::IMContextSimple::default();
+            // Delegates to the input method of the system, e.g. IBus or Fcitx, so that
+            // `set_ime_position` places its candidate window.
+            let ime = gtk::IMMulticontext::new();
             ime.set_client_window(window.window().as_ref());
             ime.focus_in();
+            let ime_allowed = Rc::new(Cell::new(true));
+            ime_contexts
+              .borrow_mut()
+              .insert(id, (ime.clone(), ime_allowed.clone()));
             ime.connect_commit(move |_, s| {
               if let Err(e) = tx_clone.send(Event::WindowEvent {
                 window_id: RootWindowId(id),
-                event: WindowEvent::ReceivedImeText(s.to_string()),
+                event: WindowEvent::Ime(Ime::Commit(s.to_string())),
               }) {
                 log::warn!(
                   "Failed to send received IME text event to event channel: {}",
@@ -624,7 +918,9 @@ impl<T: 'static> EventLoop<T> {
             let handler = keyboard_handler.clone();
             window.connect_key_press_event(move |_, event_key| {
               handler(event_key.to_owned(), ElementState::Pressed);
-              ime.filter_keypress(event_key);
+              if ime_allowed.get() {
+                ime.filter_keypress(event_key);
+              }
 
               Inhibit(false)
             });
@@ -638,6 +934,18 @@ impl<T: 'static> EventLoop<T> {
             let tx_clone = event_tx.clone();
             window.connect_window_state_event(move |window, event| {
               let state = event.changed_mask();
+              if state.contains(WindowState::ICONIFIED) {
+                let minimized = event.new_window_state().contains(WindowState::ICONIFIED);
+                if let Err(e) = tx_clone.send(Event::WindowEvent {
+                  window_id: RootWindowId(id),
+                  event: WindowEvent::Minimized(minimized),
+                }) {
+                  log::warn!(
+                    "Failed to send window minimized event to event channel: {}",
+                    e
+                  );
+                }
+              }
               if state.contains(WindowState::ICONIFIED) || state.contains(WindowState::MAXIMIZED) {
                 let scale_factor = window.scale_factor();
 
@@ -751,11 +1059,13 @@ impl<T: 'static> EventLoop<T> {
     let event_loop = Self {
       window_target: RootELW {
         p: window_target,
+        pending_control_flow: Default::default(),
         _marker: std::marker::PhantomData,
       },
       user_event_tx,
       events: event_rx,
       draws: draw_rx,
+      scale_factor_changes: scale_factor_rx,
     };
 
     Ok(event_loop)
@@ -816,10 +1126,13 @@ impl<T: 'static> EventLoop<T> {
         let window_target = &self.window_target;
         let events = &self.events;
         let draws = &self.draws;
+        let scale_factor_changes = &self.scale_factor_changes;
 
         window_target.p.app.activate();
 
         let mut state = EventState::NewStart;
+        // The size of the last `SurfaceResized` event of each window.
+        let mut surface_sizes = HashMap::new();
         let exit_code = loop {
           let mut blocking = false;
           match state {
@@ -829,7 +1142,7 @@ impl<T: 'static> EventLoop<T> {
                 break code;
               }
               ControlFlow::Wait => {
-                if !events.is_empty() || !draws.is_empty() {
+                if !events.is_empty() || !draws.is_empty() || !scale_factor_changes.is_empty() {
                   callback(
                     Event::NewEvents(StartCause::WaitCancelled {
                       start: Instant::now(),
@@ -855,7 +1168,7 @@ impl<T: 'static> EventLoop<T> {
                     &mut control_flow,
                   );
                   state = EventState::EventQueue;
-                } else if !events.is_empty() {
+                } else if !events.is_empty() || !scale_factor_changes.is_empty() {
                   callback(
                     Event::NewEvents(StartCause::WaitCancelled {
                       start,
@@ -883,19 +1196,82 @@ impl<T: 'static> EventLoop<T> {
                 callback(Event::LoopDestroyed, window_target, &mut control_flow);
                 break (code);
               }
-              _ => match events.try_recv() {
-                Ok(event) => match event {
-                  Event::LoopDestroyed => control_flow = ControlFlow::ExitWithCode(1),
-                  _ => callback(event, window_target, &mut control_flow),
-                },
-                Err(_) => {
-                  callback(Event::MainEventsCleared, window_target, &mut control_flow);
-                  if draws.is_empty() {
-                    state = EventState::NewStart;
-                  } else {
-                    state = EventState::DrawQueue;
+              _ => match scale_factor_changes.try_recv() {
+                Ok((id, scale_factor, size)) => {
+                  let mut new_inner_size = size;
+                  callback(
+                    Event::WindowEvent {
+                      window_id: RootWindowId(id),
+                      event: WindowEvent::ScaleFactorChanged {
+                        scale_factor,
+                        new_inner_size: &mut new_inner_size,
+                      },
+                    },
+                    window_target,
+                    &mut control_flow,
+                  );
+                  if new_inner_size != size {
+                    if let Some(window) = window_target.p.app.window_by_id(id.0) {
+                      let (w, h): (i32, i32) =
+                        new_inner_size.to_logical::<i32>(scale_factor).into();
+                      window.resize(w, h);
+                    }
                   }
                 }
+                Err(_) => match events.try_recv() {
+                  Ok(event) => match event {
+                    Event::LoopDestroyed => control_flow = ControlFlow::ExitWithCode(1),
+                    Event::WindowEvent {
+                      window_id,
+                      event: WindowEvent::Resized(size),
+                    } => {
+                      callback(
+                        Event::WindowEvent {
+                          window_id,
+                          event: WindowEvent::Resized(size),
+                        },
+                        window_target,
+                        &mut control_flow,
+                      );
+                      if size.width != 0
+                        && size.height != 0
+                        && surface_sizes.insert(window_id, size) != Some(size)
+                      {
+                        callback(
+                          Event::WindowEvent {
+                            window_id,
+                            event: WindowEvent::SurfaceResized(size),
+                          },
+                          window_target,
+                          &mut control_flow,
+                        );
+                      }
+                    }
+                    Event::WindowEvent {
+                      window_id,
+                      event: WindowEvent::Destroyed,
+                    } => {
+                      surface_sizes.remove(&window_id);
+                      callback(
+                        Event::WindowEvent {
+                          window_id,
+                          event: WindowEvent::Destroyed,
+                        },
+                        window_target,
+                        &mut control_flow,
+                      );
+                    }
+                    _ => callback(event, window_target, &mut control_flow),
+                  },
+                  Err(_) => {
+                    callback(Event::MainEventsCleared, window_target, &mut control_flow);
+                    if draws.is_empty() {
+                      state = EventState::NewStart;
+                    } else {
+                      state = EventState::DrawQueue;
+                    }
+                  }
+                },
               },
             },
             EventState::DrawQueue => match control_flow {
@@ -964,6 +1340,55 @@ impl<T: 'static> EventLoopProxy<T> {
   }
 }
 
+/// The last button press of a window, used to count consecutive clicks.
+#[derive(Clone, Copy)]
+struct Click {
+  button: u32,
+  time: u32,
+  position: (f64, f64),
+  count: u32,
+}
+
+/// Records a press of `button` and returns the number of consecutive clicks it is part of,
+/// according to the GTK double-click time and distance.
+fn register_press(
+  last_click: &Cell<Option<Click>>,
+  button: u32,
+  time: u32,
+  position: (f64, f64),
+) -> u32 {
+  let (max_time, max_distance) = gtk::Settings::default().map_or((400, 5.), |settings| {
+    (
+      settings.gtk_double_click_time() as u32,
+      f64::from(settings.gtk_double_click_distance()),
+    )
+  });
+  let count = match last_click.get() {
+    Some(click)
+      if click.button == button
+        && time.wrapping_sub(click.time) <= max_time
+        && (position.0 - click.position.0).abs() <= max_distance
+        && (position.1 - click.position.1).abs() <= max_distance =>
+    {
+      click.count + 1
+    }
+    _ => 1,
+  };
+  last_click.set(Some(Click {
+    button,
+    time,
+    position,
+    count,
+  }));
+  count
+}
+
+fn display_monitors(display: &gdk::Display) -> Vec<gdk::Monitor> {
+  (0..display.n_monitors())
+    .filter_map(|i| display.monitor(i))
+    .collect()
+}
+
 fn assert_is_main_thread(suggested_method: &str) {
   assert!(
     is_main_thread(),
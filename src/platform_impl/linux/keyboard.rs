@@ -217,6 +217,7 @@ pub(crate) fn make_key_event(
       platform_specific: KeyEventExtra {
         text_with_all_modifiers,
         key_without_modifiers,
+        scancode: scancode as u32,
       },
     });
   } else {
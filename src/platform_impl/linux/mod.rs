@@ -10,6 +10,7 @@
 ))]
 
 mod clipboard;
+mod device;
 mod event_loop;
 mod global_shortcut;
 mod keyboard;
@@ -18,6 +19,7 @@ mod menu;
 mod monitor;
 #[cfg(any(feature = "tray", feature = "ayatana"))]
 mod system_tray;
+mod theme;
 mod window;
 
 #[cfg(any(feature = "tray", feature = "ayatana"))]
@@ -32,17 +34,19 @@ pub use event_loop::{EventLoop, EventLoopProxy, EventLoopWindowTarget};
 pub use monitor::{MonitorHandle, VideoMode};
 pub use window::{hit_test, PlatformIcon, Window, WindowId};
 
-use crate::{event::DeviceId as RootDeviceId, keyboard::Key};
+use crate::{event::DeviceId as RootDeviceId, icon::Icon, keyboard::Key};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct KeyEventExtra {
   pub text_with_all_modifiers: Option<&'static str>,
   pub key_without_modifiers: Key<'static>,
+  pub scancode: u32,
 }
 
 #[derive(Clone, Default)]
 pub struct PlatformSpecificWindowBuilderAttributes {
   pub skip_taskbar: bool,
+  pub taskbar_icon: Option<Icon>,
 }
 
 unsafe impl Send for PlatformSpecificWindowBuilderAttributes {}
@@ -21,6 +21,10 @@ impl MonitorHandle {
     Self { monitor, number }
   }
 
+  pub(crate) fn from_monitor(monitor: gdk::Monitor, number: i32) -> Self {
+    Self { monitor, number }
+  }
+
   #[inline]
   pub fn name(&self) -> Option<String> {
     self.monitor.model().map(|s| s.as_str().to_string())
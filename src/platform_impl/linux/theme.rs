@@ -0,0 +1,127 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{cell::Cell, rc::Rc};
+
+use gio::{prelude::*, BusType, Cancellable, DBusCallFlags, DBusProxy, DBusProxyFlags};
+use glib::{ToVariant, Variant};
+use gtk::prelude::*;
+
+use crate::window::Theme;
+
+const PORTAL_NAMESPACE: &str = "org.freedesktop.appearance";
+const PORTAL_KEY: &str = "color-scheme";
+/// How long the initial read of the portal setting may block the event loop creation, the
+/// portal may have to be started first. Later changes come from its `SettingChanged` signal.
+const PORTAL_READ_TIMEOUT_MS: i32 = 200;
+
+/// Tracks the theme preferred by the desktop, from the `color-scheme` setting of the desktop
+/// portal when there is one, and from the GTK settings otherwise.
+///
+/// Returns the current theme, which is kept up to date, and calls `on_change` with the new
+/// theme whenever it changes.
+pub(crate) fn watch<F: Fn(Theme) + 'static>(on_change: F) -> Rc<Cell<Theme>> {
+  let proxy = portal_settings();
+  let portal_theme = Rc::new(Cell::new(proxy.as_ref().and_then(read_portal_color_scheme)));
+  let theme = Rc::new(Cell::new(portal_theme.get().unwrap_or_else(gtk_theme)));
+
+  // The GTK settings signals and the portal signal, which GDBus delivers on the thread-default
+  // main context, are all emitted on the event loop thread.
+  let update = {
+    let theme = theme.clone();
+    let portal_theme = portal_theme.clone();
+    Rc::new(move || {
+      let new_theme = portal_theme.get().unwrap_or_else(gtk_theme);
+      if theme.replace(new_theme) != new_theme {
+        on_change(new_theme);
+      }
+    })
+  };
+
+  if let Some(settings) = gtk::Settings::default() {
+    let update_ = update.clone();
+    settings.connect_gtk_application_prefer_dark_theme_notify(move |_| update_());
+    let update_ = update.clone();
+    settings.connect_gtk_theme_name_notify(move |_| update_());
+  }
+
+  if let Some(proxy) = proxy {
+    // `connect_g_signal` requires a `Send` handler, which isn't needed here.
+    proxy.connect_local("g-signal", false, move |values| {
+      let signal = values[2].get::<String>().ok();
+      let parameters = values[3].get::<Variant>().ok();
+      if let (Some(signal), Some(parameters)) = (signal, parameters) {
+        // The new value comes with the signal, reading it again would block the main loop.
+        if signal == "SettingChanged"
+          && parameters.child_value(0).get::<String>().as_deref() == Some(PORTAL_NAMESPACE)
+          && parameters.child_value(1).get::<String>().as_deref() == Some(PORTAL_KEY)
+        {
+          portal_theme.set(color_scheme(parameters.child_value(2)));
+          update();
+        }
+      }
+      None
+    });
+  }
+
+  theme
+}
+
+fn portal_settings() -> Option<DBusProxy> {
+  DBusProxy::for_bus_sync(
+    BusType::Session,
+    DBusProxyFlags::DO_NOT_LOAD_PROPERTIES,
+    None,
+    "org.freedesktop.portal.Desktop",
+    "/org/freedesktop/portal/desktop",
+    "org.freedesktop.portal.Settings",
+    None::<&Cancellable>,
+  )
+  .ok()
+}
+
+/// Reads the portal `color-scheme`, giving up after `PORTAL_READ_TIMEOUT_MS`.
+fn read_portal_color_scheme(proxy: &DBusProxy) -> Option<Theme> {
+  let value = proxy
+    .call_sync(
+      "Read",
+      Some(&(PORTAL_NAMESPACE, PORTAL_KEY).to_variant()),
+      DBusCallFlags::NONE,
+      PORTAL_READ_TIMEOUT_MS,
+      None::<&Cancellable>,
+    )
+    .ok()?;
+  // The value is returned as `(v)`.
+  color_scheme(value.child_value(0))
+}
+
+/// Parses the portal `color-scheme`, which is `1` for a dark preference, `2` for a light one and
+/// `0` without a preference.
+fn color_scheme(mut value: Variant) -> Option<Theme> {
+  // `Read` returns a variant of the `u` itself on most portals.
+  while let Some(inner) = value.as_variant() {
+    value = inner;
+  }
+  match value.get::<u32>()? {
+    1 => Some(Theme::Dark),
+    2 => Some(Theme::Light),
+    _ => None,
+  }
+}
+
+fn gtk_theme() -> Theme {
+  let dark = gtk::Settings::default()
+    .map(|settings| {
+      settings.is_gtk_application_prefer_dark_theme()
+        || settings
+          .gtk_theme_name()
+          .map(|name| name.to_lowercase().ends_with("-dark"))
+          .unwrap_or(false)
+    })
+    .unwrap_or(false);
+  if dark {
+    Theme::Dark
+  } else {
+    Theme::Light
+  }
+}
@@ -2,24 +2,30 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
-  cell::RefCell,
-  collections::VecDeque,
+  cell::{Cell, RefCell},
+  collections::{HashMap, VecDeque},
   rc::Rc,
   sync::atomic::{AtomicBool, AtomicI32, Ordering},
 };
 
-use gdk::{WindowEdge, WindowState};
+use gdk::{ModifierType, WindowEdge, WindowState};
 use gdk_pixbuf::{Colorspace, Pixbuf};
+use glib::ToVariant;
 use gtk::{prelude::*, AccelGroup, Orientation};
 use raw_window_handle::{RawWindowHandle, XlibHandle};
 
 use crate::{
   dpi::{LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize, Position, Size},
   error::{ExternalError, NotSupportedError, OsError as RootOsError},
-  icon::{BadIcon, Icon},
+  event::MouseButtons,
+  icon::{BadIcon, CustomCursor, Icon},
+  keyboard::KeyCode,
   menu::{MenuId, MenuItem},
   monitor::MonitorHandle as RootMonitorHandle,
-  window::{CursorIcon, Fullscreen, UserAttentionType, WindowAttributes, BORDERLESS_RESIZE_INSET},
+  window::{
+    CursorIcon, Fullscreen, ProgressBarState, ProgressState, ResizeDirection, Theme,
+    UserAttentionType, WindowAttributes, BORDERLESS_RESIZE_INSET,
+  },
 };
 
 use super::{
@@ -93,6 +99,12 @@ pub struct Window {
   maximized: Rc<AtomicBool>,
   minimized: Rc<AtomicBool>,
   fullscreen: RefCell<Option<Fullscreen>>,
+  /// The title is set asynchronously by `WindowRequest::Title`.
+  title: RefCell<String>,
+  theme: Rc<Cell<Theme>>,
+  /// The icons are set as a single list, so both are kept to update either of them.
+  window_icon: RefCell<Option<Icon>>,
+  taskbar_icon: RefCell<Option<Icon>>,
 }
 
 impl Window {
@@ -208,13 +220,21 @@ impl Window {
     if attributes.maximized {
       window.maximize();
     }
+    // Before the window is mapped, the state is set as the initial `_NET_WM_STATE` property on
+    // X11. Once mapped, it is requested from the window manager with a client message, which is
+    // lost if the window isn't managed yet.
+    window.set_keep_above(attributes.always_on_top);
     window.set_visible(attributes.visible);
+    window.set_focus_on_map(attributes.focused);
     window.set_decorated(attributes.decorations);
 
-    window.set_keep_above(attributes.always_on_top);
-    if let Some(icon) = attributes.window_icon {
-      window.set_icon(Some(&icon.inner.into()));
-    }
+    set_icons(
+      &window,
+      icon_list(
+        attributes.window_icon.clone(),
+        pl_attribs.taskbar_icon.clone(),
+      ),
+    );
 
     if attributes.visible {
       window.show_all();
@@ -281,6 +301,10 @@ impl Window {
       maximized,
       minimized,
       fullscreen: RefCell::new(attributes.fullscreen),
+      title: RefCell::new(attributes.title.clone()),
+      theme: event_loop_window_target.theme.clone(),
+      window_icon: RefCell::new(attributes.window_icon),
+      taskbar_icon: RefCell::new(pl_attribs.taskbar_icon),
     };
 
     win.set_skip_taskbar(pl_attribs.skip_taskbar);
@@ -366,6 +390,31 @@ impl Window {
     .to_physical(self.scale_factor.load(Ordering::Acquire) as f64)
   }
 
+  pub fn content_size(&self) -> PhysicalSize<u32> {
+    let size = self.inner_size();
+    PhysicalSize::new(
+      size.width,
+      size.height.saturating_sub(self.menu_bar_height()),
+    )
+  }
+
+  pub fn set_content_size<S: Into<Size>>(&self, size: S) {
+    let size = size.into().to_physical::<u32>(self.scale_factor());
+    self.set_inner_size(PhysicalSize::new(
+      size.width,
+      size.height + self.menu_bar_height(),
+    ));
+  }
+
+  /// The physical height the menu bar takes at the top of the client area.
+  fn menu_bar_height(&self) -> u32 {
+    if self.is_menu_visible() {
+      (self.menu_bar.allocated_height() as f64 * self.scale_factor()).round() as u32
+    } else {
+      0
+    }
+  }
+
   pub fn set_min_inner_size<S: Into<Size>>(&self, min_size: Option<S>) {
     if let Some(size) = min_size {
       let (min_width, min_height) = size.into().to_logical::<i32>(self.scale_factor()).into();
@@ -392,6 +441,7 @@ impl Window {
   }
 
   pub fn set_title(&self, title: &str) {
+    *self.title.borrow_mut() = title.to_string();
     if let Err(e) = self
       .window_requests_tx
       .send((self.window_id, WindowRequest::Title(title.to_string())))
@@ -400,6 +450,10 @@ impl Window {
     }
   }
 
+  pub fn title(&self) -> String {
+    self.title.borrow().clone()
+  }
+
   pub fn set_menu(&self, menu: Option<menu::Menu>) {
     if let Err(e) = self.window_requests_tx.send((
       self.window_id,
@@ -438,6 +492,15 @@ impl Window {
     }
   }
 
+  pub fn set_resize_border_width(&self, width: u32) {
+    if let Err(e) = self.window_requests_tx.send((
+      self.window_id,
+      WindowRequest::ResizeBorderWidth(width as i32),
+    )) {
+      log::warn!("Fail to send resize border width request: {}", e);
+    }
+  }
+
   pub fn set_minimized(&self, minimized: bool) {
     if let Err(e) = self
       .window_requests_tx
@@ -483,6 +546,16 @@ impl Window {
     Ok(())
   }
 
+  pub fn drag_resize_window(&self, direction: ResizeDirection) -> Result<(), ExternalError> {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::DragResizeWindow(direction)))
+    {
+      log::warn!("Fail to send drag resize window request: {}", e);
+    }
+    Ok(())
+  }
+
   pub fn set_fullscreen(&self, fullscreen: Option<Fullscreen>) {
     self.fullscreen.replace(fullscreen.clone());
     if let Err(e) = self
@@ -515,17 +588,67 @@ impl Window {
     }
   }
 
+  pub fn set_activate_on_click(&self, activate_on_click: bool) {
+    if let Err(e) = self.window_requests_tx.send((
+      self.window_id,
+      WindowRequest::ActivateOnClick(activate_on_click),
+    )) {
+      log::warn!("Fail to send activate on click request: {}", e);
+    }
+  }
+
+  pub fn set_transparent(&self, _transparent: bool) {
+    // Unsupported: the RGBA visual set up in `new` can't be changed once the window is
+    // realized, transparency is only chosen at creation.
+  }
+
   pub fn set_window_icon(&self, window_icon: Option<Icon>) {
+    *self.window_icon.borrow_mut() = window_icon;
+    self.update_icons();
+  }
+
+  pub(crate) fn set_taskbar_icon(&self, taskbar_icon: Option<Icon>) {
+    *self.taskbar_icon.borrow_mut() = taskbar_icon;
+    self.update_icons();
+  }
+
+  fn update_icons(&self) {
+    let icons = icon_list(
+      self.window_icon.borrow().clone(),
+      self.taskbar_icon.borrow().clone(),
+    );
     if let Err(e) = self
       .window_requests_tx
-      .send((self.window_id, WindowRequest::WindowIcon(window_icon)))
+      .send((self.window_id, WindowRequest::Icons(icons)))
     {
       log::warn!("Fail to send window icon request: {}", e);
     }
   }
 
-  pub fn set_ime_position<P: Into<Position>>(&self, _position: P) {
-    //TODO
+  pub fn set_ime_position<P: Into<Position>>(&self, position: P) {
+    let (x, y): (i32, i32) = position
+      .into()
+      .to_logical::<i32>(self.scale_factor())
+      .into();
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::ImePosition((x, y))))
+    {
+      log::warn!("Fail to send IME position request: {}", e);
+    }
+  }
+
+  pub fn set_ime_allowed(&self, allowed: bool) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::ImeAllowed(allowed)))
+    {
+      log::warn!("Fail to send IME allowed request: {}", e);
+    }
+  }
+
+  pub fn set_content_protected(&self, _protected: bool) {
+    // X11 has no way to keep other clients from reading the window contents.
   }
 
   pub fn request_user_attention(&self, request_type: Option<UserAttentionType>) {
@@ -537,6 +660,35 @@ impl Window {
     }
   }
 
+  pub fn set_badge_count(&self, count: Option<i64>) {
+    let count = count.filter(|&count| count > 0);
+    let mut properties = HashMap::new();
+    properties.insert("count".to_string(), count.unwrap_or(0).to_variant());
+    properties.insert("count-visible".to_string(), count.is_some().to_variant());
+
+    if let Err(e) = update_launcher_entry(properties) {
+      log::warn!("Fail to set badge count: {}", e);
+    }
+  }
+
+  pub fn set_progress_bar(&self, state: ProgressBarState) {
+    let mut properties = HashMap::new();
+    if let Some(progress) = state.progress {
+      properties.insert(
+        "progress".to_string(),
+        (progress.min(100) as f64 / 100.0).to_variant(),
+      );
+    }
+    properties.insert(
+      "progress-visible".to_string(),
+      (state.state != ProgressState::None).to_variant(),
+    );
+
+    if let Err(e) = update_launcher_entry(properties) {
+      log::warn!("Fail to set progress bar: {}", e);
+    }
+  }
+
   pub fn hide_menu(&self) {
     self.menu_bar.hide();
   }
@@ -549,6 +701,39 @@ impl Window {
     self.menu_bar.get_visible()
   }
 
+  pub fn theme(&self) -> Theme {
+    self.theme.get()
+  }
+
+  pub fn pressed_mouse_buttons(&self) -> MouseButtons {
+    let mut buttons = MouseButtons::empty();
+    let pointer = self
+      .window
+      .display()
+      .default_seat()
+      .and_then(|seat| seat.pointer());
+    if let (Some(pointer), Some(window)) = (pointer, self.window.window()) {
+      let (_, _, _, state) = window.device_position(&pointer);
+      buttons.set(
+        MouseButtons::LEFT,
+        state.contains(ModifierType::BUTTON1_MASK),
+      );
+      buttons.set(
+        MouseButtons::MIDDLE,
+        state.contains(ModifierType::BUTTON2_MASK),
+      );
+      buttons.set(
+        MouseButtons::RIGHT,
+        state.contains(ModifierType::BUTTON3_MASK),
+      );
+    }
+    buttons
+  }
+
+  pub fn pressed_keys(&self) -> Vec<KeyCode> {
+    Vec::new()
+  }
+
   pub fn set_cursor_icon(&self, cursor: CursorIcon) {
     if let Err(e) = self
       .window_requests_tx
@@ -558,14 +743,56 @@ impl Window {
     }
   }
 
-  pub fn set_cursor_position<P: Into<Position>>(&self, _position: P) -> Result<(), ExternalError> {
-    todo!()
+  pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::CustomCursor(cursor.clone())))
+    {
+      log::warn!("Fail to send custom cursor request: {}", e);
+    }
+  }
+
+  pub fn set_busy_cursor(&self, busy: bool) {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::BusyCursor(busy)))
+    {
+      log::warn!("Fail to send busy cursor request: {}", e);
+    }
+  }
+
+  pub fn set_cursor_position<P: Into<Position>>(&self, position: P) -> Result<(), ExternalError> {
+    let not_supported = || ExternalError::NotSupported(NotSupportedError::new());
+    let window = self.window.window().ok_or_else(not_supported)?;
+    let pointer = window
+      .display()
+      .default_seat()
+      .and_then(|seat| seat.pointer())
+      .ok_or_else(not_supported)?;
+    // GDK warps the pointer in logical root coordinates.
+    let (x, y): (i32, i32) = position
+      .into()
+      .to_logical::<i32>(self.scale_factor())
+      .into();
+    let (_, origin_x, origin_y) = window.origin();
+    pointer.warp(&window.screen(), origin_x + x, origin_y + y);
+    Ok(())
   }
 
   pub fn set_cursor_grab(&self, _grab: bool) -> Result<(), ExternalError> {
     todo!()
   }
 
+  pub fn set_ignore_cursor_events(&self, ignore: bool) -> Result<(), ExternalError> {
+    if let Err(e) = self
+      .window_requests_tx
+      .send((self.window_id, WindowRequest::CursorIgnoreEvents(ignore)))
+    {
+      log::warn!("Fail to send cursor ignore events request: {}", e);
+    }
+    Ok(())
+  }
+
   pub fn set_cursor_visible(&self, visible: bool) {
     let cursor = if visible {
       Some(CursorIcon::Default)
@@ -650,16 +877,25 @@ pub enum WindowRequest {
   Visible(bool),
   Focus,
   Resizable(bool),
+  ResizeBorderWidth(i32),
   Minimized(bool),
   Maximized(bool),
   DragWindow,
+  DragResizeWindow(ResizeDirection),
   Fullscreen(Option<Fullscreen>),
   Decorations(bool),
   AlwaysOnTop(bool),
-  WindowIcon(Option<Icon>),
+  ActivateOnClick(bool),
+  /// The icons of the window, the window manager picks the size that fits each place best.
+  Icons(Vec<Icon>),
   UserAttention(Option<UserAttentionType>),
   SetSkipTaskbar(bool),
   CursorIcon(Option<CursorIcon>),
+  BusyCursor(bool),
+  CursorIgnoreEvents(bool),
+  CustomCursor(CustomCursor),
+  ImePosition((i32, i32)),
+  ImeAllowed(bool),
   WireUpEvents,
   Redraw,
   Menu((Option<MenuItem>, Option<MenuId>)),
@@ -667,7 +903,53 @@ pub enum WindowRequest {
   GlobalHotKey(u16),
 }
 
+/// Returns the icons to set on the window. Window managers use the icon closest to the size
+/// they need, so the taskbar picks the larger icon over the window icon.
+fn icon_list(window_icon: Option<Icon>, taskbar_icon: Option<Icon>) -> Vec<Icon> {
+  window_icon.into_iter().chain(taskbar_icon).collect()
+}
+
+pub(crate) fn set_icons(window: &impl IsA<gtk::Window>, icons: Vec<Icon>) {
+  let pixbufs: Vec<Pixbuf> = icons.into_iter().map(|icon| icon.inner.into()).collect();
+  window.set_icon_list(&pixbufs);
+}
+
+/// Sends properties of the application icon to the launchers implementing the Unity launcher
+/// API.
+fn update_launcher_entry(properties: HashMap<String, glib::Variant>) -> Result<(), glib::Error> {
+  // The launchers match the `application://` URI of the signal against the desktop entry of the
+  // application, named after the executable here.
+  let app_uri = match std::env::current_exe().ok().and_then(|exe| {
+    exe
+      .file_stem()
+      .map(|stem| stem.to_string_lossy().into_owned())
+  }) {
+    Some(name) => format!("application://{}.desktop", name),
+    None => return Ok(()),
+  };
+
+  gio::bus_get_sync(gio::BusType::Session, None::<&gio::Cancellable>).and_then(|connection| {
+    connection.emit_signal(
+      None,
+      "/",
+      "com.canonical.Unity.LauncherEntry",
+      "Update",
+      Some(&(app_uri, properties).to_variant()),
+    )
+  })
+}
+
 pub fn hit_test(window: &gdk::Window, cx: f64, cy: f64) -> WindowEdge {
+  hit_test_with_inset(window, cx, cy, BORDERLESS_RESIZE_INSET)
+}
+
+/// Like [`hit_test`], with the resize borders `inset` logical pixels wide.
+pub(crate) fn hit_test_with_inset(
+  window: &gdk::Window,
+  cx: f64,
+  cy: f64,
+  inset: i32,
+) -> WindowEdge {
   let (left, top) = window.position();
   let (w, h) = (window.width(), window.height());
   let (right, bottom) = (left + w, top + h);
@@ -683,10 +965,10 @@ pub fn hit_test(window: &gdk::Window, cx: f64, cy: f64) -> WindowEdge {
   const BOTTOMRIGHT: i32 = BOTTOM | RIGHT;
 
   #[rustfmt::skip]
-  let result = (LEFT * (if cx < (left + BORDERLESS_RESIZE_INSET) { 1 } else { 0 }))
-    | (RIGHT * (if cx >= (right - BORDERLESS_RESIZE_INSET) { 1 } else { 0 }))
-    | (TOP * (if cy < (top + BORDERLESS_RESIZE_INSET) { 1 } else { 0 }))
-    | (BOTTOM * (if cy >= (bottom - BORDERLESS_RESIZE_INSET) { 1 } else { 0 }));
+  let result = (LEFT * (if cx < (left + inset) { 1 } else { 0 }))
+    | (RIGHT * (if cx >= (right - inset) { 1 } else { 0 }))
+    | (TOP * (if cy < (top + inset) { 1 } else { 0 }))
+    | (BOTTOM * (if cy >= (bottom - inset) { 1 } else { 0 }));
 
   match result {
     LEFT => WindowEdge::West,
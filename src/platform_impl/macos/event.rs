@@ -56,6 +56,7 @@ pub enum EventProxy {
 pub struct KeyEventExtra {
   pub text_with_all_modifiers: Option<&'static str>,
   pub key_without_modifiers: Key<'static>,
+  pub scancode: u32,
 }
 
 pub fn get_modifierless_char(scancode: u16) -> Key<'static> {
@@ -194,6 +195,7 @@ pub fn create_key_event(
     platform_specific: KeyEventExtra {
       text_with_all_modifiers,
       key_without_modifiers,
+      scancode: scancode as u32,
     },
   }
 }
@@ -23,12 +23,15 @@ use scopeguard::defer;
 
 use crate::{
   event::Event,
-  event_loop::{ControlFlow, EventLoopClosed, EventLoopWindowTarget as RootWindowTarget},
+  event_loop::{
+    ControlFlow, DeviceEventFilter, EventLoopClosed, EventLoopWindowTarget as RootWindowTarget,
+  },
   monitor::MonitorHandle as RootMonitorHandle,
   platform_impl::platform::{
     app::APP_CLASS,
     app_delegate::APP_DELEGATE_CLASS,
     app_state::AppState,
+    event::EventWrapper,
     monitor::{self, MonitorHandle},
     observer::*,
     util::IdRef,
@@ -82,11 +85,29 @@ impl<T: 'static> EventLoopWindowTarget<T> {
     monitor::available_monitors()
   }
 
+  #[inline]
+  pub fn set_device_event_filter(&self, _filter: DeviceEventFilter) {}
+
   #[inline]
   pub fn primary_monitor(&self) -> Option<RootMonitorHandle> {
     let monitor = monitor::primary_monitor();
     Some(RootMonitorHandle { inner: monitor })
   }
+
+  #[cfg(feature = "testing")]
+  pub(crate) fn inject_window_event(
+    &self,
+    window_id: crate::window::WindowId,
+    event: crate::event::WindowEvent<'static>,
+  ) {
+    AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
+      window_id,
+      event,
+    }));
+    unsafe {
+      CFRunLoopWakeUp(CFRunLoopGetMain());
+    }
+  }
 }
 
 pub struct EventLoop<T: 'static> {
@@ -130,6 +151,7 @@ impl<T> EventLoop<T> {
       delegate,
       window_target: Rc::new(RootWindowTarget {
         p: Default::default(),
+        pending_control_flow: Default::default(),
         _marker: PhantomData,
       }),
       panic_info,
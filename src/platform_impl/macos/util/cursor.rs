@@ -15,6 +15,7 @@ pub enum Cursor {
   Native(&'static str),
   Undocumented(&'static str),
   WebKit(&'static str),
+  Invisible,
 }
 
 impl From<CursorIcon> for Cursor {
@@ -67,6 +68,8 @@ impl From<CursorIcon> for Cursor {
       // https://stackoverflow.com/a/21786835/5435443
       CursorIcon::Move | CursorIcon::AllScroll => Cursor::WebKit("move"),
       CursorIcon::Cell => Cursor::WebKit("cell"),
+
+      CursorIcon::None => Cursor::Invisible,
     }
   }
 }
@@ -96,6 +99,7 @@ impl Cursor {
         msg_send![class, performSelector: sel]
       }
       Cursor::WebKit(cursor_name) => load_webkit_cursor(cursor_name),
+      Cursor::Invisible => invisible_cursor(),
     }
   }
 }
@@ -22,7 +22,7 @@ use objc::{
 use crate::{
   dpi::LogicalPosition,
   event::{
-    DeviceEvent, ElementState, Event, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent,
+    DeviceEvent, ElementState, Event, Ime, MouseButton, MouseScrollDelta, TouchPhase, WindowEvent,
   },
   keyboard::{KeyCode, ModifiersState},
   platform_impl::platform::{
@@ -559,7 +559,7 @@ extern "C" fn insert_text(this: &Object, _sel: Sel, string: id, _replacement_ran
 
     AppState::queue_event(EventWrapper::StaticEvent(Event::WindowEvent {
       window_id: WindowId(get_window_id(state.ns_window)),
-      event: WindowEvent::ReceivedImeText(string),
+      event: WindowEvent::Ime(Ime::Commit(string)),
     }));
     if state.in_ime_preedit {
       state.in_ime_preedit = false;
@@ -945,12 +945,15 @@ fn mouse_click(this: &Object, event: id, button: MouseButton, button_state: Elem
 
     update_potentially_stale_modifiers(state, event);
 
+    let click_count: NSInteger = msg_send![event, clickCount];
+
     let window_event = Event::WindowEvent {
       window_id: WindowId(get_window_id(state.ns_window)),
       event: WindowEvent::MouseInput {
         device_id: DEVICE_ID,
         state: button_state,
         button,
+        click_count: click_count.max(1) as u32,
         modifiers: event_mods(event),
       },
     };
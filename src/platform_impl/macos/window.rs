@@ -18,7 +18,9 @@ use crate::{
     LogicalPosition, LogicalSize, PhysicalPosition, PhysicalSize, Position, Size, Size::Logical,
   },
   error::{ExternalError, NotSupportedError, OsError as RootOsError},
-  icon::Icon,
+  event::MouseButtons,
+  icon::{CustomCursor, Icon},
+  keyboard::KeyCode,
   monitor::{MonitorHandle as RootMonitorHandle, VideoMode as RootVideoMode},
   platform::macos::WindowExtMacOS,
   platform_impl::platform::{
@@ -30,7 +32,10 @@ use crate::{
     window_delegate::new_delegate,
     OsError,
   },
-  window::{CursorIcon, Fullscreen, UserAttentionType, WindowAttributes, WindowId as RootWindowId},
+  window::{
+    CursorIcon, Fullscreen, ProgressBarState, ResizeDirection, Theme, UserAttentionType,
+    WindowAttributes, WindowId as RootWindowId,
+  },
 };
 use cocoa::{
   appkit::{
@@ -302,6 +307,8 @@ pub struct SharedState {
   /// transitioning back to borderless fullscreen.
   save_presentation_opts: Option<NSApplicationPresentationOptions>,
   pub saved_desktop_display_mode: Option<(CGDisplay, CGDisplayMode)>,
+  /// The title passed to `set_title`, which sets it asynchronously.
+  pub title: String,
 }
 
 impl SharedState {
@@ -324,6 +331,7 @@ impl From<WindowAttributes> for SharedState {
       // identical, resulting in a no-op.
       fullscreen: None,
       maximized: attribs.maximized,
+      title: attribs.title,
       ..Default::default()
     }
   }
@@ -408,6 +416,7 @@ impl UnownedWindow {
     let fullscreen = win_attribs.fullscreen.take();
     let maximized = win_attribs.maximized;
     let visible = win_attribs.visible;
+    let focused = win_attribs.focused;
     let decorations = win_attribs.decorations;
     let inner_rect = win_attribs
       .inner_size
@@ -431,9 +440,13 @@ impl UnownedWindow {
     // Setting the window as key has to happen *after* we set the fullscreen
     // state, since otherwise we'll briefly see the window at normal size
     // before it transitions.
-    if visible {
+    if visible && focused {
       // Tightly linked with `app_state::window_activation_hack`
       unsafe { window.ns_window.makeKeyAndOrderFront_(nil) };
+    } else if visible {
+      unsafe {
+        let _: () = msg_send![*window.ns_window, orderFront: nil];
+      }
     }
 
     if maximized {
@@ -458,11 +471,16 @@ impl UnownedWindow {
   }
 
   pub fn set_title(&self, title: &str) {
+    self.shared_state.lock().unwrap().title = title.to_string();
     unsafe {
       util::set_title_async(*self.ns_window, title.to_string());
     }
   }
 
+  pub fn title(&self) -> String {
+    self.shared_state.lock().unwrap().title.clone()
+  }
+
   pub fn set_menu(&self, menu: Option<Menu>) {
     unsafe {
       util::set_menu_async(*self.ns_window, menu);
@@ -547,6 +565,29 @@ impl UnownedWindow {
     }
   }
 
+  #[inline]
+  pub fn content_size(&self) -> PhysicalSize<u32> {
+    // Unlike the view, the content rect never extends below a transparent title bar.
+    let content_rect = unsafe {
+      NSWindow::contentRectForFrameRect_(*self.ns_window, NSWindow::frame(*self.ns_window))
+    };
+    let logical: LogicalSize<f64> = (
+      content_rect.size.width as f64,
+      content_rect.size.height as f64,
+    )
+      .into();
+    logical.to_physical(self.scale_factor())
+  }
+
+  #[inline]
+  pub fn set_content_size(&self, size: Size) {
+    // `setContentSize:` sizes the content rect, see `content_size`.
+    unsafe {
+      let scale_factor = self.scale_factor();
+      util::set_content_size_async(*self.ns_window, size.to_logical(scale_factor));
+    }
+  }
+
   pub fn set_min_inner_size(&self, dimensions: Option<Size>) {
     unsafe {
       let dimensions = dimensions.unwrap_or(Logical(LogicalSize {
@@ -589,6 +630,11 @@ impl UnownedWindow {
     } // Otherwise, we don't change the mask until we exit fullscreen.
   }
 
+  #[inline]
+  pub fn set_resize_border_width(&self, _width: u32) {
+    debug!("`Window::set_resize_border_width` is ignored on macOS")
+  }
+
   pub fn set_cursor_icon(&self, cursor: CursorIcon) {
     let cursor = util::Cursor::from(cursor);
     if let Some(cursor_access) = self.cursor_state.upgrade() {
@@ -601,6 +647,9 @@ impl UnownedWindow {
     }
   }
 
+  #[inline]
+  pub fn set_busy_cursor(&self, _busy: bool) {}
+
   #[inline]
   pub fn set_cursor_grab(&self, grab: bool) -> Result<(), ExternalError> {
     // TODO: Do this for real https://stackoverflow.com/a/40922095/5435443
@@ -608,6 +657,19 @@ impl UnownedWindow {
       .map_err(|status| ExternalError::Os(os_error!(OsError::CGError(status))))
   }
 
+  #[inline]
+  pub fn set_custom_cursor(&self, _cursor: &CustomCursor) {
+    debug!("`Window::set_custom_cursor` is ignored on macOS")
+  }
+
+  #[inline]
+  pub fn set_ignore_cursor_events(&self, ignore: bool) -> Result<(), ExternalError> {
+    unsafe {
+      let _: () = msg_send![*self.ns_window, setIgnoresMouseEvents: if ignore { YES } else { NO }];
+    }
+    Ok(())
+  }
+
   #[inline]
   pub fn set_cursor_visible(&self, visible: bool) {
     if let Some(cursor_access) = self.cursor_state.upgrade() {
@@ -657,6 +719,11 @@ impl UnownedWindow {
     Ok(())
   }
 
+  #[inline]
+  pub fn drag_resize_window(&self, _direction: ResizeDirection) -> Result<(), ExternalError> {
+    Err(ExternalError::NotSupported(NotSupportedError::new()))
+  }
+
   pub(crate) fn is_zoomed(&self) -> bool {
     // because `isZoomed` doesn't work if the window's borderless,
     // we make it resizable temporalily.
@@ -1032,6 +1099,24 @@ impl UnownedWindow {
     unsafe { util::set_level_async(*self.ns_window, level) };
   }
 
+  #[inline]
+  pub fn set_activate_on_click(&self, _activate_on_click: bool) {}
+
+  #[inline]
+  pub fn set_transparent(&self, transparent: bool) {
+    unsafe {
+      self
+        .ns_window
+        .setOpaque_(if transparent { NO } else { YES });
+      let color = if transparent {
+        NSColor::clearColor(nil)
+      } else {
+        msg_send![class!(NSColor), windowBackgroundColor]
+      };
+      self.ns_window.setBackgroundColor_(color);
+    }
+  }
+
   #[inline]
   pub fn set_window_icon(&self, _icon: Option<Icon>) {
     // macOS doesn't have window icons. Though, there is
@@ -1058,6 +1143,25 @@ impl UnownedWindow {
     }
   }
 
+  #[inline]
+  pub fn set_ime_allowed(&self, _allowed: bool) {
+    debug!("`Window::set_ime_allowed` is ignored on macOS")
+  }
+
+  #[inline]
+  pub fn set_content_protected(&self, protected: bool) {
+    // `NSWindowSharingNone` and `NSWindowSharingReadOnly`, the default.
+    let sharing_type: NSUInteger = if protected { 0 } else { 1 };
+    unsafe {
+      let _: () = msg_send![*self.ns_window, setSharingType: sharing_type];
+    }
+  }
+
+  #[inline]
+  pub fn set_progress_bar(&self, _state: ProgressBarState) {
+    debug!("`Window::set_progress_bar` is ignored on macOS")
+  }
+
   #[inline]
   pub fn request_user_attention(&self, request_type: Option<UserAttentionType>) {
     let ns_request_type = request_type.map(|ty| match ty {
@@ -1071,6 +1175,22 @@ impl UnownedWindow {
     }
   }
 
+  #[inline]
+  pub fn set_badge_count(&self, count: Option<i64>) {
+    unsafe {
+      let dock_tile: id = msg_send![NSApp(), dockTile];
+      match count.filter(|&count| count > 0) {
+        Some(count) => {
+          let label = util::ns_string_id_ref(&count.to_string());
+          let _: () = msg_send![dock_tile, setBadgeLabel: *label];
+        }
+        None => {
+          let _: () = msg_send![dock_tile, setBadgeLabel: nil];
+        }
+      }
+    }
+  }
+
   #[inline]
   pub fn hide_menu(&self) {}
 
@@ -1083,6 +1203,42 @@ impl UnownedWindow {
     true
   }
 
+  #[inline]
+  pub fn theme(&self) -> Theme {
+    unsafe {
+      // `effectiveAppearance` is only available on macOS 10.14+, which introduced dark mode.
+      let supported: BOOL =
+        msg_send![*self.ns_window, respondsToSelector: sel!(effectiveAppearance)];
+      if supported == NO {
+        return Theme::Light;
+      }
+      let appearance: id = msg_send![*self.ns_window, effectiveAppearance];
+      let name: id = msg_send![appearance, name];
+      if util::ns_string_to_rust(name).contains("Dark") {
+        Theme::Dark
+      } else {
+        Theme::Light
+      }
+    }
+  }
+
+  pub fn pressed_mouse_buttons(&self) -> MouseButtons {
+    // Bit `n` of `pressedMouseButtons` is set while the button numbered `n` is down.
+    let pressed: NSUInteger = unsafe { msg_send![class!(NSEvent), pressedMouseButtons] };
+    let mut buttons = MouseButtons::empty();
+    buttons.set(MouseButtons::LEFT, pressed & (1 << 0) != 0);
+    buttons.set(MouseButtons::RIGHT, pressed & (1 << 1) != 0);
+    buttons.set(MouseButtons::MIDDLE, pressed & (1 << 2) != 0);
+    buttons.set(MouseButtons::BACK, pressed & (1 << 3) != 0);
+    buttons.set(MouseButtons::FORWARD, pressed & (1 << 4) != 0);
+    buttons
+  }
+
+  #[inline]
+  pub fn pressed_keys(&self) -> Vec<KeyCode> {
+    Vec::new()
+  }
+
   #[inline]
   // Allow directly accessing the current monitor internally without unwrapping.
   pub(crate) fn current_monitor_inner(&self) -> RootMonitorHandle {
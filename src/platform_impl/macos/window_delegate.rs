@@ -184,6 +184,15 @@ lazy_static! {
       window_did_resign_key as extern "C" fn(&Object, Sel, id),
     );
 
+    decl.add_method(
+      sel!(windowDidMiniaturize:),
+      window_did_miniaturize as extern "C" fn(&Object, Sel, id),
+    );
+    decl.add_method(
+      sel!(windowDidDeminiaturize:),
+      window_did_deminiaturize as extern "C" fn(&Object, Sel, id),
+    );
+
     decl.add_method(
       sel!(draggingEntered:),
       dragging_entered as extern "C" fn(&Object, Sel, id) -> BOOL,
@@ -289,8 +298,10 @@ extern "C" fn window_will_close(this: &Object, _: Sel, _: id) {
 extern "C" fn window_did_resize(this: &Object, _: Sel, _: id) {
   trace!("Triggered `windowDidResize:`");
   with_state(this, |state| {
-    state.emit_resize_event();
+    // Resizing from the top or left edges moves the window as well, `Moved` comes first like on
+    // the other platforms.
     state.emit_move_event();
+    state.emit_resize_event();
   });
   trace!("Completed `windowDidResize:`");
 }
@@ -322,6 +333,22 @@ extern "C" fn window_did_become_key(this: &Object, _: Sel, _: id) {
   trace!("Completed `windowDidBecomeKey:`");
 }
 
+extern "C" fn window_did_miniaturize(this: &Object, _: Sel, _: id) {
+  trace!("Triggered `windowDidMiniaturize:`");
+  with_state(this, |state| {
+    state.emit_event(WindowEvent::Minimized(true));
+  });
+  trace!("Completed `windowDidMiniaturize:`");
+}
+
+extern "C" fn window_did_deminiaturize(this: &Object, _: Sel, _: id) {
+  trace!("Triggered `windowDidDeminiaturize:`");
+  with_state(this, |state| {
+    state.emit_event(WindowEvent::Minimized(false));
+  });
+  trace!("Completed `windowDidDeminiaturize:`");
+}
+
 extern "C" fn window_did_resign_key(this: &Object, _: Sel, _: id) {
   trace!("Triggered `windowDidResignKey:`");
   with_state(this, |state| {
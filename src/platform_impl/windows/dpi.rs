@@ -12,8 +12,9 @@ use windows::Win32::{
 };
 
 use crate::platform_impl::platform::util::{
-  ENABLE_NON_CLIENT_DPI_SCALING, GET_DPI_FOR_MONITOR, GET_DPI_FOR_WINDOW, SET_PROCESS_DPI_AWARE,
-  SET_PROCESS_DPI_AWARENESS, SET_PROCESS_DPI_AWARENESS_CONTEXT,
+  ENABLE_NON_CLIENT_DPI_SCALING, GET_AWARENESS_FROM_DPI_AWARENESS_CONTEXT, GET_DPI_FOR_MONITOR,
+  GET_DPI_FOR_WINDOW, GET_WINDOW_DPI_AWARENESS_CONTEXT, SET_PROCESS_DPI_AWARE,
+  SET_PROCESS_DPI_AWARENESS, SET_PROCESS_DPI_AWARENESS_CONTEXT, SET_THREAD_DPI_AWARENESS_CONTEXT,
 };
 
 pub fn become_dpi_aware() {
@@ -38,6 +39,23 @@ pub fn become_dpi_aware() {
   });
 }
 
+/// Sets the DPI awareness of the calling thread, returning the previous context so it can be
+/// restored. Returns `None` if the OS doesn't support per-thread awareness (pre Windows 10 1607)
+/// or if `context` is invalid.
+pub fn set_thread_dpi_awareness_context(
+  context: DPI_AWARENESS_CONTEXT,
+) -> Option<DPI_AWARENESS_CONTEXT> {
+  unsafe {
+    if let Some(SetThreadDpiAwarenessContext) = *SET_THREAD_DPI_AWARENESS_CONTEXT {
+      let previous = SetThreadDpiAwarenessContext(context);
+      if !previous.is_invalid() {
+        return Some(previous);
+      }
+    }
+  }
+  None
+}
+
 pub fn enable_non_client_dpi_scaling(hwnd: HWND) {
   unsafe {
     if let Some(EnableNonClientDpiScaling) = *ENABLE_NON_CLIENT_DPI_SCALING {
@@ -46,10 +64,12 @@ pub fn enable_non_client_dpi_scaling(hwnd: HWND) {
   }
 }
 
+/// Returns the DPI a window on `hmonitor` has, which is what `hwnd_dpi` reports for it.
 pub fn get_monitor_dpi(hmonitor: HMONITOR) -> Option<u32> {
   unsafe {
     if let Some(GetDpiForMonitor) = *GET_DPI_FOR_MONITOR {
-      // We are on Windows 8.1 or later.
+      // We are on Windows 8.1 or later. The effective DPI is already adjusted to the DPI
+      // awareness of the process, e.g. it's 96 for a DPI unaware process.
       let mut dpi_x = 0;
       let mut dpi_y = 0;
       if GetDpiForMonitor(hmonitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y).is_ok() {
@@ -58,6 +78,14 @@ pub fn get_monitor_dpi(hmonitor: HMONITOR) -> Option<u32> {
         // https://msdn.microsoft.com/en-us/library/windows/desktop/dn280510(v=vs.85).aspx
         return Some(dpi_x as u32);
       }
+    } else if IsProcessDPIAware().as_bool() {
+      // We are on Vista or later, where all monitors share the system DPI, see `hwnd_dpi`.
+      let hdc = GetDC(HWND::default());
+      if !hdc.is_invalid() {
+        let dpi = GetDeviceCaps(hdc, LOGPIXELSX) as u32;
+        ReleaseDC(HWND::default(), hdc);
+        return Some(dpi);
+      }
     }
   }
   None
@@ -68,6 +96,20 @@ pub fn dpi_to_scale_factor(dpi: u32) -> f64 {
   dpi as f64 / BASE_DPI as f64
 }
 
+/// Whether the DPI of `hwnd` follows the monitor it is on. Only reported on Windows 10
+/// Anniversary Update (1607) or later, older versions always return `false`.
+pub unsafe fn is_per_monitor_dpi_aware(hwnd: HWND) -> bool {
+  if let (Some(GetWindowDpiAwarenessContext), Some(GetAwarenessFromDpiAwarenessContext)) = (
+    *GET_WINDOW_DPI_AWARENESS_CONTEXT,
+    *GET_AWARENESS_FROM_DPI_AWARENESS_CONTEXT,
+  ) {
+    GetAwarenessFromDpiAwarenessContext(GetWindowDpiAwarenessContext(hwnd))
+      == DPI_AWARENESS_PER_MONITOR_AWARE
+  } else {
+    false
+  }
+}
+
 pub unsafe fn hwnd_dpi(hwnd: HWND) -> u32 {
   let hdc = GetDC(hwnd);
   if hdc.is_invalid() {
@@ -0,0 +1,127 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{ffi::OsStr, io, os::windows::ffi::OsStrExt, path::PathBuf, ptr};
+
+use windows::{
+  self as Windows,
+  Win32::{
+    Foundation::{BOOL, DRAGDROP_S_CANCEL, DRAGDROP_S_DROP, DRAGDROP_S_USEDEFAULTCURSORS, PWSTR},
+    System::{
+      Com::{IBindCtx, IDataObject},
+      Ole::{
+        DoDragDrop, IDropSource, OleInitialize, OleUninitialize, DROPEFFECT_COPY, DROPEFFECT_LINK,
+        DROPEFFECT_NONE,
+      },
+    },
+    UI::{
+      Shell::{
+        BHID_DataObject, Common::ITEMIDLIST, ILCreateFromPathW, ILFree,
+        SHCreateShellItemArrayFromIDLists,
+      },
+      WindowsAndMessaging::{MK_LBUTTON, MK_RBUTTON},
+    },
+  },
+};
+
+use windows_macros::implement;
+
+/// The operation performed by the drop target at the end of a drag started with
+/// [`WindowExtWindows::start_drag`](crate::platform::windows::WindowExtWindows::start_drag).
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DropEffect {
+  /// The drag was cancelled or the target didn't accept the files.
+  None,
+  /// The target copied the files.
+  Copy,
+  /// The target created links to the files.
+  Link,
+}
+
+#[implement(Windows::Win32::System::Ole::IDropSource)]
+struct DragSource;
+
+#[allow(non_snake_case)]
+impl DragSource {
+  unsafe fn QueryContinueDrag(
+    &self,
+    fEscapePressed: BOOL,
+    grfKeyState: u32,
+  ) -> windows::core::Result<()> {
+    if fEscapePressed.as_bool() {
+      Err(DRAGDROP_S_CANCEL.into())
+    } else if grfKeyState & (MK_LBUTTON | MK_RBUTTON) == 0 {
+      Err(DRAGDROP_S_DROP.into())
+    } else {
+      Ok(())
+    }
+  }
+
+  unsafe fn GiveFeedback(&self, _dwEffect: u32) -> windows::core::Result<()> {
+    Err(DRAGDROP_S_USEDEFAULTCURSORS.into())
+  }
+}
+
+/// Creates a shell data object holding `paths`, which provides `CF_HDROP` along with the formats
+/// Explorer expects.
+unsafe fn create_data_object(paths: &[PathBuf]) -> windows::core::Result<IDataObject> {
+  let mut pidls: Vec<*const ITEMIDLIST> = Vec::with_capacity(paths.len());
+  for path in paths {
+    let mut path = OsStr::new(path).encode_wide().collect::<Vec<_>>();
+    path.push(0);
+    let pidl = ILCreateFromPathW(PWSTR(path.as_mut_ptr()));
+    if pidl.is_null() {
+      pidls.iter().for_each(|pidl| ILFree(*pidl));
+      return Err(windows::core::Error::from_win32());
+    }
+    pidls.push(pidl);
+  }
+
+  let data_object = SHCreateShellItemArrayFromIDLists(pidls.len() as u32, pidls.as_ptr())
+    .and_then(|items| items.BindToHandler(None::<IBindCtx>, &BHID_DataObject));
+  pidls.iter().for_each(|pidl| ILFree(*pidl));
+  data_object
+}
+
+/// Drags `paths` out of the application and blocks until they are dropped or the drag is
+/// cancelled. `DoDragDrop` runs its own message loop, so windows keep being serviced meanwhile.
+/// Moving is not offered to the drop target so it never deletes the dragged files.
+///
+/// Must be called on the thread owning the windows, while a mouse button is held.
+pub fn start_drag(paths: &[PathBuf]) -> Result<DropEffect, io::Error> {
+  unsafe {
+    // Balanced below if it succeeds, `S_FALSE` included, as OLE might already be initialized for
+    // the drop target.
+    let ole_initialized = OleInitialize(ptr::null_mut()).is_ok();
+
+    let result = create_data_object(paths).and_then(|data_object| {
+      let drop_source: IDropSource = DragSource.into();
+      let mut effect = DROPEFFECT_NONE;
+      let hr = DoDragDrop(
+        data_object,
+        drop_source,
+        DROPEFFECT_COPY | DROPEFFECT_LINK,
+        &mut effect,
+      );
+      if hr == DRAGDROP_S_DROP {
+        Ok(effect)
+      } else {
+        hr.ok().map(|_| DROPEFFECT_NONE)
+      }
+    });
+
+    if ole_initialized {
+      OleUninitialize();
+    }
+
+    let effect = result?;
+    Ok(if effect & DROPEFFECT_COPY != 0 {
+      DropEffect::Copy
+    } else if effect & DROPEFFECT_LINK != 0 {
+      DropEffect::Link
+    } else {
+      DropEffect::None
+    })
+  }
+}
@@ -5,11 +5,10 @@
 
 mod runner;
 
-use crossbeam_channel::{self as channel, Receiver, Sender};
 use parking_lot::Mutex;
 use std::{
-  cell::Cell,
-  collections::VecDeque,
+  cell::{Cell, RefCell},
+  collections::{HashSet, VecDeque},
   marker::PhantomData,
   mem, panic, ptr,
   rc::Rc,
@@ -22,16 +21,31 @@ use windows::Win32::{
   Foundation::{
     BOOL, HANDLE, HINSTANCE, HWND, LPARAM, LRESULT, POINT, PWSTR, RECT, WAIT_TIMEOUT, WPARAM,
   },
+  Globalization::HIMC,
   Graphics::Gdi::*,
   System::{
     LibraryLoader::GetModuleHandleW,
     Ole::{IDropTarget, RevokeDragDrop},
+    Power::{
+      RegisterPowerSettingNotification, UnregisterPowerSettingNotification,
+      DEVICE_NOTIFY_WINDOW_HANDLE, HPOWERNOTIFY, POWERBROADCAST_SETTING,
+    },
+    SystemServices::{GUID_ACDC_POWER_SOURCE, GUID_LIDSWITCH_STATE_CHANGE, GUID_MONITOR_POWER_ON},
     Threading::GetCurrentThreadId,
     WindowsProgramming::INFINITE,
   },
   UI::{
     Controls::{self as win32c, HOVER_DEFAULT},
-    Input::{KeyboardAndMouse::*, Pointer::*, Touch::*, *},
+    Input::{
+      Ime::{
+        ImmAssociateContext, ImmDestroyContext, ImmGetContext, ImmNotifyIME, ImmReleaseContext,
+        CPS_COMPLETE, NI_COMPOSITIONSTR,
+      },
+      KeyboardAndMouse::*,
+      Pointer::*,
+      Touch::*,
+      *,
+    },
     Shell::{DefSubclassProc, RemoveWindowSubclass, SetWindowSubclass},
     WindowsAndMessaging::{self as win32wm, *},
   },
@@ -40,8 +54,11 @@ use windows::Win32::{
 use crate::{
   accelerator::AcceleratorId,
   dpi::{PhysicalPosition, PhysicalSize},
-  event::{DeviceEvent, Event, Force, RawKeyEvent, Touch, TouchPhase, WindowEvent},
-  event_loop::{ControlFlow, EventLoopClosed, EventLoopWindowTarget as RootELW},
+  event::{
+    DeviceEvent, Event, Force, MouseButton, PowerSetting, PowerSource, RawKeyEvent, Touch,
+    TouchPhase, WindowEvent,
+  },
+  event_loop::{ControlFlow, DeviceEventFilter, EventLoopClosed, EventLoopWindowTarget as RootELW},
   keyboard::{KeyCode, ModifiersState},
   monitor::MonitorHandle as RootMonitorHandle,
   platform_impl::platform::{
@@ -53,10 +70,11 @@ use crate::{
     minimal_ime::is_msg_ime_related,
     monitor::{self, MonitorHandle},
     raw_input, util,
-    window_state::{CursorFlags, WindowFlags, WindowState},
-    wrap_device_id, WindowId, DEVICE_ID,
+    window::hit_test_with_inset,
+    window_state::{CursorFlags, HitTestCursor, TouchSource, WindowFlags, WindowState},
+    wrap_device_id, WaitPrecision, WindowId, DEVICE_ID,
   },
-  window::{Fullscreen, WindowId as RootWindowId},
+  window::{Fullscreen, WindowId as RootWindowId, BORDERLESS_RESIZE_INSET},
 };
 use runner::{EventLoopRunner, EventLoopRunnerShared};
 
@@ -94,8 +112,19 @@ lazy_static! {
     get_function!("user32.dll", GetPointerPenInfo);
 }
 
+/// Whether the APIs used to handle `WM_POINTER*` messages are available (Windows 8 and newer).
+pub(crate) fn is_pointer_input_available() -> bool {
+  GET_POINTER_FRAME_INFO_HISTORY.is_some()
+    && SKIP_POINTER_FRAME_MESSAGES.is_some()
+    && GET_POINTER_DEVICE_RECTS.is_some()
+}
+
 pub(crate) struct SubclassInput<T: 'static> {
   pub window_state: Arc<Mutex<WindowState>>,
+  /// Messages passed to `DefWindowProcW` rather than `DefSubclassProc` when tao doesn't handle
+  /// them, see `WindowExtWindows::set_force_def_window_proc`. They are only updated on the event
+  /// loop thread, through `SET_FORCE_DEF_WINDOW_PROC_MSG_ID`.
+  pub forced_def_window_proc: RefCell<HashSet<u32>>,
   pub event_loop_runner: EventLoopRunnerShared<T>,
   pub _file_drop_handler: Option<IDropTarget>,
   pub subclass_removed: Cell<bool>,
@@ -110,7 +139,6 @@ impl<T> SubclassInput<T> {
 
 struct ThreadMsgTargetSubclassInput<T: 'static> {
   event_loop_runner: EventLoopRunnerShared<T>,
-  user_event_receiver: Receiver<T>,
 }
 
 impl<T> ThreadMsgTargetSubclassInput<T> {
@@ -127,8 +155,10 @@ pub(crate) enum ProcResult {
 }
 
 pub struct EventLoop<T: 'static> {
-  thread_msg_sender: Sender<T>,
   window_target: RootELW<T>,
+  power_notifications: Vec<HPOWERNOTIFY>,
+  /// Cleared once `run_return` exits, so the proxies stop queueing events no one will receive.
+  alive: Arc<Mutex<bool>>,
 }
 
 #[derive(Clone)]
@@ -182,17 +212,23 @@ impl<T: 'static> EventLoop<T> {
 
     let runner_shared = Rc::new(EventLoopRunner::new(thread_msg_target, wait_thread_id));
 
-    let thread_msg_sender = subclass_event_target_window(thread_msg_target, runner_shared.clone());
-    raw_input::register_all_mice_and_keyboards_for_raw_input(thread_msg_target);
+    subclass_event_target_window(thread_msg_target, runner_shared.clone());
+    raw_input::register_all_mice_and_keyboards_for_raw_input(
+      thread_msg_target,
+      DeviceEventFilter::default(),
+    );
+    let power_notifications = register_power_setting_notifications(thread_msg_target);
 
     EventLoop {
-      thread_msg_sender,
+      power_notifications,
+      alive: Arc::new(Mutex::new(true)),
       window_target: RootELW {
         p: EventLoopWindowTarget {
           thread_id,
           thread_msg_target,
           runner_shared,
         },
+        pending_control_flow: Default::default(),
         _marker: PhantomData,
       },
     }
@@ -214,6 +250,7 @@ impl<T: 'static> EventLoop<T> {
   where
     F: FnMut(Event<'_, T>, &RootELW<T>, &mut ControlFlow),
   {
+    *self.alive.lock() = true;
     let event_loop_windows_ref = &self.window_target;
 
     unsafe {
@@ -238,7 +275,7 @@ impl<T: 'static> EventLoop<T> {
         }
 
         // global accelerator
-        if msg.message == WM_HOTKEY {
+        if msg.message == WM_HOTKEY && msg.hwnd.is_invalid() {
           let event_loop_runner = self.window_target.p.runner_shared.clone();
           event_loop_runner.send_event(Event::GlobalShortcutEvent(AcceleratorId(
             msg.wParam.0 as u16,
@@ -256,6 +293,7 @@ impl<T: 'static> EventLoop<T> {
         }
 
         if let Err(payload) = runner.take_panic_error() {
+          release_system_mouse();
           runner.reset_runner();
           panic::resume_unwind(payload);
         }
@@ -270,7 +308,11 @@ impl<T: 'static> EventLoop<T> {
 
     unsafe {
       runner.loop_destroyed();
+      release_system_mouse();
     }
+    // No event can be posted once this is cleared, so the queue is drained for good.
+    *self.alive.lock() = false;
+    unsafe { drop_pending_user_events::<T>(self.window_target.p.thread_msg_target) };
     runner.reset_runner();
     exit_code
   }
@@ -278,12 +320,33 @@ impl<T: 'static> EventLoop<T> {
   pub fn create_proxy(&self) -> EventLoopProxy<T> {
     EventLoopProxy {
       target_window: self.window_target.p.thread_msg_target,
-      event_send: self.thread_msg_sender.clone(),
+      alive: self.alive.clone(),
+      _marker: PhantomData,
     }
   }
 }
 
 impl<T> EventLoopWindowTarget<T> {
+  #[inline(always)]
+  pub(crate) fn msg_hwnd(&self) -> HWND {
+    self.thread_msg_target
+  }
+
+  #[inline(always)]
+  pub(crate) fn set_poll_interval(&self, interval: Option<Duration>) {
+    self.runner_shared.set_poll_interval(interval);
+  }
+
+  #[inline(always)]
+  pub(crate) fn set_wait_precision(&self, precision: WaitPrecision) {
+    self.runner_shared.set_wait_precision(precision);
+  }
+
+  #[inline(always)]
+  pub(crate) fn set_buffered_raw_input(&self, buffered: bool) {
+    self.runner_shared.set_buffered_raw_input(buffered);
+  }
+
   #[inline(always)]
   pub(crate) fn create_thread_executor(&self) -> EventLoopThreadExecutor {
     EventLoopThreadExecutor {
@@ -292,14 +355,37 @@ impl<T> EventLoopWindowTarget<T> {
     }
   }
 
-  // TODO: Investigate opportunities for caching
+  pub fn set_device_event_filter(&self, filter: DeviceEventFilter) {
+    raw_input::register_all_mice_and_keyboards_for_raw_input(self.thread_msg_target, filter);
+  }
+
   pub fn available_monitors(&self) -> VecDeque<MonitorHandle> {
-    monitor::available_monitors()
+    self.runner_shared.monitors().available
   }
 
   pub fn primary_monitor(&self) -> Option<RootMonitorHandle> {
-    let monitor = monitor::primary_monitor();
-    Some(RootMonitorHandle { inner: monitor })
+    self
+      .runner_shared
+      .monitors()
+      .primary
+      .map(|inner| RootMonitorHandle { inner })
+  }
+
+  pub(crate) fn all_outputs(&self) -> Vec<monitor::DisplayOutput> {
+    monitor::all_outputs()
+  }
+
+  #[cfg(feature = "testing")]
+  pub(crate) fn inject_window_event(
+    &self,
+    window_id: RootWindowId,
+    event: crate::event::WindowEvent<'static>,
+  ) {
+    unsafe {
+      self
+        .runner_shared
+        .send_event(Event::WindowEvent { window_id, event })
+    }
   }
 }
 
@@ -349,6 +435,7 @@ fn wait_thread(parent_thread_id: u32, msg_window_id: HWND) {
     );
 
     let mut wait_until_opt = None;
+    let mut wait_precision = WaitPrecision::default();
     'main: loop {
       // Zeroing out the message ensures that the `WaitUntilInstantBox` doesn't get
       // double-freed if `MsgWaitForMultipleObjectsEx` returns early and there aren't
@@ -369,6 +456,10 @@ fn wait_thread(parent_thread_id: u32, msg_window_id: HWND) {
 
       if msg.message == *WAIT_UNTIL_MSG_ID {
         wait_until_opt = Some(*WaitUntilInstantBox::from_raw(msg.lParam.0 as *mut _));
+        wait_precision = match msg.wParam.0 {
+          0 => WaitPrecision::HighPrecision,
+          _ => WaitPrecision::PowerSaving,
+        };
       } else if msg.message == *CANCEL_WAIT_UNTIL_MSG_ID {
         wait_until_opt = None;
       }
@@ -376,17 +467,21 @@ fn wait_thread(parent_thread_id: u32, msg_window_id: HWND) {
       if let Some(wait_until) = wait_until_opt {
         let now = Instant::now();
         if now < wait_until {
-          // MsgWaitForMultipleObjects tends to overshoot just a little bit. We subtract
-          // 1 millisecond from the requested time and spinlock for the remainder to
+          // MsgWaitForMultipleObjects tends to overshoot just a little bit. With high precision,
+          // we subtract 1 millisecond from the requested time and spinlock for the remainder to
           // compensate for that.
-          let resume_reason = MsgWaitForMultipleObjectsEx(
-            0,
-            ptr::null(),
-            dur2timeout(wait_until - now).saturating_sub(1),
-            QS_ALLEVENTS,
-            MWMO_INPUTAVAILABLE,
-          );
+          let timeout = match wait_precision {
+            WaitPrecision::HighPrecision => dur2timeout(wait_until - now).saturating_sub(1),
+            _ => dur2timeout(wait_until - now),
+          };
+          let resume_reason =
+            MsgWaitForMultipleObjectsEx(0, ptr::null(), timeout, QS_ALLEVENTS, MWMO_INPUTAVAILABLE);
           if resume_reason == WAIT_TIMEOUT {
+            if wait_precision == WaitPrecision::HighPrecision {
+              while Instant::now() < wait_until {
+                std::hint::spin_loop();
+              }
+            }
             PostMessageW(
               msg_window_id,
               *PROCESS_NEW_EVENTS_MSG_ID,
@@ -442,11 +537,36 @@ fn dur2timeout(dur: Duration) -> u32 {
 impl<T> Drop for EventLoop<T> {
   fn drop(&mut self) {
     unsafe {
+      for handle in self.power_notifications.drain(..) {
+        UnregisterPowerSettingNotification(handle);
+      }
+      // Events may have been sent to a loop that was never run.
+      *self.alive.lock() = false;
+      drop_pending_user_events::<T>(self.window_target.p.thread_msg_target);
       DestroyWindow(self.window_target.p.thread_msg_target);
     }
   }
 }
 
+/// Removes the user events still queued for the thread target window and drops them, as they
+/// won't be received anymore.
+///
+/// Must be called on the thread of the event loop, after marking it as no longer alive.
+unsafe fn drop_pending_user_events<T>(target_window: HWND) {
+  let mut msg = MSG::default();
+  while PeekMessageW(
+    &mut msg,
+    target_window,
+    *USER_EVENT_MSG_ID,
+    *USER_EVENT_MSG_ID,
+    PM_REMOVE,
+  )
+  .as_bool()
+  {
+    drop(Box::<T>::from_raw(msg.wParam.0 as *mut T));
+  }
+}
+
 pub(crate) struct EventLoopThreadExecutor {
   thread_id: u32,
   target_window: HWND,
@@ -508,7 +628,8 @@ type ThreadExecFn = Box<Box<dyn FnMut()>>;
 
 pub struct EventLoopProxy<T: 'static> {
   target_window: HWND,
-  event_send: Sender<T>,
+  alive: Arc<Mutex<bool>>,
+  _marker: PhantomData<T>,
 }
 unsafe impl<T: Send + 'static> Send for EventLoopProxy<T> {}
 unsafe impl<T: Send + 'static> Sync for EventLoopProxy<T> {}
@@ -517,19 +638,37 @@ impl<T: 'static> Clone for EventLoopProxy<T> {
   fn clone(&self) -> Self {
     Self {
       target_window: self.target_window,
-      event_send: self.event_send.clone(),
+      alive: self.alive.clone(),
+      _marker: PhantomData,
     }
   }
 }
 
 impl<T: 'static> EventLoopProxy<T> {
   pub fn send_event(&self, event: T) -> Result<(), EventLoopClosed<T>> {
+    // The thread target window outlives `run_return`, posting to it would succeed even though
+    // the event is never received. The lock is held while posting, so that the loop can't exit
+    // between the check and the post without dropping the event.
+    let alive = self.alive.lock();
+    if !*alive {
+      return Err(EventLoopClosed(event));
+    }
+
+    // The event travels with the message itself, so it is delivered in the order it was posted
+    // relative to the closures of `EventLoopThreadExecutor::execute_in_thread`.
     unsafe {
-      if PostMessageW(self.target_window, *USER_EVENT_MSG_ID, WPARAM(0), LPARAM(0)).as_bool() {
-        self.event_send.send(event).ok();
+      let event = Box::into_raw(Box::new(event));
+      if PostMessageW(
+        self.target_window,
+        *USER_EVENT_MSG_ID,
+        WPARAM(event as _),
+        LPARAM(0),
+      )
+      .as_bool()
+      {
         Ok(())
       } else {
-        Err(EventLoopClosed(event))
+        Err(EventLoopClosed(*Box::from_raw(event)))
       }
     }
   }
@@ -538,8 +677,9 @@ impl<T: 'static> EventLoopProxy<T> {
 type WaitUntilInstantBox = Box<Instant>;
 
 lazy_static! {
-    // Message sent by the `EventLoopProxy` when we want to wake up the thread.
-    // WPARAM and LPARAM are unused.
+    // Message posted by the `EventLoopProxy` to send a user event.
+    // WPARAM contains a `Box<T>` that must be retrieved with `Box::from_raw`, and LPARAM is
+    // unused.
     static ref USER_EVENT_MSG_ID: u32 = {
         unsafe {
             RegisterWindowMessageA("Tao::WakeupMsg")
@@ -588,6 +728,26 @@ lazy_static! {
     pub static ref SET_RETAIN_STATE_ON_SIZE_MSG_ID: u32 = unsafe {
         RegisterWindowMessageA("Tao::SetRetainMaximized")
     };
+    // Message posted by a `Window` once it's created, so the initial `Focused` event comes
+    // after the events sent while the window was set up. WPARAM and LPARAM are unused.
+    pub static ref REPORT_INITIAL_FOCUS_MSG_ID: u32 = unsafe {
+        RegisterWindowMessageA("Tao::ReportInitialFocus")
+    };
+    // Message sent by `Window::set_decorations` when the decorations are toggled, to emit
+    // `DecorationsChanged`. WPARAM is whether the window is now decorated, LPARAM is unused.
+    pub static ref DECORATIONS_CHANGED_MSG_ID: u32 = unsafe {
+        RegisterWindowMessageA("Tao::DecorationsChanged")
+    };
+    // Message sent by `Window::set_ime_allowed` when the input method is toggled, to emit
+    // `Ime::Enabled` or `Ime::Disabled`. WPARAM is whether it is now allowed, LPARAM is unused.
+    pub static ref IME_ALLOWED_CHANGED_MSG_ID: u32 = unsafe {
+        RegisterWindowMessageA("Tao::ImeAllowedChanged")
+    };
+    // Message sent by `Window::set_force_def_window_proc`. WPARAM is the message, LPARAM is
+    // whether it is passed to `DefWindowProcW`.
+    pub static ref SET_FORCE_DEF_WINDOW_PROC_MSG_ID: u32 = unsafe {
+        RegisterWindowMessageA("Tao::SetForceDefWindowProc")
+    };
     static ref THREAD_EVENT_TARGET_WINDOW_CLASS: Vec<u16> = unsafe {
         let mut class_name= util::to_wstring("Tao Thread Event Target");
 
@@ -612,6 +772,14 @@ lazy_static! {
     };
 }
 
+/// Releases the mouse capture and cursor clipping a window may still hold, so they aren't left
+/// applied to the whole system once the event loop exits, be it normally or by unwinding a panic.
+/// A clip set by another application is left alone.
+unsafe fn release_system_mouse() {
+  ReleaseCapture();
+  util::release_cursor_clip();
+}
+
 fn create_event_target_window() -> HWND {
   let window = unsafe {
     CreateWindowExW(
@@ -640,17 +808,9 @@ fn create_event_target_window() -> HWND {
   window
 }
 
-fn subclass_event_target_window<T>(
-  window: HWND,
-  event_loop_runner: EventLoopRunnerShared<T>,
-) -> Sender<T> {
+fn subclass_event_target_window<T>(window: HWND, event_loop_runner: EventLoopRunnerShared<T>) {
   unsafe {
-    let (tx, rx) = channel::unbounded();
-
-    let subclass_input = ThreadMsgTargetSubclassInput {
-      event_loop_runner,
-      user_event_receiver: rx,
-    };
+    let subclass_input = ThreadMsgTargetSubclassInput { event_loop_runner };
     let input_ptr = Box::into_raw(Box::new(subclass_input));
     let subclass_result = SetWindowSubclass(
       window,
@@ -659,8 +819,44 @@ fn subclass_event_target_window<T>(
       input_ptr as usize,
     );
     assert!(subclass_result.as_bool());
+  }
+}
+
+fn register_power_setting_notifications(window: HWND) -> Vec<HPOWERNOTIFY> {
+  [
+    GUID_MONITOR_POWER_ON,
+    GUID_LIDSWITCH_STATE_CHANGE,
+    GUID_ACDC_POWER_SOURCE,
+  ]
+  .iter()
+  .map(|guid| unsafe {
+    RegisterPowerSettingNotification(HANDLE(window.0), guid, DEVICE_NOTIFY_WINDOW_HANDLE)
+  })
+  .filter(|handle| !handle.is_invalid())
+  .collect()
+}
 
-    tx
+/// Converts the payload of a `PBT_POWERSETTINGCHANGE` broadcast into a `PowerSetting`.
+unsafe fn power_setting_from_broadcast(setting: &POWERBROADCAST_SETTING) -> Option<PowerSetting> {
+  if (setting.DataLength as usize) < mem::size_of::<u32>() {
+    return None;
+  }
+  let value = ptr::read_unaligned(setting.Data.as_ptr() as *const u32);
+
+  if setting.PowerSetting == GUID_MONITOR_POWER_ON {
+    Some(PowerSetting::MonitorPower(value != 0))
+  } else if setting.PowerSetting == GUID_LIDSWITCH_STATE_CHANGE {
+    Some(PowerSetting::LidOpen(value != 0))
+  } else if setting.PowerSetting == GUID_ACDC_POWER_SOURCE {
+    let source = match value {
+      0 => PowerSource::Ac,
+      1 => PowerSource::Battery,
+      2 => PowerSource::ShortTerm,
+      _ => return None,
+    };
+    Some(PowerSetting::PowerSource(source))
+  } else {
+    None
   }
 }
 
@@ -678,21 +874,35 @@ fn remove_event_target_window_subclass<T: 'static>(window: HWND) {
 /// Capture mouse input, allowing `window` to receive mouse events when the cursor is outside of
 /// the window.
 unsafe fn capture_mouse(window: HWND, window_state: &mut WindowState) {
-  window_state.mouse.capture_count += 1;
+  window_state.mouse.capture();
   SetCapture(window);
 }
 
 /// Release mouse input, stopping windows on this thread from receiving mouse input when the cursor
 /// is outside the window.
 unsafe fn release_mouse(mut window_state: parking_lot::MutexGuard<'_, WindowState>) {
-  window_state.mouse.capture_count = window_state.mouse.capture_count.saturating_sub(1);
-  if window_state.mouse.capture_count == 0 {
+  if window_state.mouse.release_capture() {
     // ReleaseCapture() causes a WM_CAPTURECHANGED where we lock the window_state.
     drop(window_state);
     ReleaseCapture();
   }
 }
 
+/// Records a press of `button` at the position held in `lparam`, returning its click count.
+unsafe fn register_press(
+  window_state: &mut WindowState,
+  button: MouseButton,
+  lparam: LPARAM,
+) -> u32 {
+  let position = POINT {
+    x: i32::from(util::GET_X_LPARAM(lparam)),
+    y: i32::from(util::GET_Y_LPARAM(lparam)),
+  };
+  window_state
+    .mouse
+    .register_press(button, position, GetMessageTime() as u32)
+}
+
 const WINDOW_SUBCLASS_ID: usize = 0;
 const THREAD_EVENT_TARGET_SUBCLASS_ID: usize = 1;
 pub(crate) fn subclass_window<T>(window: HWND, subclass_input: SubclassInput<T>) {
@@ -775,20 +985,32 @@ unsafe fn flush_paint_messages<T: 'static>(
 
 unsafe fn process_control_flow<T: 'static>(runner: &EventLoopRunner<T>) {
   match runner.control_flow() {
-    ControlFlow::Poll => {
-      PostMessageW(
-        runner.thread_msg_target(),
-        *PROCESS_NEW_EVENTS_MSG_ID,
-        WPARAM(0),
-        LPARAM(0),
-      );
-    }
+    ControlFlow::Poll => match runner.poll_interval() {
+      // Wait for the interval on the wait thread, like `WaitUntil` does, so new OS events still
+      // start the next iteration right away.
+      Some(interval) => {
+        PostThreadMessageW(
+          runner.wait_thread_id(),
+          *WAIT_UNTIL_MSG_ID,
+          WPARAM(runner.wait_precision() as _),
+          LPARAM(Box::into_raw(WaitUntilInstantBox::new(Instant::now() + interval)) as _),
+        );
+      }
+      None => {
+        PostMessageW(
+          runner.thread_msg_target(),
+          *PROCESS_NEW_EVENTS_MSG_ID,
+          WPARAM(0),
+          LPARAM(0),
+        );
+      }
+    },
     ControlFlow::Wait => (),
     ControlFlow::WaitUntil(until) => {
       PostThreadMessageW(
         runner.wait_thread_id(),
         *WAIT_UNTIL_MSG_ID,
-        WPARAM(0),
+        WPARAM(runner.wait_precision() as _),
         LPARAM(Box::into_raw(WaitUntilInstantBox::new(until)) as _),
       );
     }
@@ -799,17 +1021,23 @@ unsafe fn process_control_flow<T: 'static>(runner: &EventLoopRunner<T>) {
 /// Emit a `ModifiersChanged` event whenever modifiers have changed.
 /// Returns the current modifier state
 fn update_modifiers<T>(window: HWND, subclass_input: &SubclassInput<T>) -> ModifiersState {
-  use crate::event::WindowEvent::ModifiersChanged;
-
   let modifiers = {
     let mut layouts = LAYOUT_CACHE.lock().unwrap();
     layouts.get_agnostic_mods()
   };
+  set_modifiers(window, subclass_input, modifiers)
+}
 
-  let mut window_state = subclass_input.window_state.lock();
-  if window_state.modifiers_state != modifiers {
-    window_state.modifiers_state = modifiers;
+/// Same as `update_modifiers` but with an already known modifier state.
+fn set_modifiers<T>(
+  window: HWND,
+  subclass_input: &SubclassInput<T>,
+  modifiers: ModifiersState,
+) -> ModifiersState {
+  use crate::event::WindowEvent::ModifiersChanged;
 
+  let mut window_state = subclass_input.window_state.lock();
+  if window_state.set_modifiers(modifiers) {
     // Drop lock
     drop(window_state);
 
@@ -823,13 +1051,70 @@ fn update_modifiers<T>(window: HWND, subclass_input: &SubclassInput<T>) -> Modif
   modifiers
 }
 
+/// Emit a `Resized` event, followed by a `SurfaceResized` event if the size is non-zero and
+/// differs from the last one reported.
+unsafe fn send_resized<T>(
+  window: HWND,
+  subclass_input: &SubclassInput<T>,
+  size: PhysicalSize<u32>,
+) {
+  use crate::event::WindowEvent::{Resized, SurfaceResized};
+
+  subclass_input.send_event(Event::WindowEvent {
+    window_id: RootWindowId(WindowId(window.0)),
+    event: Resized(size),
+  });
+
+  let surface_resized = size.width != 0
+    && size.height != 0
+    && subclass_input
+      .window_state
+      .lock()
+      .surface_size
+      .replace(size)
+      != Some(size);
+  if surface_resized {
+    subclass_input.send_event(Event::WindowEvent {
+      window_id: RootWindowId(WindowId(window.0)),
+      event: SurfaceResized(size),
+    });
+  }
+}
+
+/// Emit a `CursorLeft` event if the cursor was in the window.
+///
+/// Besides `WM_MOUSELEAVE`, this is used when the window is hidden or destroyed, since no
+/// `WM_MOUSELEAVE` is guaranteed to arrive then.
+fn cursor_left<T>(window: HWND, subclass_input: &SubclassInput<T>) {
+  use crate::event::WindowEvent::CursorLeft;
+
+  let was_in_window = {
+    let mut w = subclass_input.window_state.lock();
+    let was_in_window = w.mouse.cursor_flags().contains(CursorFlags::IN_WINDOW);
+    w.mouse
+      .set_cursor_flags(window, |f| f.set(CursorFlags::IN_WINDOW, false))
+      .ok();
+    was_in_window
+  };
+
+  if was_in_window {
+    unsafe {
+      subclass_input.send_event(Event::WindowEvent {
+        window_id: RootWindowId(WindowId(window.0)),
+        event: CursorLeft {
+          device_id: DEVICE_ID,
+        },
+      });
+    }
+  }
+}
+
 /// Any window whose callback is configured to this function will have its events propagated
 /// through the events loop of the thread the window was created in.
 //
 // This is the callback that is called by `DispatchMessage` in the events loop.
 //
 // Returning 0 tells the Win32 API that the message has been processed.
-// FIXME: detect WM_DWMCOMPOSITIONCHANGED and call DwmEnableBlurBehindWindow if necessary
 unsafe extern "system" fn public_window_callback<T: 'static>(
   window: HWND,
   msg: u32,
@@ -926,21 +1211,23 @@ unsafe fn public_window_callback_inner<T: 'static>(
     .unwrap_or_else(|| result = ProcResult::Value(LRESULT(-1)));
 
   let ime_callback = || {
-    use crate::event::WindowEvent::ReceivedImeText;
     let is_ime_related = is_msg_ime_related(msg);
     if !is_ime_related {
       return;
     }
-    let text = {
+    let events = {
       let mut window_state = subclass_input.window_state.lock();
+      if !window_state.ime_allowed {
+        return;
+      }
       window_state
         .ime_handler
         .process_message(window, msg, wparam, lparam, &mut result)
     };
-    if let Some(str) = text {
+    for event in events {
       subclass_input.send_event(Event::WindowEvent {
         window_id: RootWindowId(WindowId(window.0)),
-        event: ReceivedImeText(str),
+        event: WindowEvent::Ime(event),
       });
     }
   };
@@ -990,6 +1277,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
     win32wm::WM_DESTROY => {
       use crate::event::WindowEvent::Destroyed;
       let _ = RevokeDragDrop(window);
+      cursor_left(window, subclass_input);
       subclass_input.send_event(Event::WindowEvent {
         window_id: RootWindowId(WindowId(window.0)),
         event: Destroyed,
@@ -998,7 +1286,42 @@ unsafe fn public_window_callback_inner<T: 'static>(
       result = ProcResult::Value(LRESULT(0));
     }
 
+    win32wm::WM_INPUTLANGCHANGE => {
+      // The layout cache is keyed by layout handle, so it doesn't need to be refreshed. The
+      // layout is shared by the windows of the thread, each of them may be notified.
+      if subclass_input
+        .event_loop_runner
+        .set_keyboard_layout(lparam.0)
+      {
+        subclass_input.send_event(Event::KeyboardLayoutChanged);
+      }
+      result = ProcResult::DefSubclassProc;
+    }
+
+    win32wm::WM_SETTEXT => {
+      use crate::event::WindowEvent::TitleChanged;
+
+      let set = DefSubclassProc(window, msg, wparam, lparam);
+      if set.0 != 0 {
+        let title = if lparam.0 == 0 {
+          String::new()
+        } else {
+          util::wchar_ptr_to_string(PWSTR(lparam.0 as *mut u16))
+        };
+        subclass_input.send_event(Event::WindowEvent {
+          window_id: RootWindowId(WindowId(window.0)),
+          event: TitleChanged(title),
+        });
+      }
+      result = ProcResult::Value(set);
+    }
+
     win32wm::WM_NCDESTROY => {
+      let ime_context = mem::take(&mut subclass_input.window_state.lock().ime_context);
+      if !ime_context.is_invalid() {
+        ImmAssociateContext(window, HIMC::default());
+        ImmDestroyContext(ime_context);
+      }
       remove_window_subclass::<T>(window);
       subclass_input.subclass_removed.set(true);
       result = ProcResult::Value(LRESULT(0));
@@ -1011,6 +1334,8 @@ unsafe fn public_window_callback_inner<T: 'static>(
         RedrawWindow(window, ptr::null(), HRGN::default(), RDW_INTERNALPAINT);
       } else {
         let managing_redraw = flush_paint_messages(Some(window), &subclass_input.event_loop_runner);
+        // Cleared before the event is sent so a redraw requested while handling it isn't lost.
+        subclass_input.window_state.lock().redraw_pending = false;
         subclass_input.send_event(Event::RedrawRequested(RootWindowId(WindowId(window.0))));
         if managing_redraw {
           subclass_input.event_loop_runner.redraw_events_cleared();
@@ -1061,6 +1386,33 @@ unsafe fn public_window_callback_inner<T: 'static>(
             }
           }
         }
+      } else if !window_state
+        .window_flags()
+        .contains(WindowFlags::DECORATIONS)
+        && util::is_maximized(window)
+      {
+        // The system sizes maximized windows from `ptMaxSize`, which it interprets relative to
+        // the primary monitor, so a borderless window can end up larger or smaller than the
+        // monitor it maximizes on. Its rect is set to the area of that monitor instead.
+        let window_pos = &mut *(lparam.0 as *mut WINDOWPOS);
+        if window_pos.flags & (SWP_NOMOVE | SWP_NOSIZE) != (SWP_NOMOVE | SWP_NOSIZE) {
+          let new_rect = RECT {
+            left: window_pos.x,
+            top: window_pos.y,
+            right: window_pos.x + window_pos.cx,
+            bottom: window_pos.y + window_pos.cy,
+          };
+          let new_monitor = MonitorFromRect(&new_rect, MONITOR_DEFAULTTONEAREST);
+          if let Some((rect, _)) =
+            monitor::monitor_maximized_rect(new_monitor, window_state.maximize_mode)
+          {
+            window_pos.x = rect.left;
+            window_pos.y = rect.top;
+            window_pos.cx = rect.right - rect.left;
+            window_pos.cy = rect.bottom - rect.top;
+            window_pos.flags &= !(SWP_NOMOVE | SWP_NOSIZE);
+          }
+        }
       }
 
       result = ProcResult::Value(LRESULT(0));
@@ -1073,28 +1425,42 @@ unsafe fn public_window_callback_inner<T: 'static>(
       let windowpos = lparam.0 as *const WINDOWPOS;
       if (*windowpos).flags & SWP_NOMOVE != SWP_NOMOVE {
         let physical_position = PhysicalPosition::new((*windowpos).x as i32, (*windowpos).y as i32);
-        subclass_input.send_event(Event::WindowEvent {
-          window_id: RootWindowId(WindowId(window.0)),
-          event: Moved(physical_position),
-        });
+        // `SWP_NOMOVE` isn't always set when the position is unchanged.
+        let moved = subclass_input
+          .window_state
+          .lock()
+          .set_moved(physical_position);
+        if moved {
+          subclass_input.send_event(Event::WindowEvent {
+            window_id: RootWindowId(WindowId(window.0)),
+            event: Moved(physical_position),
+          });
+        }
       }
 
-      // This is necessary for us to still get sent WM_SIZE.
+      // The cursor clip doesn't follow the window, so it's updated to the new client area, which
+      // for a maximized borderless window is the work area set by `WM_NCCALCSIZE`.
+      if (*windowpos).flags & (SWP_NOMOVE | SWP_NOSIZE) != (SWP_NOMOVE | SWP_NOSIZE) {
+        let mut w = subclass_input.window_state.lock();
+        if w.mouse.cursor_flags().contains(CursorFlags::GRABBED) {
+          w.mouse.set_cursor_flags(window, |_| ()).ok();
+        }
+      }
+
+      // This is necessary for us to still get sent WM_SIZE. `DefWindowProc` sends it synchronously,
+      // so `Resized` follows `Moved` when both changed in the same operation, though `Minimized`
+      // may come in between.
       result = ProcResult::DefSubclassProc;
     }
 
     win32wm::WM_SIZE => {
-      use crate::event::WindowEvent::Resized;
       let w = u32::from(util::LOWORD(lparam.0 as u32));
       let h = u32::from(util::HIWORD(lparam.0 as u32));
 
       let physical_size = PhysicalSize::new(w, h);
-      let event = Event::WindowEvent {
-        window_id: RootWindowId(WindowId(window.0)),
-        event: Resized(physical_size),
-      };
 
-      {
+      let minimized = wparam.0 == win32wm::SIZE_MINIMIZED as _;
+      let (in_size_transition, minimized_changed) = {
         let mut w = subclass_input.window_state.lock();
         // See WindowFlags::MARKER_RETAIN_STATE_ON_SIZE docs for info on why this `if` check exists.
         if !w
@@ -1104,9 +1470,31 @@ unsafe fn public_window_callback_inner<T: 'static>(
           let maximized = wparam.0 == win32wm::SIZE_MAXIMIZED as _;
           w.set_window_flags_in_place(|f| f.set(WindowFlags::MAXIMIZED, maximized));
         }
+        // Minimizing doesn't always go through `WM_SYSCOMMAND`, e.g. with `ShowWindow`, but it
+        // always results in a `WM_SIZE`.
+        w.set_window_flags_in_place(|f| f.set(WindowFlags::MINIMIZED, minimized));
+        // A minimized window isn't painted, the pending redraw would swallow later requests.
+        if minimized {
+          w.redraw_pending = false;
+        }
+        (
+          w.window_flags()
+            .contains(WindowFlags::MARKER_IN_SIZE_TRANSITION),
+          mem::replace(&mut w.minimized, minimized) != minimized,
+        )
+      };
+
+      if minimized_changed {
+        subclass_input.send_event(Event::WindowEvent {
+          window_id: RootWindowId(WindowId(window.0)),
+          event: WindowEvent::Minimized(minimized),
+        });
       }
 
-      subclass_input.send_event(event);
+      // The final size is reported by `WM_SYSCOMMAND` once the transition is over.
+      if !in_size_transition {
+        send_resized(window, subclass_input, physical_size);
+      }
       result = ProcResult::Value(LRESULT(0));
     }
 
@@ -1120,8 +1508,6 @@ unsafe fn public_window_callback_inner<T: 'static>(
         let mut w = subclass_input.window_state.lock();
         w.set_window_flags_in_place(|f| f.set(WindowFlags::MINIMIZED, true));
       }
-      // Send `WindowEvent::Minimized` here if we decide to implement one
-
       if wparam.0 == SC_SCREENSAVE as _ {
         let window_state = subclass_input.window_state.lock();
         if window_state.fullscreen.is_some() {
@@ -1130,6 +1516,41 @@ unsafe fn public_window_callback_inner<T: 'static>(
         }
       }
 
+      // The low four bits are used internally by the system.
+      let command = wparam.0 as u32 & 0xFFF0;
+      if command == SC_MAXIMIZE || command == SC_RESTORE {
+        let client_size = || {
+          util::get_client_rect(window)
+            .map(|rect| {
+              PhysicalSize::new(
+                (rect.right - rect.left) as u32,
+                (rect.bottom - rect.top) as u32,
+              )
+            })
+            .ok()
+        };
+        let old_size = client_size();
+
+        // The default procedure runs the whole, possibly animated, transition before returning.
+        subclass_input
+          .window_state
+          .lock()
+          .set_window_flags_in_place(|f| f.insert(WindowFlags::MARKER_IN_SIZE_TRANSITION));
+        DefWindowProcW(window, msg, wparam, lparam);
+        subclass_input
+          .window_state
+          .lock()
+          .set_window_flags_in_place(|f| f.remove(WindowFlags::MARKER_IN_SIZE_TRANSITION));
+
+        match client_size() {
+          Some(size) if Some(size) != old_size => send_resized(window, subclass_input, size),
+          _ => (),
+        }
+
+        result = ProcResult::Value(LRESULT(0));
+        return;
+      }
+
       result = ProcResult::DefWindowProc;
     }
 
@@ -1142,6 +1563,11 @@ unsafe fn public_window_callback_inner<T: 'static>(
         w.mouse
           .set_cursor_flags(window, |f| f.set(CursorFlags::IN_WINDOW, true))
           .ok();
+
+        if w.mouse.reapply_cursor_on_move() {
+          SetCursor(w.mouse.current_cursor());
+        }
+
         was_outside_window
       };
 
@@ -1190,24 +1616,19 @@ unsafe fn public_window_callback_inner<T: 'static>(
     }
 
     win32c::WM_MOUSELEAVE => {
-      use crate::event::WindowEvent::CursorLeft;
-      {
-        let mut w = subclass_input.window_state.lock();
-        w.mouse
-          .set_cursor_flags(window, |f| f.set(CursorFlags::IN_WINDOW, false))
-          .ok();
-      }
-
-      subclass_input.send_event(Event::WindowEvent {
-        window_id: RootWindowId(WindowId(window.0)),
-        event: CursorLeft {
-          device_id: DEVICE_ID,
-        },
-      });
-
+      cursor_left(window, subclass_input);
       result = ProcResult::Value(LRESULT(0));
     }
 
+    win32wm::WM_SHOWWINDOW => {
+      if wparam.0 == 0 {
+        cursor_left(window, subclass_input);
+        // A hidden window isn't painted, the pending redraw would swallow later requests.
+        subclass_input.window_state.lock().redraw_pending = false;
+      }
+      result = ProcResult::DefSubclassProc;
+    }
+
     win32wm::WM_MOUSEWHEEL => {
       use crate::event::MouseScrollDelta::LineDelta;
 
@@ -1256,10 +1677,15 @@ unsafe fn public_window_callback_inner<T: 'static>(
       }
     }
 
-    win32wm::WM_LBUTTONDOWN => {
+    // With `CS_DBLCLKS`, the second press of a double click comes as `WM_LBUTTONDBLCLK`.
+    win32wm::WM_LBUTTONDOWN | win32wm::WM_LBUTTONDBLCLK => {
       use crate::event::{ElementState::Pressed, MouseButton::Left, WindowEvent::MouseInput};
 
-      capture_mouse(window, &mut *subclass_input.window_state.lock());
+      let click_count = {
+        let mut window_state = subclass_input.window_state.lock();
+        capture_mouse(window, &mut *window_state);
+        register_press(&mut *window_state, Left, lparam)
+      };
 
       let modifiers = update_modifiers(window, subclass_input);
 
@@ -1269,6 +1695,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Pressed,
           button: Left,
+          click_count,
           modifiers,
         },
       });
@@ -1278,7 +1705,12 @@ unsafe fn public_window_callback_inner<T: 'static>(
     win32wm::WM_LBUTTONUP => {
       use crate::event::{ElementState::Released, MouseButton::Left, WindowEvent::MouseInput};
 
-      release_mouse(subclass_input.window_state.lock());
+      let click_count = {
+        let window_state = subclass_input.window_state.lock();
+        let click_count = window_state.mouse.release_click_count(Left);
+        release_mouse(window_state);
+        click_count
+      };
 
       let modifiers = update_modifiers(window, subclass_input);
 
@@ -1288,16 +1720,21 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Released,
           button: Left,
+          click_count,
           modifiers,
         },
       });
       result = ProcResult::Value(LRESULT(0));
     }
 
-    win32wm::WM_RBUTTONDOWN => {
+    win32wm::WM_RBUTTONDOWN | win32wm::WM_RBUTTONDBLCLK => {
       use crate::event::{ElementState::Pressed, MouseButton::Right, WindowEvent::MouseInput};
 
-      capture_mouse(window, &mut *subclass_input.window_state.lock());
+      let click_count = {
+        let mut window_state = subclass_input.window_state.lock();
+        capture_mouse(window, &mut *window_state);
+        register_press(&mut *window_state, Right, lparam)
+      };
 
       let modifiers = update_modifiers(window, subclass_input);
 
@@ -1307,6 +1744,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Pressed,
           button: Right,
+          click_count,
           modifiers,
         },
       });
@@ -1316,7 +1754,12 @@ unsafe fn public_window_callback_inner<T: 'static>(
     win32wm::WM_RBUTTONUP => {
       use crate::event::{ElementState::Released, MouseButton::Right, WindowEvent::MouseInput};
 
-      release_mouse(subclass_input.window_state.lock());
+      let click_count = {
+        let window_state = subclass_input.window_state.lock();
+        let click_count = window_state.mouse.release_click_count(Right);
+        release_mouse(window_state);
+        click_count
+      };
 
       let modifiers = update_modifiers(window, subclass_input);
 
@@ -1326,16 +1769,21 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Released,
           button: Right,
+          click_count,
           modifiers,
         },
       });
       result = ProcResult::Value(LRESULT(0));
     }
 
-    win32wm::WM_MBUTTONDOWN => {
+    win32wm::WM_MBUTTONDOWN | win32wm::WM_MBUTTONDBLCLK => {
       use crate::event::{ElementState::Pressed, MouseButton::Middle, WindowEvent::MouseInput};
 
-      capture_mouse(window, &mut *subclass_input.window_state.lock());
+      let click_count = {
+        let mut window_state = subclass_input.window_state.lock();
+        capture_mouse(window, &mut *window_state);
+        register_press(&mut *window_state, Middle, lparam)
+      };
 
       let modifiers = update_modifiers(window, subclass_input);
 
@@ -1345,6 +1793,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Pressed,
           button: Middle,
+          click_count,
           modifiers,
         },
       });
@@ -1354,7 +1803,12 @@ unsafe fn public_window_callback_inner<T: 'static>(
     win32wm::WM_MBUTTONUP => {
       use crate::event::{ElementState::Released, MouseButton::Middle, WindowEvent::MouseInput};
 
-      release_mouse(subclass_input.window_state.lock());
+      let click_count = {
+        let window_state = subclass_input.window_state.lock();
+        let click_count = window_state.mouse.release_click_count(Middle);
+        release_mouse(window_state);
+        click_count
+      };
 
       let modifiers = update_modifiers(window, subclass_input);
 
@@ -1364,17 +1818,22 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Released,
           button: Middle,
+          click_count,
           modifiers,
         },
       });
       result = ProcResult::Value(LRESULT(0));
     }
 
-    win32wm::WM_XBUTTONDOWN => {
+    win32wm::WM_XBUTTONDOWN | win32wm::WM_XBUTTONDBLCLK => {
       use crate::event::{ElementState::Pressed, MouseButton::Other, WindowEvent::MouseInput};
       let xbutton = util::GET_XBUTTON_WPARAM(wparam);
 
-      capture_mouse(window, &mut *subclass_input.window_state.lock());
+      let click_count = {
+        let mut window_state = subclass_input.window_state.lock();
+        capture_mouse(window, &mut *window_state);
+        register_press(&mut *window_state, Other(xbutton), lparam)
+      };
 
       let modifiers = update_modifiers(window, subclass_input);
 
@@ -1384,6 +1843,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Pressed,
           button: Other(xbutton),
+          click_count,
           modifiers,
         },
       });
@@ -1394,7 +1854,12 @@ unsafe fn public_window_callback_inner<T: 'static>(
       use crate::event::{ElementState::Released, MouseButton::Other, WindowEvent::MouseInput};
       let xbutton = util::GET_XBUTTON_WPARAM(wparam);
 
-      release_mouse(subclass_input.window_state.lock());
+      let click_count = {
+        let window_state = subclass_input.window_state.lock();
+        let click_count = window_state.mouse.release_click_count(Other(xbutton));
+        release_mouse(window_state);
+        click_count
+      };
 
       let modifiers = update_modifiers(window, subclass_input);
 
@@ -1404,6 +1869,7 @@ unsafe fn public_window_callback_inner<T: 'static>(
           device_id: DEVICE_ID,
           state: Released,
           button: Other(xbutton),
+          click_count,
           modifiers,
         },
       });
@@ -1415,9 +1881,11 @@ unsafe fn public_window_callback_inner<T: 'static>(
       // If it is the same as our window, then we're essentially retaining the capture. This
       // can happen if `SetCapture` is called on our window when it already has the mouse
       // capture.
-      if lparam.0 != window.0 {
-        subclass_input.window_state.lock().mouse.capture_count = 0;
-      }
+      subclass_input
+        .window_state
+        .lock()
+        .mouse
+        .capture_changed(lparam.0 == window.0);
       result = ProcResult::Value(LRESULT(0));
     }
 
@@ -1447,21 +1915,30 @@ unsafe fn public_window_callback_inner<T: 'static>(
           let x = location.x as f64 + (input.x % 100) as f64 / 100f64;
           let y = location.y as f64 + (input.y % 100) as f64 / 100f64;
           let location = PhysicalPosition::new(x, y);
+          let phase = if (input.dwFlags & TOUCHEVENTF_DOWN) != 0 {
+            TouchPhase::Started
+          } else if (input.dwFlags & TOUCHEVENTF_UP) != 0 {
+            TouchPhase::Ended
+          } else if (input.dwFlags & TOUCHEVENTF_MOVE) != 0 {
+            TouchPhase::Moved
+          } else {
+            continue;
+          };
+          let id = subclass_input.window_state.lock().touch_ids.contact_id(
+            TouchSource::Touch,
+            input.dwID,
+            phase,
+            location,
+          );
           subclass_input.send_event(Event::WindowEvent {
             window_id: RootWindowId(WindowId(window.0)),
             event: WindowEvent::Touch(Touch {
-              phase: if (input.dwFlags & TOUCHEVENTF_DOWN) != 0 {
-                TouchPhase::Started
-              } else if (input.dwFlags & TOUCHEVENTF_UP) != 0 {
-                TouchPhase::Ended
-              } else if (input.dwFlags & TOUCHEVENTF_MOVE) != 0 {
-                TouchPhase::Moved
-              } else {
-                continue;
-              },
+              phase,
               location,
               force: None, // WM_TOUCH doesn't support pressure information
-              id: input.dwID as u64,
+              tilt: None,
+              twist: None,
+              id,
               device_id: DEVICE_ID,
             }),
           });
@@ -1472,6 +1949,12 @@ unsafe fn public_window_callback_inner<T: 'static>(
     }
 
     win32wm::WM_POINTERDOWN | win32wm::WM_POINTERUPDATE | win32wm::WM_POINTERUP => {
+      if subclass_input.window_state.lock().legacy_touch {
+        // Let the default procedure turn the pointer messages into `WM_TOUCH`.
+        result = ProcResult::DefSubclassProc;
+        return;
+      }
+
       if let (
         Some(GetPointerFrameInfoHistory),
         Some(SkipPointerFrameMessages),
@@ -1555,6 +2038,19 @@ unsafe fn public_window_callback_inner<T: 'static>(
             continue;
           }
 
+          let pen_info = if pointer_info.pointerType == win32wm::PT_PEN {
+            GET_POINTER_PEN_INFO.and_then(|GetPointerPenInfo| {
+              let mut pen_info = mem::MaybeUninit::uninit();
+              if GetPointerPenInfo(pointer_info.pointerId, pen_info.as_mut_ptr()).as_bool() {
+                Some(pen_info.assume_init())
+              } else {
+                None
+              }
+            })
+          } else {
+            None
+          };
+
           let force = match pointer_info.pointerType {
             win32wm::PT_TOUCH => {
               let mut touch_info = mem::MaybeUninit::uninit();
@@ -1567,36 +2063,47 @@ unsafe fn public_window_callback_inner<T: 'static>(
               })
             }
             win32wm::PT_PEN => {
-              let mut pen_info = mem::MaybeUninit::uninit();
-              GET_POINTER_PEN_INFO.and_then(|GetPointerPenInfo| {
-                if GetPointerPenInfo(pointer_info.pointerId, pen_info.as_mut_ptr()).as_bool() {
-                  normalize_pointer_pressure(pen_info.assume_init().pressure)
-                } else {
-                  None
-                }
-              })
+              pen_info.and_then(|pen_info| normalize_pointer_pressure(pen_info.pressure))
             }
             _ => None,
           };
+          let tilt = pen_info
+            .filter(|pen_info| pen_info.penMask & (PEN_MASK_TILT_X | PEN_MASK_TILT_Y) != 0)
+            .map(|pen_info| (pen_info.tiltX as f64, pen_info.tiltY as f64));
+          let twist = pen_info
+            .filter(|pen_info| pen_info.penMask & PEN_MASK_ROTATION != 0)
+            .map(|pen_info| pen_info.rotation as f64);
 
           let x = location.x as f64 + x.fract();
           let y = location.y as f64 + y.fract();
           let location = PhysicalPosition::new(x, y);
+          // A cancelled contact, e.g. rejected as a palm, comes with `POINTER_FLAG_UP` as well.
+          let phase = if (pointer_info.pointerFlags & POINTER_FLAG_CANCELED) != 0 {
+            TouchPhase::Cancelled
+          } else if (pointer_info.pointerFlags & POINTER_FLAG_DOWN) != 0 {
+            TouchPhase::Started
+          } else if (pointer_info.pointerFlags & POINTER_FLAG_UP) != 0 {
+            TouchPhase::Ended
+          } else if (pointer_info.pointerFlags & POINTER_FLAG_UPDATE) != 0 {
+            TouchPhase::Moved
+          } else {
+            continue;
+          };
+          let id = subclass_input.window_state.lock().touch_ids.contact_id(
+            TouchSource::Pointer,
+            pointer_info.pointerId,
+            phase,
+            location,
+          );
           subclass_input.send_event(Event::WindowEvent {
             window_id: RootWindowId(WindowId(window.0)),
             event: WindowEvent::Touch(Touch {
-              phase: if (pointer_info.pointerFlags & POINTER_FLAG_DOWN) != 0 {
-                TouchPhase::Started
-              } else if (pointer_info.pointerFlags & POINTER_FLAG_UP) != 0 {
-                TouchPhase::Ended
-              } else if (pointer_info.pointerFlags & POINTER_FLAG_UPDATE) != 0 {
-                TouchPhase::Moved
-              } else {
-                continue;
-              },
+              phase,
               location,
               force,
-              id: pointer_info.pointerId as u64,
+              tilt,
+              twist,
+              id,
               device_id: DEVICE_ID,
             }),
           });
@@ -1608,31 +2115,121 @@ unsafe fn public_window_callback_inner<T: 'static>(
       result = ProcResult::Value(LRESULT(0));
     }
 
-    win32wm::WM_SETFOCUS => {
-      use crate::event::WindowEvent::Focused;
-      update_modifiers(window, subclass_input);
+    win32wm::WM_POINTERCAPTURECHANGED => {
+      // Pointers are captured by the window they went down in until they go up. Losing the
+      // capture, e.g. to another window or to a system gesture, means no more messages come for
+      // that contact.
+      let pointer_id = u32::from(util::LOWORD(wparam.0 as u32));
+      let cancelled = subclass_input
+        .window_state
+        .lock()
+        .touch_ids
+        .cancel(TouchSource::Pointer, pointer_id);
+      if let Some((id, location)) = cancelled {
+        subclass_input.send_event(Event::WindowEvent {
+          window_id: RootWindowId(WindowId(window.0)),
+          event: WindowEvent::Touch(Touch {
+            phase: TouchPhase::Cancelled,
+            location,
+            force: None,
+            tilt: None,
+            twist: None,
+            id,
+            device_id: DEVICE_ID,
+          }),
+        });
+      }
+      result = ProcResult::Value(LRESULT(0));
+    }
 
+    win32wm::WM_MOUSEACTIVATE => {
+      if subclass_input.window_state.lock().activate_on_click {
+        result = ProcResult::DefSubclassProc;
+      } else {
+        result = ProcResult::Value(LRESULT(MA_NOACTIVATE as isize));
+      }
+    }
+
+    win32wm::WM_ACTIVATE => {
+      use crate::event::{Activation, WindowEvent::Activated};
+
+      let activation = match u32::from(util::LOWORD(wparam.0 as u32)) {
+        WA_ACTIVE => Activation::Active,
+        WA_CLICKACTIVE => Activation::ClickActive,
+        _ => Activation::Inactive,
+      };
       subclass_input.send_event(Event::WindowEvent {
         window_id: RootWindowId(WindowId(window.0)),
-        event: Focused(true),
+        event: Activated(activation),
       });
 
+      // The default procedure sets the focus on the activated window.
+      result = ProcResult::DefSubclassProc;
+    }
+
+    win32wm::WM_SETFOCUS => {
+      use crate::event::WindowEvent::Focused;
+
+      // The modifiers were reset on `WM_KILLFOCUS`, but one may still be physically held,
+      // e.g. when Alt-Tabbing back while holding Shift. Query the physical key state since
+      // the message queue doesn't know about keys pressed while we didn't have the focus.
+      let modifiers = {
+        let mut layouts = LAYOUT_CACHE.lock().unwrap();
+        layouts.get_async_agnostic_mods()
+      };
+      set_modifiers(window, subclass_input, modifiers);
+
+      // `WM_SETFOCUS` may be sent again to a window which already has the focus. Before the
+      // initial focus state is reported, it's left to `REPORT_INITIAL_FOCUS_MSG_ID`.
+      let gained_focus = {
+        let mut window_state = subclass_input.window_state.lock();
+        if mem::take(&mut window_state.flashing) {
+          util::flash_window(window, FLASHW_STOP, 0);
+        }
+        window_state.mouse.set_focus(window, true).ok();
+        window_state.set_focus(true)
+      };
+      if gained_focus {
+        subclass_input.send_event(Event::WindowEvent {
+          window_id: RootWindowId(WindowId(window.0)),
+          event: Focused(true),
+        });
+      }
+
       result = ProcResult::Value(LRESULT(0));
     }
 
     win32wm::WM_KILLFOCUS => {
       use crate::event::WindowEvent::{Focused, ModifiersChanged};
 
-      subclass_input.window_state.lock().modifiers_state = ModifiersState::empty();
-      subclass_input.send_event(Event::WindowEvent {
-        window_id: RootWindowId(WindowId(window.0)),
-        event: ModifiersChanged(ModifiersState::empty()),
-      });
+      // Finish the composition in progress, so its text goes to this window instead of being
+      // left pending until the focus comes back.
+      let himc = ImmGetContext(window);
+      if !himc.is_invalid() {
+        ImmNotifyIME(himc, NI_COMPOSITIONSTR, CPS_COMPLETE, 0);
+        ImmReleaseContext(window, himc);
+      }
 
-      subclass_input.send_event(Event::WindowEvent {
-        window_id: RootWindowId(WindowId(window.0)),
-        event: Focused(false),
-      });
+      let lost_focus = {
+        let mut window_state = subclass_input.window_state.lock();
+        window_state.mouse.set_focus(window, false).ok();
+        window_state.set_focus(false)
+      };
+      if lost_focus {
+        subclass_input
+          .window_state
+          .lock()
+          .set_modifiers(ModifiersState::empty());
+        subclass_input.send_event(Event::WindowEvent {
+          window_id: RootWindowId(WindowId(window.0)),
+          event: ModifiersChanged(ModifiersState::empty()),
+        });
+
+        subclass_input.send_event(Event::WindowEvent {
+          window_id: RootWindowId(WindowId(window.0)),
+          event: Focused(false),
+        });
+      }
       result = ProcResult::Value(LRESULT(0));
     }
 
@@ -1642,17 +2239,18 @@ unsafe fn public_window_callback_inner<T: 'static>(
         // The return value for the preceding `WM_NCHITTEST` message is conveniently
         // provided through the low-order word of lParam. We use that here since
         // `WM_MOUSEMOVE` seems to come after `WM_SETCURSOR` for a given cursor movement.
-        let in_client_area = u32::from(util::LOWORD(lparam.0 as u32)) == HTCLIENT;
-        if in_client_area {
-          Some(window_state.mouse.cursor)
-        } else {
-          None
-        }
+        let hit_test = u32::from(util::LOWORD(lparam.0 as u32));
+        window_state
+          .mouse
+          .hit_test_cursor(hit_test, window_state.window_flags())
+          .map(|cursor| match cursor {
+            HitTestCursor::Current => window_state.mouse.current_cursor(),
+            HitTestCursor::Resize(cursor) => cursor.load(),
+          })
       };
 
       match set_cursor_to {
         Some(cursor) => {
-          let cursor = LoadCursorW(HINSTANCE::default(), cursor.to_windows_cursor());
           SetCursor(cursor);
           result = ProcResult::Value(LRESULT(0));
         }
@@ -1665,6 +2263,28 @@ unsafe fn public_window_callback_inner<T: 'static>(
 
       let window_state = subclass_input.window_state.lock();
 
+      // Borderless windows have no frame for the system to take into account, so tell it which
+      // area a maximized window should cover. The system interprets these relative to the
+      // primary monitor, which only gives the right size on monitors as large as the primary
+      // one, so the final rect is set by `WM_WINDOWPOSCHANGING`.
+      if !window_state
+        .window_flags()
+        .contains(WindowFlags::DECORATIONS)
+      {
+        if let Some((rect, monitor_rect)) =
+          monitor::maximized_rect(window, window_state.maximize_mode)
+        {
+          (*mmi).ptMaxPosition = POINT {
+            x: rect.left - monitor_rect.left,
+            y: rect.top - monitor_rect.top,
+          };
+          (*mmi).ptMaxSize = POINT {
+            x: rect.right - rect.left,
+            y: rect.bottom - rect.top,
+          };
+        }
+      }
+
       if window_state.min_size.is_some() || window_state.max_size.is_some() {
         if let Some(min_size) = window_state.min_size {
           let min_size = min_size.to_physical(window_state.scale_factor);
@@ -1761,9 +2381,13 @@ unsafe fn public_window_callback_inner<T: 'static>(
       let mut new_physical_inner_size = match allow_resize {
         // We calculate our own size because the default suggested rect doesn't do a great job
         // of preserving the window's logical size.
-        true => old_physical_inner_size
-          .to_logical::<f64>(old_scale_factor)
-          .to_physical::<u32>(new_scale_factor),
+        // A physical min/max size stays the same under the new scale factor, so the rescaled
+        // size is clamped to the bounds rather than to the rescaled bounds.
+        true => subclass_input.window_state.lock().clamp_inner_size(
+          old_physical_inner_size
+            .to_logical::<f64>(old_scale_factor)
+            .to_physical::<u32>(new_scale_factor),
+        ),
         false => old_physical_inner_size,
       };
 
@@ -1919,37 +2543,76 @@ unsafe fn public_window_callback_inner<T: 'static>(
       }
     }
 
-    win32wm::WM_NCCALCSIZE => {
+    win32wm::WM_DWMCOMPOSITIONCHANGED => {
+      // DWM resets the blur-behind when the composition is re-enabled, restore it.
       let win_flags = subclass_input.window_state.lock().window_flags();
+      if win_flags.contains(WindowFlags::TRANSPARENT)
+        && !win_flags.contains(WindowFlags::NO_BACK_BUFFER)
+      {
+        util::set_blur_behind(window, true);
+      }
+      result = ProcResult::Value(LRESULT(0));
+    }
+
+    win32wm::WM_NCCALCSIZE => {
+      let (win_flags, maximize_mode) = {
+        let window_state = subclass_input.window_state.lock();
+        (window_state.window_flags(), window_state.maximize_mode)
+      };
 
       if !win_flags.contains(WindowFlags::DECORATIONS) {
-        // adjust the maximized borderless window so it doesn't cover the taskbar
+        // When `wparam` is `FALSE`, `lparam` points to a single `RECT`, which is also the first
+        // rect of `NCCALCSIZE_PARAMS`.
+        let client_rect = &mut *(lparam.0 as *mut RECT);
+        // adjust the maximized borderless window to the area requested by its maximize mode,
+        // by default so it doesn't cover the taskbar
         if util::is_maximized(window) {
-          let monitor = monitor::current_monitor(window);
-          if let Ok(monitor_info) = monitor::get_monitor_info(monitor.hmonitor()) {
-            let params = &mut *(lparam.0 as *mut NCCALCSIZE_PARAMS);
-            params.rgrc[0] = monitor_info.monitorInfo.rcWork;
+          if let Some((rect, _)) = monitor::maximized_rect(window, maximize_mode) {
+            *client_rect = rect;
           }
         }
+        // Otherwise the proposed window rect is left untouched, so the client area covers the
+        // whole window, without any frame left over from the `WS_CAPTION` and `WS_THICKFRAME`
+        // styles clipping its edges.
         result = ProcResult::Value(LRESULT(0)); // return 0 here to make the windowo borderless
       } else {
         result = ProcResult::DefSubclassProc;
       }
     }
 
+    win32wm::WM_NCACTIVATE => {
+      let win_flags = subclass_input.window_state.lock().window_flags();
+      if !win_flags.contains(WindowFlags::DECORATIONS) {
+        // The default processing repaints the title bar of the `WS_CAPTION` style over the top
+        // edge of the client area when DWM composition is off, passing `-1` as `lparam` skips
+        // that repaint.
+        result = ProcResult::Value(DefSubclassProc(window, msg, wparam, LPARAM(-1)));
+      } else {
+        result = ProcResult::DefSubclassProc;
+      }
+    }
+
     win32wm::WM_NCHITTEST => {
       if let Some(state) = subclass_input.window_state.try_lock() {
         let win_flags = state.window_flags();
 
-        // Only apply this hit test for borderless windows that wants to be resizable
-        if !win_flags.contains(WindowFlags::DECORATIONS) {
+        // Only apply this hit test for borderless windows that wants to be resizable. The flags
+        // are read on every hit test, so `set_resizable` takes effect on the next mouse move.
+        if !win_flags.contains(WindowFlags::DECORATIONS)
+          && win_flags.contains(WindowFlags::RESIZABLE)
+        {
           // cursor location
           let (cx, cy) = (
             i32::from(util::GET_X_LPARAM(lparam)),
             i32::from(util::GET_Y_LPARAM(lparam)),
           );
 
-          result = ProcResult::Value(crate::platform_impl::hit_test(window, cx, cy));
+          let inset = state
+            .resize_border_width
+            .map(|width| (f64::from(width) * state.scale_factor).round() as i32)
+            .unwrap_or(BORDERLESS_RESIZE_INSET);
+
+          result = ProcResult::Value(hit_test_with_inset(window, cx, cy, inset));
         } else {
           result = ProcResult::DefSubclassProc;
         }
@@ -1966,6 +2629,41 @@ unsafe fn public_window_callback_inner<T: 'static>(
           f.set(WindowFlags::MARKER_RETAIN_STATE_ON_SIZE, wparam.0 != 0)
         });
         result = ProcResult::Value(LRESULT(0));
+      } else if msg == *REPORT_INITIAL_FOCUS_MSG_ID {
+        let focused = GetFocus() == window;
+        subclass_input.window_state.lock().has_focus = Some(focused);
+        subclass_input.send_event(Event::WindowEvent {
+          window_id: RootWindowId(WindowId(window.0)),
+          event: WindowEvent::Focused(focused),
+        });
+        result = ProcResult::Value(LRESULT(0));
+      } else if msg == *DECORATIONS_CHANGED_MSG_ID {
+        subclass_input.send_event(Event::WindowEvent {
+          window_id: RootWindowId(WindowId(window.0)),
+          event: WindowEvent::DecorationsChanged(wparam.0 != 0),
+        });
+        result = ProcResult::Value(LRESULT(0));
+      } else if msg == *IME_ALLOWED_CHANGED_MSG_ID {
+        let events = subclass_input
+          .window_state
+          .lock()
+          .ime_handler
+          .set_allowed(wparam.0 != 0);
+        for event in events {
+          subclass_input.send_event(Event::WindowEvent {
+            window_id: RootWindowId(WindowId(window.0)),
+            event: WindowEvent::Ime(event),
+          });
+        }
+        result = ProcResult::Value(LRESULT(0));
+      } else if msg == *SET_FORCE_DEF_WINDOW_PROC_MSG_ID {
+        let mut forced_def_window_proc = subclass_input.forced_def_window_proc.borrow_mut();
+        if lparam.0 != 0 {
+          forced_def_window_proc.insert(wparam.0 as u32);
+        } else {
+          forced_def_window_proc.remove(&(wparam.0 as u32));
+        }
+        result = ProcResult::Value(LRESULT(0));
       }
     }
   };
@@ -1975,6 +2673,16 @@ unsafe fn public_window_callback_inner<T: 'static>(
     .catch_unwind(callback)
     .unwrap_or_else(|| result = ProcResult::Value(LRESULT(-1)));
 
+  if let ProcResult::DefSubclassProc = result {
+    if subclass_input
+      .forced_def_window_proc
+      .borrow()
+      .contains(&msg)
+    {
+      result = ProcResult::DefWindowProc;
+    }
+  }
+
   match result {
     ProcResult::DefSubclassProc => DefSubclassProc(window, msg, wparam, lparam),
     ProcResult::DefWindowProc => DefWindowProcW(window, msg, wparam, lparam),
@@ -2055,13 +2763,61 @@ unsafe extern "system" fn thread_event_target_callback<T: 'static>(
         handle_raw_input(&subclass_input, data);
       }
 
+      // The buffer layout of 32-bit processes differs on 64-bit Windows, where the inputs are
+      // read message by message instead.
+      if cfg!(target_pointer_width = "64") && subclass_input.event_loop_runner.buffered_raw_input()
+      {
+        for data in raw_input::get_raw_input_buffer() {
+          handle_raw_input(&subclass_input, data);
+        }
+      }
+
       DefSubclassProc(window, msg, wparam, lparam)
     }
 
-    _ if msg == *USER_EVENT_MSG_ID => {
-      if let Ok(event) = subclass_input.user_event_receiver.recv() {
-        subclass_input.send_event(Event::UserEvent(event));
+    win32wm::WM_POWERBROADCAST if wparam.0 as u32 == win32wm::PBT_POWERSETTINGCHANGE => {
+      let setting = &*(lparam.0 as *const POWERBROADCAST_SETTING);
+      if let Some(setting) = power_setting_from_broadcast(setting) {
+        subclass_input.send_event(Event::PowerSettingChanged(setting));
+      }
+
+      LRESULT(1)
+    }
+
+    // Sent to every top-level window when the display configuration changes, e.g. when a
+    // monitor is connected or its resolution changes.
+    win32wm::WM_DISPLAYCHANGE => {
+      let runner = &subclass_input.event_loop_runner;
+      let old_monitors = runner.invalidate_monitors();
+      let new_monitors = runner.monitors();
+
+      if let Some(old_monitors) = old_monitors {
+        for monitor in old_monitors.missing_from(&new_monitors) {
+          subclass_input.send_event(Event::MonitorDisconnected(RootMonitorHandle {
+            inner: monitor.clone(),
+          }));
+        }
+        for monitor in new_monitors.missing_from(&old_monitors) {
+          subclass_input.send_event(Event::MonitorConnected(RootMonitorHandle {
+            inner: monitor.clone(),
+          }));
+        }
       }
+
+      DefSubclassProc(window, msg, wparam, lparam)
+    }
+
+    // Sent to every top-level window of the thread, this hidden one included, so handling it
+    // here reports each change of the application's activation once.
+    win32wm::WM_ACTIVATEAPP => {
+      subclass_input.send_event(Event::ApplicationActivated(wparam.0 != 0));
+
+      DefSubclassProc(window, msg, wparam, lparam)
+    }
+
+    _ if msg == *USER_EVENT_MSG_ID => {
+      let event: Box<T> = Box::from_raw(wparam.0 as *mut _);
+      subclass_input.send_event(Event::UserEvent(*event));
       LRESULT(0)
     }
     _ if msg == *EXEC_MSG_ID => {
@@ -2078,10 +2834,13 @@ unsafe extern "system" fn thread_event_target_callback<T: 'static>(
       );
 
       // if the control_flow is WaitUntil, make sure the given moment has actually passed
-      // before emitting NewEvents
+      // before emitting NewEvents. When saving power, an early wake-up is reported as
+      // `WaitCancelled` instead, and the next `WaitUntil` arms the wait again.
+      let high_precision =
+        subclass_input.event_loop_runner.wait_precision() == WaitPrecision::HighPrecision;
       if let ControlFlow::WaitUntil(wait_until) = subclass_input.event_loop_runner.control_flow() {
         let mut msg = MSG::default();
-        while Instant::now() < wait_until {
+        while high_precision && Instant::now() < wait_until {
           if PeekMessageW(&mut msg, HWND::default(), 0, 0, PM_NOREMOVE).as_bool() {
             // This works around a "feature" in PeekMessageW. If the message PeekMessageW
             // gets is a WM_PAINT message that had RDW_INTERNALPAINT set (i.e. doesn't
@@ -2164,7 +2923,7 @@ unsafe fn handle_raw_input<T: 'static>(
       RI_MOUSE_WHEEL as u16,
     ) {
       // We must cast to SHORT first, becaues `usButtonData` must be interpreted as signed.
-      let delta = mouse.Anonymous.Anonymous.usButtonData as f32 / WHEEL_DELTA as f32;
+      let delta = mouse.Anonymous.Anonymous.usButtonData as i16 as f32 / WHEEL_DELTA as f32;
       subclass_input.send_event(Event::DeviceEvent {
         device_id,
         event: MouseWheel {
@@ -2173,6 +2932,20 @@ unsafe fn handle_raw_input<T: 'static>(
       });
     }
 
+    if util::has_flag(
+      mouse.Anonymous.Anonymous.usButtonFlags,
+      RI_MOUSE_HWHEEL as u16,
+    ) {
+      // Positive values scroll to the right, like `WM_MOUSEHWHEEL`.
+      let delta = mouse.Anonymous.Anonymous.usButtonData as i16 as f32 / WHEEL_DELTA as f32;
+      subclass_input.send_event(Event::DeviceEvent {
+        device_id,
+        event: MouseWheel {
+          delta: LineDelta(delta, 0.0),
+        },
+      });
+    }
+
     let button_state =
       raw_input::get_raw_mouse_button_state(mouse.Anonymous.Anonymous.usButtonFlags);
     // Left, middle, and right, respectively.
@@ -7,19 +7,20 @@ use std::{
   collections::{HashSet, VecDeque},
   mem, panic, ptr,
   rc::Rc,
-  time::Instant,
+  time::{Duration, Instant},
 };
 
 use windows::Win32::{
   Foundation::HWND,
   Graphics::Gdi::{RedrawWindow, HRGN, RDW_INTERNALPAINT},
+  UI::Input::KeyboardAndMouse::GetKeyboardLayout,
 };
 
 use crate::{
   dpi::PhysicalSize,
   event::{Event, StartCause, WindowEvent},
   event_loop::ControlFlow,
-  platform_impl::platform::util,
+  platform_impl::platform::{monitor::MonitorCache, util, WaitPrecision},
   window::WindowId,
 };
 
@@ -30,6 +31,9 @@ pub(crate) struct EventLoopRunner<T: 'static> {
   wait_thread_id: u32,
 
   control_flow: Cell<ControlFlow>,
+  poll_interval: Cell<Option<Duration>>,
+  wait_precision: Cell<WaitPrecision>,
+  buffered_raw_input: Cell<bool>,
   runner_state: Cell<RunnerState>,
   last_events_cleared: Cell<Instant>,
 
@@ -37,6 +41,10 @@ pub(crate) struct EventLoopRunner<T: 'static> {
   event_buffer: RefCell<VecDeque<BufferedEvent<T>>>,
 
   owned_windows: Cell<HashSet<isize>>,
+  monitor_cache: RefCell<Option<MonitorCache>>,
+  /// The last keyboard layout reported by `WM_INPUTLANGCHANGE`, which every window of the thread
+  /// may receive for the same change.
+  keyboard_layout: Cell<isize>,
 
   panic_error: Cell<Option<PanicError>>,
 }
@@ -72,11 +80,17 @@ impl<T> EventLoopRunner<T> {
       wait_thread_id,
       runner_state: Cell::new(RunnerState::Uninitialized),
       control_flow: Cell::new(ControlFlow::Poll),
+      poll_interval: Cell::new(None),
+      wait_precision: Cell::new(WaitPrecision::default()),
+      buffered_raw_input: Cell::new(false),
       panic_error: Cell::new(None),
       last_events_cleared: Cell::new(Instant::now()),
       event_handler: Cell::new(None),
       event_buffer: RefCell::new(VecDeque::new()),
       owned_windows: Cell::new(HashSet::new()),
+      // Enumerated upfront so that the first display change can be compared against it.
+      monitor_cache: RefCell::new(Some(MonitorCache::new())),
+      keyboard_layout: Cell::new(unsafe { GetKeyboardLayout(0) }.0),
     }
   }
 
@@ -98,10 +112,15 @@ impl<T> EventLoopRunner<T> {
       runner_state,
       panic_error,
       control_flow,
+      poll_interval: _,
+      wait_precision: _,
+      buffered_raw_input: _,
       last_events_cleared: _,
       event_handler,
       event_buffer: _,
       owned_windows: _,
+      monitor_cache: _,
+      keyboard_layout: _,
     } = self;
     runner_state.set(RunnerState::Uninitialized);
     panic_error.set(None);
@@ -135,6 +154,49 @@ impl<T> EventLoopRunner<T> {
     self.control_flow.get()
   }
 
+  pub fn poll_interval(&self) -> Option<Duration> {
+    self.poll_interval.get()
+  }
+
+  pub fn set_poll_interval(&self, interval: Option<Duration>) {
+    self.poll_interval.set(interval);
+  }
+
+  pub fn wait_precision(&self) -> WaitPrecision {
+    self.wait_precision.get()
+  }
+
+  pub fn set_wait_precision(&self, precision: WaitPrecision) {
+    self.wait_precision.set(precision);
+  }
+
+  /// Returns the monitors, enumerating them if the cache was invalidated.
+  pub fn monitors(&self) -> MonitorCache {
+    self
+      .monitor_cache
+      .borrow_mut()
+      .get_or_insert_with(MonitorCache::new)
+      .clone()
+  }
+
+  /// Invalidates the monitors after a display change, returning the previously cached ones.
+  pub fn invalidate_monitors(&self) -> Option<MonitorCache> {
+    self.monitor_cache.take()
+  }
+
+  /// Records the keyboard layout of the thread, returning whether it changed.
+  pub fn set_keyboard_layout(&self, hkl: isize) -> bool {
+    self.keyboard_layout.replace(hkl) != hkl
+  }
+
+  pub fn buffered_raw_input(&self) -> bool {
+    self.buffered_raw_input.get()
+  }
+
+  pub fn set_buffered_raw_input(&self, buffered: bool) {
+    self.buffered_raw_input.set(buffered);
+  }
+
   pub fn handling_events(&self) -> bool {
     self.runner_state.get() != RunnerState::Idle
   }
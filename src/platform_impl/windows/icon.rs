@@ -1,12 +1,19 @@
 // Copyright 2019-2021 Tauri Programme within The Commons Conservancy
 // SPDX-License-Identifier: Apache-2.0
 
-use std::{fmt, io, iter::once, mem, os::windows::ffi::OsStrExt, path::Path, sync::Arc};
+use std::{fmt, io, iter::once, mem, os::windows::ffi::OsStrExt, path::Path, ptr, sync::Arc};
 
 use windows::Win32::{
-  Foundation::{HINSTANCE, HWND, LPARAM, PWSTR, WPARAM},
+  Foundation::{HINSTANCE, HWND, LPARAM, MAX_PATH, PWSTR, RECT, WPARAM},
+  Graphics::Gdi::{
+    CreateBitmap, CreateCompatibleBitmap, CreateCompatibleDC, CreateFontW, CreateSolidBrush,
+    DeleteDC, DeleteObject, DrawTextW, Ellipse, GetDC, GetStockObject, PatBlt, ReleaseDC,
+    SelectObject, SetBkMode, SetTextColor, ANTIALIASED_QUALITY, BLACKNESS, BLACK_BRUSH,
+    CLIP_DEFAULT_PRECIS, DEFAULT_CHARSET, DT_CENTER, DT_NOCLIP, DT_SINGLELINE, DT_VCENTER,
+    FF_SWISS, FW_BOLD, NULL_PEN, OUT_DEFAULT_PRECIS, TRANSPARENT, WHITENESS,
+  },
   System::LibraryLoader::*,
-  UI::WindowsAndMessaging::*,
+  UI::{Shell::ExtractIconExW, WindowsAndMessaging::*},
 };
 
 use crate::{dpi::PhysicalSize, icon::*};
@@ -127,11 +134,129 @@ impl WinIcon {
     ))
   }
 
+  /// Loads the large and small versions of the first icon embedded in the executable, which is
+  /// the icon the shell shows for it.
+  pub fn from_executable() -> (Option<Self>, Option<Self>) {
+    let mut path = vec![0u16; MAX_PATH as usize];
+    let (mut large, mut small) = (HICON::default(), HICON::default());
+    unsafe {
+      // The path is truncated when the buffer is too small, so it grows until the path fits.
+      loop {
+        let len = GetModuleFileNameW(
+          HINSTANCE::default(),
+          PWSTR(path.as_mut_ptr()),
+          path.len() as u32,
+        ) as usize;
+        if len == 0 {
+          return (None, None);
+        } else if len < path.len() {
+          break;
+        }
+        path.resize(path.len() * 2, 0);
+      }
+      ExtractIconExW(PWSTR(path.as_mut_ptr()), 0, &mut large, &mut small, 1);
+    }
+    let from_handle = |handle: HICON| handle.ok().ok().map(WinIcon::from_handle);
+    (from_handle(large), from_handle(small))
+  }
+
   pub fn from_rgba(rgba: Vec<u8>, width: u32, height: u32) -> Result<Self, BadIcon> {
     let rgba_icon = RgbaIcon::from_rgba(rgba, width, height)?;
     rgba_icon.into_windows_icon()
   }
 
+  /// Renders `text` in white on a red disc into a square icon of `size` pixels, as used for
+  /// badges.
+  pub fn from_badge_text(text: &str, size: i32) -> Result<Self, BadIcon> {
+    const BADGE_COLOR: u32 = 0x0023_11E8; // COLORREF of rgb(232, 17, 35)
+    const TEXT_COLOR: u32 = 0x00FF_FFFF;
+
+    let handle = unsafe {
+      let screen_dc = GetDC(HWND::default());
+      let color_dc = CreateCompatibleDC(screen_dc);
+      let mask_dc = CreateCompatibleDC(screen_dc);
+      let color = CreateCompatibleBitmap(screen_dc, size, size);
+      let mask = CreateBitmap(size, size, 1, 1, ptr::null());
+      ReleaseDC(HWND::default(), screen_dc);
+
+      // The mask is black where the disc is opaque and white where the icon is transparent.
+      let old_mask = SelectObject(mask_dc, mask);
+      let old_mask_brush = SelectObject(mask_dc, GetStockObject(BLACK_BRUSH));
+      let old_mask_pen = SelectObject(mask_dc, GetStockObject(NULL_PEN));
+      PatBlt(mask_dc, 0, 0, size, size, WHITENESS);
+      Ellipse(mask_dc, 0, 0, size + 1, size + 1);
+      SelectObject(mask_dc, old_mask_pen);
+      SelectObject(mask_dc, old_mask_brush);
+      SelectObject(mask_dc, old_mask);
+
+      let brush = CreateSolidBrush(BADGE_COLOR);
+      let font = CreateFontW(
+        -(size * 2 / 3),
+        0,
+        0,
+        0,
+        FW_BOLD as i32,
+        0,
+        0,
+        0,
+        DEFAULT_CHARSET,
+        OUT_DEFAULT_PRECIS,
+        CLIP_DEFAULT_PRECIS,
+        ANTIALIASED_QUALITY,
+        FF_SWISS,
+        "Segoe UI",
+      );
+      let old_color = SelectObject(color_dc, color);
+      let old_brush = SelectObject(color_dc, brush);
+      let old_pen = SelectObject(color_dc, GetStockObject(NULL_PEN));
+      let old_font = SelectObject(color_dc, font);
+      PatBlt(color_dc, 0, 0, size, size, BLACKNESS);
+      Ellipse(color_dc, 0, 0, size + 1, size + 1);
+      SetBkMode(color_dc, TRANSPARENT);
+      SetTextColor(color_dc, TEXT_COLOR);
+      let mut text: Vec<u16> = text.encode_utf16().collect();
+      let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: size,
+        bottom: size,
+      };
+      DrawTextW(
+        color_dc,
+        PWSTR(text.as_mut_ptr()),
+        text.len() as i32,
+        &mut rect,
+        DT_CENTER | DT_VCENTER | DT_SINGLELINE | DT_NOCLIP,
+      );
+      SelectObject(color_dc, old_font);
+      SelectObject(color_dc, old_pen);
+      SelectObject(color_dc, old_brush);
+      SelectObject(color_dc, old_color);
+      DeleteObject(font);
+      DeleteObject(brush);
+      DeleteDC(color_dc);
+      DeleteDC(mask_dc);
+
+      // `CreateIconIndirect` copies the bitmaps, so they can be deleted right away.
+      let icon_info = ICONINFO {
+        fIcon: true.into(),
+        xHotspot: 0,
+        yHotspot: 0,
+        hbmMask: mask,
+        hbmColor: color,
+      };
+      let handle = CreateIconIndirect(&icon_info);
+      DeleteObject(color);
+      DeleteObject(mask);
+      handle
+    };
+    Ok(WinIcon::from_handle(
+      handle
+        .ok()
+        .map_err(|_| BadIcon::OsError(io::Error::last_os_error()))?,
+    ))
+  }
+
   pub fn set_for_window(&self, hwnd: HWND, icon_type: IconType) {
     unsafe {
       SendMessageW(
@@ -162,6 +287,119 @@ impl fmt::Debug for WinIcon {
   }
 }
 
+/// A cursor, kept apart from `WinIcon` so a cursor can't be set as a window icon, nor an icon as
+/// a cursor, whose hotspot would be at its center.
+#[derive(Clone)]
+pub struct WinCursor {
+  inner: Arc<RaiiCursor>,
+}
+
+#[derive(Debug)]
+struct RaiiCursor {
+  handle: HCURSOR,
+}
+
+unsafe impl Send for WinCursor {}
+
+impl WinCursor {
+  pub fn as_raw_handle(&self) -> HCURSOR {
+    self.inner.handle
+  }
+
+  /// Loads a cursor from a `.cur` or animated `.ani` file.
+  pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, BadIcon> {
+    let mut wide_path: Vec<u16> = path
+      .as_ref()
+      .as_os_str()
+      .encode_wide()
+      .chain(once(0))
+      .collect();
+
+    let handle = HCURSOR(
+      unsafe {
+        LoadImageW(
+          HINSTANCE::default(),
+          PWSTR(wide_path.as_mut_ptr()),
+          IMAGE_CURSOR,
+          0,
+          0,
+          LR_DEFAULTSIZE | LR_LOADFROMFILE,
+        )
+      }
+      .0,
+    );
+    WinCursor::from_handle(handle)
+  }
+
+  /// Creates a cursor from `image`, with its hotspot at (`hotspot_x`, `hotspot_y`).
+  pub fn from_rgba(image: &RgbaIcon, hotspot_x: u32, hotspot_y: u32) -> Result<Self, BadIcon> {
+    let RgbaIcon {
+      rgba,
+      width,
+      height,
+    } = image;
+    let bgra: Vec<u8> = rgba
+      .chunks_exact(PIXEL_SIZE)
+      .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+      .collect();
+    // The color bitmap has an alpha channel, which the system uses instead of the mask. The
+    // rows of the monochrome mask are aligned to 16 bits.
+    let mask_bits = vec![0xFFu8; (((*width as usize + 15) / 16) * 2) * *height as usize];
+
+    let handle = unsafe {
+      let mask = CreateBitmap(
+        *width as i32,
+        *height as i32,
+        1,
+        1,
+        mask_bits.as_ptr() as *const _,
+      );
+      let color = CreateBitmap(
+        *width as i32,
+        *height as i32,
+        1,
+        (PIXEL_SIZE * 8) as u32,
+        bgra.as_ptr() as *const _,
+      );
+      // `CreateIconIndirect` copies the bitmaps, so they can be deleted right away.
+      let icon_info = ICONINFO {
+        fIcon: false.into(),
+        xHotspot: hotspot_x,
+        yHotspot: hotspot_y,
+        hbmMask: mask,
+        hbmColor: color,
+      };
+      let handle = CreateIconIndirect(&icon_info);
+      DeleteObject(color);
+      DeleteObject(mask);
+      handle
+    };
+    WinCursor::from_handle(HCURSOR(handle.0))
+  }
+
+  fn from_handle(handle: HCURSOR) -> Result<Self, BadIcon> {
+    let handle = handle
+      .ok()
+      .map_err(|_| BadIcon::OsError(io::Error::last_os_error()))?;
+    Ok(Self {
+      inner: Arc::new(RaiiCursor { handle }),
+    })
+  }
+}
+
+impl Drop for RaiiCursor {
+  fn drop(&mut self) {
+    // The cursors aren't loaded with `LR_SHARED`, so they are owned here.
+    unsafe { DestroyCursor(self.handle) };
+  }
+}
+
+impl fmt::Debug for WinCursor {
+  fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+    (*self.inner).fmt(formatter)
+  }
+}
+
 pub fn unset_for_window(hwnd: HWND, icon_type: IconType) {
   unsafe {
     SendMessageW(hwnd, WM_SETICON, WPARAM(icon_type as _), LPARAM(0));
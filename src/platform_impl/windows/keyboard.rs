@@ -654,6 +654,7 @@ impl PartialKeyEventInfo {
       platform_specific: KeyEventExtra {
         text_with_all_modifiers: char_with_all_modifiers,
         key_without_modifiers: self.key_without_modifiers,
+        scancode: self.scancode as u32,
       },
     }
   }
@@ -916,3 +917,24 @@ pub(crate) fn key_to_vk(key: &KeyCode) -> Option<VIRTUAL_KEY> {
     _ => return None,
   })
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn right_ctrl_has_an_extended_scancode() {
+    // `WM_KEYDOWN` for the right Ctrl key: scancode 0x1D with the extended key flag.
+    let lparam = LPARAM(0x011D_0001);
+    let scancode = ex_scancode_from_lparam(lparam);
+    assert_eq!(scancode, 0xE01D);
+    assert_eq!(
+      KeyCode::from_scancode(scancode as u32),
+      KeyCode::ControlRight
+    );
+
+    // The left Ctrl key shares the scancode, without the extended key flag.
+    let lparam = LPARAM(0x001D_0001);
+    assert_eq!(ex_scancode_from_lparam(lparam), 0x001D);
+  }
+}
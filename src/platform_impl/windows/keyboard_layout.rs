@@ -31,6 +31,34 @@ fn key_pressed(vkey: VIRTUAL_KEY) -> bool {
   unsafe { (GetKeyState(u32::from(vkey) as i32) & (1 << 15)) == (1 << 15) }
 }
 
+fn key_pressed_async(vkey: VIRTUAL_KEY) -> bool {
+  unsafe { (GetAsyncKeyState(u32::from(vkey) as i32) & (1 << 15)) == (1 << 15) }
+}
+
+/// Returns the modifiers held according to `key_pressed`. On layouts with AltGr, the `Ctrl+Alt`
+/// it's made of isn't reported as modifiers.
+pub(crate) fn mods_from_keys(
+  has_alt_graph: bool,
+  key_pressed: impl Fn(VIRTUAL_KEY) -> bool,
+) -> ModifiersState {
+  let filter_out_altgr = has_alt_graph && key_pressed(VK_RMENU);
+  let mut mods = ModifiersState::empty();
+  mods.set(ModifiersState::SHIFT, key_pressed(VK_SHIFT));
+  mods.set(
+    ModifiersState::CONTROL,
+    key_pressed(VK_CONTROL) && !filter_out_altgr,
+  );
+  mods.set(
+    ModifiersState::ALT,
+    key_pressed(VK_MENU) && !filter_out_altgr,
+  );
+  mods.set(
+    ModifiersState::SUPER,
+    key_pressed(VK_LWIN) || key_pressed(VK_RWIN),
+  );
+  mods
+}
+
 const NUMPAD_VKEYS: [VIRTUAL_KEY; 16] = [
   VK_NUMPAD0,
   VK_NUMPAD1,
@@ -253,23 +281,19 @@ impl LayoutCache {
   }
 
   pub fn get_agnostic_mods(&mut self) -> ModifiersState {
+    self.agnostic_mods(key_pressed)
+  }
+
+  /// Same as `get_agnostic_mods` but reads the physical key state. The key state of the
+  /// thread's message queue is stale right after the window (re)gains focus, as it doesn't
+  /// see the keys that were pressed while another window had the focus.
+  pub fn get_async_agnostic_mods(&mut self) -> ModifiersState {
+    self.agnostic_mods(key_pressed_async)
+  }
+
+  fn agnostic_mods(&mut self, key_pressed: fn(VIRTUAL_KEY) -> bool) -> ModifiersState {
     let (_, layout) = self.get_current_layout();
-    let filter_out_altgr = layout.has_alt_graph && key_pressed(VK_RMENU);
-    let mut mods = ModifiersState::empty();
-    mods.set(ModifiersState::SHIFT, key_pressed(VK_SHIFT));
-    mods.set(
-      ModifiersState::CONTROL,
-      key_pressed(VK_CONTROL) && !filter_out_altgr,
-    );
-    mods.set(
-      ModifiersState::ALT,
-      key_pressed(VK_MENU) && !filter_out_altgr,
-    );
-    mods.set(
-      ModifiersState::SUPER,
-      key_pressed(VK_LWIN) || key_pressed(VK_RWIN),
-    );
-    mods
+    mods_from_keys(layout.has_alt_graph, key_pressed)
   }
 
   fn prepare_layout(strings: &mut HashSet<&'static str>, locale_id: HKL) -> Layout {
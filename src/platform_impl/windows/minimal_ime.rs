@@ -1,11 +1,18 @@
-use std::mem::MaybeUninit;
+use std::ptr;
 
 use windows::Win32::{
   Foundation::{HWND, LPARAM, LRESULT, WPARAM},
-  UI::WindowsAndMessaging::{self as win32wm, *},
+  Globalization::HIMC,
+  UI::{
+    Input::Ime::*,
+    WindowsAndMessaging::{self as win32wm, *},
+  },
 };
 
-use crate::platform_impl::platform::event_loop::ProcResult;
+use crate::{event::Ime, platform_impl::platform::event_loop::ProcResult};
+
+/// Sends a UTF-32 character, defined by the RichEdit headers which aren't enabled here.
+const WM_UNICHAR: u32 = 0x0109;
 
 pub fn is_msg_ime_related(msg_kind: u32) -> bool {
   matches!(
@@ -17,22 +24,18 @@ pub fn is_msg_ime_related(msg_kind: u32) -> bool {
       | win32wm::WM_IME_CHAR
       | win32wm::WM_CHAR
       | win32wm::WM_SYSCHAR
+      | WM_UNICHAR
   )
 }
 
+#[derive(Default)]
 pub struct MinimalIme {
-  // True if we're currently receiving messages belonging to a finished IME session.
-  getting_ime_text: bool,
+  // True if a non-empty preedit was sent, which has to be cleared when the composition ends.
+  has_preedit: bool,
 
-  utf16parts: Vec<u16>,
-}
-impl Default for MinimalIme {
-  fn default() -> Self {
-    MinimalIme {
-      getting_ime_text: false,
-      utf16parts: Vec::with_capacity(16),
-    }
-  }
+  // The high surrogate of a character sent as a surrogate pair of `WM_CHAR` messages outside of
+  // an IME session, e.g. by the emoji panel.
+  high_surrogate: Option<u16>,
 }
 impl MinimalIme {
   pub(crate) fn process_message(
@@ -40,53 +43,234 @@ impl MinimalIme {
     hwnd: HWND,
     msg_kind: u32,
     wparam: WPARAM,
-    _lparam: LPARAM,
+    lparam: LPARAM,
     result: &mut ProcResult,
-  ) -> Option<String> {
+  ) -> Vec<Ime> {
+    let mut events = Vec::new();
+    // `Ime::Enabled` and `Ime::Disabled` report whether the input method is allowed, see
+    // `set_allowed`, not the start and end of each composition.
     match msg_kind {
+      win32wm::WM_IME_COMPOSITION => unsafe {
+        let himc = ImmGetContext(hwnd);
+        if himc.is_invalid() {
+          return events;
+        }
+        let flags = lparam.0 as u32;
+
+        if flags & GCS_RESULTSTR != 0 {
+          if let Some(text) = composition_string(himc, GCS_RESULTSTR) {
+            self.clear_preedit(&mut events);
+            events.push(Ime::Commit(text));
+            // The text was handled, the default procedure would send it again as `WM_IME_CHAR`
+            // and `WM_CHAR` messages.
+            *result = ProcResult::Value(LRESULT(0));
+          }
+        }
+
+        if flags & GCS_COMPSTR != 0 {
+          if let Some(text) = composition_string(himc, GCS_COMPSTR) {
+            let cursor = if flags & GCS_CURSORPOS != 0 {
+              let position = ImmGetCompositionStringW(himc, GCS_CURSORPOS, ptr::null_mut(), 0);
+              (position >= 0).then(|| {
+                let offset = byte_offset(&text, position as usize);
+                (offset, offset)
+              })
+            } else {
+              None
+            };
+            self.has_preedit = !text.is_empty();
+            events.push(Ime::Preedit { text, cursor });
+          }
+        } else if flags & GCS_RESULTSTR == 0 {
+          // The composition string was removed, e.g. with Escape.
+          self.clear_preedit(&mut events);
+        }
+
+        ImmReleaseContext(hwnd, himc);
+      },
       win32wm::WM_IME_ENDCOMPOSITION => {
-        self.getting_ime_text = true;
+        self.clear_preedit(&mut events);
       }
       win32wm::WM_CHAR | win32wm::WM_SYSCHAR => {
         *result = ProcResult::Value(LRESULT(0));
-        if self.getting_ime_text {
-          self.utf16parts.push(wparam.0 as u16);
-
-          let more_char_coming;
-          unsafe {
-            let mut next_msg = MaybeUninit::uninit();
-            let has_message = PeekMessageW(
-              next_msg.as_mut_ptr(),
-              hwnd,
-              WM_KEYFIRST,
-              WM_KEYLAST,
-              PM_NOREMOVE,
-            );
-            let has_message = has_message.as_bool();
-            if !has_message {
-              more_char_coming = false;
-            } else {
-              let next_msg = next_msg.assume_init().message;
-              if next_msg == WM_CHAR || next_msg == WM_SYSCHAR {
-                more_char_coming = true;
-              } else {
-                more_char_coming = false;
-              }
+        let code_unit = wparam.0 as u16;
+        match code_unit {
+          0xD800..=0xDBFF => self.high_surrogate = Some(code_unit),
+          0xDC00..=0xDFFF => {
+            if let Some(high_surrogate) = self.high_surrogate.take() {
+              events.extend(
+                String::from_utf16(&[high_surrogate, code_unit])
+                  .ok()
+                  .map(Ime::Commit),
+              );
             }
           }
-          if !more_char_coming {
-            let result = String::from_utf16(&self.utf16parts).ok();
-            self.utf16parts.clear();
-            self.getting_ime_text = false;
-            return result;
+          _ => {
+            self.high_surrogate = None;
+            events.extend(String::from_utf16(&[code_unit]).ok().map(Ime::Commit));
           }
-        } else {
-          return String::from_utf16(&[wparam.0 as u16]).ok();
         }
       }
+      WM_UNICHAR => {
+        // Returning `TRUE` to the `UNICODE_NOCHAR` probe tells the sender that UTF-32
+        // characters are accepted.
+        if wparam.0 as u32 == UNICODE_NOCHAR {
+          *result = ProcResult::Value(LRESULT(1));
+          return events;
+        }
+        *result = ProcResult::Value(LRESULT(0));
+        events.extend(
+          char::from_u32(wparam.0 as u32)
+            .map(String::from)
+            .map(Ime::Commit),
+        );
+      }
       _ => (),
     }
 
-    None
+    events
+  }
+
+  /// Returns the events sent when `Window::set_ime_allowed` toggles the input method. The
+  /// preedit of a composition cancelled by disallowing it is cleared.
+  pub(crate) fn set_allowed(&mut self, allowed: bool) -> Vec<Ime> {
+    let mut events = Vec::new();
+    if allowed {
+      events.push(Ime::Enabled);
+    } else {
+      self.clear_preedit(&mut events);
+      events.push(Ime::Disabled);
+    }
+    events
+  }
+
+  fn clear_preedit(&mut self, events: &mut Vec<Ime>) {
+    if self.has_preedit {
+      self.has_preedit = false;
+      events.push(Ime::Preedit {
+        text: String::new(),
+        cursor: None,
+      });
+    }
+  }
+}
+
+/// Reads the composition string of the given kind, e.g. `GCS_COMPSTR`.
+unsafe fn composition_string(himc: HIMC, kind: u32) -> Option<String> {
+  // The size is in bytes.
+  let size = ImmGetCompositionStringW(himc, kind, ptr::null_mut(), 0);
+  if size < 0 {
+    return None;
+  }
+  let mut buffer = vec![0u16; size as usize / 2];
+  let size = ImmGetCompositionStringW(himc, kind, buffer.as_mut_ptr() as *mut _, size as u32);
+  if size < 0 {
+    return None;
+  }
+  buffer.truncate(size as usize / 2);
+  String::from_utf16(&buffer).ok()
+}
+
+/// Converts a position in UTF-16 code units to a byte offset into `text`.
+fn byte_offset(text: &str, utf16_position: usize) -> usize {
+  let mut utf16_offset = 0;
+  for (offset, c) in text.char_indices() {
+    if utf16_offset >= utf16_position {
+      return offset;
+    }
+    utf16_offset += c.len_utf16();
+  }
+  text.len()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn byte_offset_counts_surrogate_pairs_as_one_char() {
+    // "a😀b": the emoji is 2 UTF-16 code units and 4 bytes.
+    let text = "a\u{1F600}b";
+    assert_eq!(byte_offset(text, 0), 0);
+    assert_eq!(byte_offset(text, 1), 1);
+    // A position between the two halves of the pair lands on the next char boundary.
+    assert_eq!(byte_offset(text, 2), 5);
+    assert_eq!(byte_offset(text, 3), 5);
+    assert_eq!(byte_offset(text, 4), 6);
+  }
+
+  #[test]
+  fn disallowing_clears_the_preedit() {
+    let mut ime = MinimalIme {
+      has_preedit: true,
+      ..Default::default()
+    };
+    assert_eq!(
+      ime.set_allowed(false),
+      vec![
+        Ime::Preedit {
+          text: String::new(),
+          cursor: None
+        },
+        Ime::Disabled
+      ]
+    );
+    assert_eq!(ime.set_allowed(true), vec![Ime::Enabled]);
+    assert_eq!(ime.set_allowed(false), vec![Ime::Disabled]);
+  }
+
+  #[test]
+  fn compositions_dont_toggle_the_allowed_state() {
+    let mut ime = MinimalIme {
+      has_preedit: true,
+      ..Default::default()
+    };
+    let mut message = |msg_kind: u32| {
+      let mut result = ProcResult::DefSubclassProc;
+      ime.process_message(HWND::default(), msg_kind, WPARAM(0), LPARAM(0), &mut result)
+    };
+
+    assert_eq!(message(win32wm::WM_IME_STARTCOMPOSITION), vec![]);
+    // Ending the composition only clears its preedit, the input method is still allowed.
+    assert_eq!(
+      message(win32wm::WM_IME_ENDCOMPOSITION),
+      vec![Ime::Preedit {
+        text: String::new(),
+        cursor: None
+      }]
+    );
+    assert_eq!(message(win32wm::WM_IME_ENDCOMPOSITION), vec![]);
+  }
+
+  #[test]
+  fn byte_offset_is_clamped_to_the_text() {
+    assert_eq!(byte_offset("\u{1F600}", 5), 4);
+    assert_eq!(byte_offset("", 1), 0);
+  }
+
+  #[test]
+  fn surrogate_pair_chars_are_committed_as_one_string() {
+    let mut ime = MinimalIme::default();
+    let mut char_message = |code_unit: u16| {
+      let mut result = ProcResult::DefSubclassProc;
+      let events = ime.process_message(
+        HWND::default(),
+        win32wm::WM_CHAR,
+        WPARAM(code_unit as usize),
+        LPARAM(1),
+        &mut result,
+      );
+      assert!(matches!(result, ProcResult::Value(LRESULT(0))));
+      events
+    };
+
+    // U+1F600 is sent as the surrogate pair D83D DE00.
+    assert_eq!(char_message(0xD83D), vec![]);
+    assert_eq!(
+      char_message(0xDE00),
+      vec![Ime::Commit("\u{1F600}".to_string())]
+    );
+    // A lone low surrogate is dropped.
+    assert_eq!(char_message(0xDE00), vec![]);
   }
 }
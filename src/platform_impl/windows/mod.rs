@@ -5,21 +5,22 @@
 
 use windows::Win32::{
   Foundation::{HANDLE, HWND},
-  UI::WindowsAndMessaging::HMENU,
+  UI::{HiDpi::DPI_AWARENESS_CONTEXT, WindowsAndMessaging::HMENU},
 };
 
 pub use self::{
   clipboard::Clipboard,
+  drag_source::DropEffect,
   event_loop::{EventLoop, EventLoopProxy, EventLoopWindowTarget},
   global_shortcut::{GlobalShortcut, ShortcutManager},
   icon::WinIcon,
   keycode::{keycode_from_scancode, keycode_to_scancode},
   menu::{Menu, MenuItemAttributes},
-  monitor::{MonitorHandle, VideoMode},
+  monitor::{DisplayOutput, MonitorHandle, VideoMode},
   window::{hit_test, Window},
 };
 
-pub use self::icon::WinIcon as PlatformIcon;
+pub use self::icon::{WinCursor as PlatformCursor, WinIcon as PlatformIcon};
 
 use crate::{event::DeviceId as RootDeviceId, icon::Icon, keyboard::Key, window::Theme};
 mod accelerator;
@@ -40,6 +41,42 @@ pub enum Parent {
   OwnedBy(HWND),
 }
 
+/// The area a maximized borderless window covers.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MaximizeMode {
+  /// Cover the monitor's work area, leaving the taskbar visible.
+  WorkArea,
+  /// Cover the whole monitor, including the taskbar.
+  FullMonitor,
+}
+
+impl Default for MaximizeMode {
+  fn default() -> Self {
+    MaximizeMode::WorkArea
+  }
+}
+
+/// How precisely the event loop wakes up at the instant of
+/// [`ControlFlow::WaitUntil`](crate::event_loop::ControlFlow::WaitUntil).
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WaitPrecision {
+  /// Wake up at the requested instant. The system timers tend to overshoot, so the wait stops
+  /// a millisecond early and the remainder is spent spinning.
+  HighPrecision,
+  /// Wake up with the system timer, without spinning. This saves power, e.g. on battery, at
+  /// the cost of waking up a few milliseconds late. A wake-up that comes before the instant
+  /// is reported as [`StartCause::WaitCancelled`](crate::event::StartCause::WaitCancelled).
+  PowerSaving,
+}
+
+impl Default for WaitPrecision {
+  fn default() -> Self {
+    WaitPrecision::HighPrecision
+  }
+}
+
 #[derive(Clone)]
 pub struct PlatformSpecificWindowBuilderAttributes {
   pub parent: Parent,
@@ -47,8 +84,13 @@ pub struct PlatformSpecificWindowBuilderAttributes {
   pub taskbar_icon: Option<Icon>,
   pub skip_taskbar: bool,
   pub no_redirection_bitmap: bool,
+  pub tool_window: bool,
+  pub executable_icon: bool,
   pub drag_and_drop: bool,
   pub preferred_theme: Option<Theme>,
+  pub dpi_awareness_context: Option<DPI_AWARENESS_CONTEXT>,
+  pub legacy_touch: bool,
+  pub class_name: String,
 }
 
 impl Default for PlatformSpecificWindowBuilderAttributes {
@@ -58,9 +100,14 @@ impl Default for PlatformSpecificWindowBuilderAttributes {
       menu: None,
       taskbar_icon: None,
       no_redirection_bitmap: false,
+      tool_window: false,
+      executable_icon: true,
       drag_and_drop: true,
       preferred_theme: None,
       skip_taskbar: false,
+      dpi_awareness_context: None,
+      legacy_touch: false,
+      class_name: "Window Class".to_owned(),
     }
   }
 }
@@ -121,6 +168,7 @@ fn wrap_device_id(id: isize) -> RootDeviceId {
 pub struct KeyEventExtra {
   pub text_with_all_modifiers: Option<&'static str>,
   pub key_without_modifiers: Key<'static>,
+  pub scancode: u32,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -139,6 +187,7 @@ mod util;
 mod clipboard;
 mod dark_mode;
 mod dpi;
+mod drag_source;
 mod drop_handler;
 mod event_loop;
 mod icon;
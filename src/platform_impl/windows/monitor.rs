@@ -2,7 +2,13 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use windows::Win32::{
-  Foundation::{BOOL, HWND, LPARAM, POINT, PWSTR, RECT},
+  Devices::Display::{
+    DisplayConfigGetDeviceInfo, GetDisplayConfigBufferSizes, QueryDisplayConfig,
+    DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME, DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME,
+    DISPLAYCONFIG_DEVICE_INFO_HEADER, DISPLAYCONFIG_MODE_INFO, DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE,
+    DISPLAYCONFIG_PATH_INFO, DISPLAYCONFIG_SOURCE_DEVICE_NAME, DISPLAYCONFIG_TARGET_DEVICE_NAME,
+  },
+  Foundation::{BOOL, ERROR_INSUFFICIENT_BUFFER, ERROR_SUCCESS, HWND, LPARAM, POINT, PWSTR, RECT},
   Graphics::Gdi::*,
 };
 
@@ -13,11 +19,12 @@ use std::{
 
 use super::util;
 use crate::{
-  dpi::{PhysicalPosition, PhysicalSize},
+  dpi::{PhysicalPosition, PhysicalSize, Position},
   monitor::{MonitorHandle as RootMonitorHandle, VideoMode as RootVideoMode},
   platform_impl::platform::{
-    dpi::{dpi_to_scale_factor, get_monitor_dpi},
+    dpi::{dpi_to_scale_factor, get_monitor_dpi, BASE_DPI},
     window::Window,
+    MaximizeMode,
   },
 };
 
@@ -84,6 +91,39 @@ impl VideoMode {
 #[derive(Debug, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
 pub struct MonitorHandle(isize);
 
+/// The monitors of the desktop, enumerated once and kept until the display configuration
+/// changes, see `WM_DISPLAYCHANGE`.
+#[derive(Debug, Clone)]
+pub(crate) struct MonitorCache {
+  pub available: VecDeque<MonitorHandle>,
+  pub primary: Option<MonitorHandle>,
+  /// The device names of `available`, in the same order. Unlike the `HMONITOR`s, which may be
+  /// reused or invalidated by a display change, they identify a monitor across changes.
+  pub device_names: Vec<Option<String>>,
+}
+
+impl MonitorCache {
+  pub fn new() -> Self {
+    let available = available_monitors();
+    let device_names = available.iter().map(MonitorHandle::name).collect();
+    Self {
+      available,
+      primary: primary_monitor(),
+      device_names,
+    }
+  }
+
+  /// The monitors of `self` whose device isn't in `other`.
+  pub fn missing_from<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a MonitorHandle> {
+    self
+      .available
+      .iter()
+      .zip(&self.device_names)
+      .filter(move |(_, name)| !other.device_names.contains(name))
+      .map(|(monitor, _)| monitor)
+  }
+}
+
 unsafe extern "system" fn monitor_enum_proc(
   hmonitor: HMONITOR,
   _hdc: HDC,
@@ -108,10 +148,11 @@ pub fn available_monitors() -> VecDeque<MonitorHandle> {
   monitors
 }
 
-pub fn primary_monitor() -> MonitorHandle {
+/// Returns `None` when there is no monitor, e.g. in some headless remote desktop sessions.
+pub fn primary_monitor() -> Option<MonitorHandle> {
   const ORIGIN: POINT = POINT { x: 0, y: 0 };
   let hmonitor = unsafe { MonitorFromPoint(ORIGIN, MONITOR_DEFAULTTOPRIMARY) };
-  MonitorHandle::new(hmonitor)
+  (!hmonitor.is_invalid()).then(|| MonitorHandle::new(hmonitor))
 }
 
 pub fn current_monitor(hwnd: HWND) -> MonitorHandle {
@@ -119,17 +160,201 @@ pub fn current_monitor(hwnd: HWND) -> MonitorHandle {
   MonitorHandle::new(hmonitor)
 }
 
+/// Returns the monitor containing `position`. Logical positions are resolved with the scale
+/// factor of each candidate monitor, as there is no single logical coordinate space on Windows.
+pub fn monitor_from_position(position: Position) -> Option<MonitorHandle> {
+  available_monitors().into_iter().find(|monitor| {
+    let PhysicalPosition { x, y } = position.to_physical::<i32>(monitor.scale_factor());
+    let origin = monitor.position();
+    let size = monitor.size();
+    x >= origin.x
+      && y >= origin.y
+      && x < origin.x + size.width as i32
+      && y < origin.y + size.height as i32
+  })
+}
+
+/// A display connected to an output of a display adapter, whether or not it is part of the
+/// desktop.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DisplayOutput {
+  /// The name of the adapter output, e.g. `\\.\DISPLAY1`. While the output is active, this
+  /// is the `native_id` of its monitor.
+  pub adapter_name: String,
+  /// The name of the display, e.g. `Generic PnP Monitor`.
+  pub name: String,
+  /// The device interface path of the display, which identifies it across reboots.
+  pub device_id: String,
+  /// Whether the display is part of the desktop, as opposed to disabled in the display
+  /// settings.
+  pub active: bool,
+  /// Whether the display shows the same content as another display of the same output.
+  pub mirrored: bool,
+  /// Whether the display is on the primary output.
+  pub primary: bool,
+  /// The position of the output on the desktop, if it is active.
+  pub position: Option<PhysicalPosition<i32>>,
+  /// The resolution of the output, if it is active.
+  pub size: Option<PhysicalSize<u32>>,
+}
+
+/// Returns the displays of all the outputs of all the display adapters, including the disabled
+/// and mirrored ones that `available_monitors` leaves out.
+pub fn all_outputs() -> Vec<DisplayOutput> {
+  let (paths, modes) = match unsafe { query_display_config(QDC_ALL_PATHS) } {
+    Some(config) => config,
+    None => return Vec::new(),
+  };
+  // A source is an output of an adapter, the targets sharing an active source mirror each other.
+  let source_key = |path: &DISPLAYCONFIG_PATH_INFO| {
+    let adapter = path.sourceInfo.adapterId;
+    (adapter.LowPart, adapter.HighPart, path.sourceInfo.id)
+  };
+  let target_key = |path: &DISPLAYCONFIG_PATH_INFO| {
+    let adapter = path.targetInfo.adapterId;
+    (adapter.LowPart, adapter.HighPart, path.targetInfo.id)
+  };
+  let is_active = |path: &&DISPLAYCONFIG_PATH_INFO| path.flags & DISPLAYCONFIG_PATH_ACTIVE != 0;
+
+  let mut outputs = Vec::new();
+  let mut listed_targets = Vec::new();
+  // Every combination of source and target is listed, an inactive display once per source it
+  // could be connected to, so the active paths are handled first to take precedence.
+  let (active_paths, inactive_paths): (Vec<_>, Vec<_>) = paths.iter().partition(is_active);
+  for &path in active_paths.iter().chain(&inactive_paths) {
+    // Targets without a display connected to them are possible outputs only.
+    if !path.targetInfo.targetAvailable.as_bool() || listed_targets.contains(&target_key(path)) {
+      continue;
+    }
+    listed_targets.push(target_key(path));
+
+    let active = is_active(&path);
+    let (target, adapter_name) = unsafe { (target_name(path), source_name(path)) };
+    let source_mode = modes
+      .get(unsafe { path.sourceInfo.Anonymous.modeInfoIdx } as usize)
+      .filter(|mode| active && mode.infoType == DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE)
+      .map(|mode| unsafe { mode.Anonymous.sourceMode });
+    let position = source_mode.map(|mode| PhysicalPosition::new(mode.position.x, mode.position.y));
+
+    outputs.push(DisplayOutput {
+      adapter_name: adapter_name.unwrap_or_default(),
+      name: target
+        .as_ref()
+        .map(|name| wide_to_string(&name.monitorFriendlyDeviceName))
+        .unwrap_or_default(),
+      device_id: target
+        .as_ref()
+        .map(|name| wide_to_string(&name.monitorDevicePath))
+        .unwrap_or_default(),
+      active,
+      mirrored: active
+        && active_paths
+          .iter()
+          .filter(|&&other| source_key(other) == source_key(path))
+          .count()
+          > 1,
+      // The primary monitor is the origin of the desktop coordinates.
+      primary: position == Some(PhysicalPosition::new(0, 0)),
+      position,
+      size: source_mode.map(|mode| PhysicalSize::new(mode.width, mode.height)),
+    });
+  }
+  outputs
+}
+
+/// Returns the paths and modes of the display configuration.
+unsafe fn query_display_config(
+  flags: u32,
+) -> Option<(Vec<DISPLAYCONFIG_PATH_INFO>, Vec<DISPLAYCONFIG_MODE_INFO>)> {
+  loop {
+    let (mut path_count, mut mode_count) = (0, 0);
+    if GetDisplayConfigBufferSizes(flags, &mut path_count, &mut mode_count) != ERROR_SUCCESS as i32
+    {
+      return None;
+    }
+    let mut paths = vec![mem::zeroed::<DISPLAYCONFIG_PATH_INFO>(); path_count as usize];
+    let mut modes = vec![mem::zeroed::<DISPLAYCONFIG_MODE_INFO>(); mode_count as usize];
+    let status = QueryDisplayConfig(
+      flags,
+      &mut path_count,
+      paths.as_mut_ptr(),
+      &mut mode_count,
+      modes.as_mut_ptr(),
+      ptr::null_mut(),
+    );
+    match status as u32 {
+      ERROR_SUCCESS => {
+        paths.truncate(path_count as usize);
+        modes.truncate(mode_count as usize);
+        return Some((paths, modes));
+      }
+      // The configuration changed since the buffer sizes were queried.
+      ERROR_INSUFFICIENT_BUFFER => continue,
+      _ => return None,
+    }
+  }
+}
+
+/// Returns the name and device path of the display of `path`.
+unsafe fn target_name(path: &DISPLAYCONFIG_PATH_INFO) -> Option<DISPLAYCONFIG_TARGET_DEVICE_NAME> {
+  let mut name: DISPLAYCONFIG_TARGET_DEVICE_NAME = mem::zeroed();
+  name.header = DISPLAYCONFIG_DEVICE_INFO_HEADER {
+    r#type: DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME,
+    size: mem::size_of_val(&name) as u32,
+    adapterId: path.targetInfo.adapterId,
+    id: path.targetInfo.id,
+  };
+  (DisplayConfigGetDeviceInfo(&mut name.header) == ERROR_SUCCESS as i32).then(|| name)
+}
+
+/// Returns the GDI name of the adapter output of `path`, e.g. `\\.\DISPLAY1`.
+unsafe fn source_name(path: &DISPLAYCONFIG_PATH_INFO) -> Option<String> {
+  let mut name: DISPLAYCONFIG_SOURCE_DEVICE_NAME = mem::zeroed();
+  name.header = DISPLAYCONFIG_DEVICE_INFO_HEADER {
+    r#type: DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME,
+    size: mem::size_of_val(&name) as u32,
+    adapterId: path.sourceInfo.adapterId,
+    id: path.sourceInfo.id,
+  };
+  (DisplayConfigGetDeviceInfo(&mut name.header) == ERROR_SUCCESS as i32)
+    .then(|| wide_to_string(&name.viewGdiDeviceName))
+}
+
+fn wide_to_string(wide: &[u16]) -> String {
+  let len = wide.iter().position(|&c| c == 0).unwrap_or(wide.len());
+  String::from_utf16_lossy(&wide[..len])
+}
+
 impl Window {
   pub fn available_monitors(&self) -> VecDeque<MonitorHandle> {
     available_monitors()
   }
 
   pub fn primary_monitor(&self) -> Option<RootMonitorHandle> {
-    let monitor = primary_monitor();
-    Some(RootMonitorHandle { inner: monitor })
+    primary_monitor().map(|inner| RootMonitorHandle { inner })
   }
 }
 
+/// Returns the rect a maximized borderless window should cover according to `mode`, together with
+/// the rect of the monitor the window is on.
+pub(crate) fn maximized_rect(hwnd: HWND, mode: MaximizeMode) -> Option<(RECT, RECT)> {
+  monitor_maximized_rect(current_monitor(hwnd).hmonitor(), mode)
+}
+
+/// Returns the rect a maximized borderless window should cover on `hmonitor` according to
+/// `mode`, together with the rect of the monitor.
+pub(crate) fn monitor_maximized_rect(
+  hmonitor: HMONITOR,
+  mode: MaximizeMode,
+) -> Option<(RECT, RECT)> {
+  let monitor_info = get_monitor_info(hmonitor).ok()?.monitorInfo;
+  let rect = match mode {
+    MaximizeMode::WorkArea => monitor_info.rcWork,
+    MaximizeMode::FullMonitor => monitor_info.rcMonitor,
+  };
+  Some((rect, monitor_info.rcMonitor))
+}
+
 pub(crate) fn get_monitor_info(hmonitor: HMONITOR) -> Result<MONITORINFOEXW, io::Error> {
   let mut monitor_info = MONITORINFOEXW::default();
   monitor_info.monitorInfo.cbSize = mem::size_of::<MONITORINFOEXW>() as u32;
@@ -151,9 +376,17 @@ impl MonitorHandle {
     MonitorHandle(hmonitor.0)
   }
 
+  /// The rect of the monitor, or of the virtual screen when the monitor isn't available, e.g.
+  /// when it was disconnected or in a headless session.
+  fn rect(&self) -> RECT {
+    get_monitor_info(self.hmonitor())
+      .map(|monitor_info| monitor_info.monitorInfo.rcMonitor)
+      .unwrap_or_else(|_| util::get_desktop_rect())
+  }
+
   #[inline]
   pub fn name(&self) -> Option<String> {
-    let mut monitor_info = get_monitor_info(self.hmonitor()).unwrap();
+    let mut monitor_info = get_monitor_info(self.hmonitor()).ok()?;
     Some(util::wchar_ptr_to_string(PWSTR(
       monitor_info.szDevice.as_mut_ptr(),
     )))
@@ -161,7 +394,7 @@ impl MonitorHandle {
 
   #[inline]
   pub fn native_identifier(&self) -> String {
-    self.name().unwrap()
+    self.name().unwrap_or_default()
   }
 
   #[inline]
@@ -171,27 +404,25 @@ impl MonitorHandle {
 
   #[inline]
   pub fn size(&self) -> PhysicalSize<u32> {
-    let monitor_info = get_monitor_info(self.hmonitor()).unwrap();
+    let rect = self.rect();
     PhysicalSize {
-      width: (monitor_info.monitorInfo.rcMonitor.right - monitor_info.monitorInfo.rcMonitor.left)
-        as u32,
-      height: (monitor_info.monitorInfo.rcMonitor.bottom - monitor_info.monitorInfo.rcMonitor.top)
-        as u32,
+      width: (rect.right - rect.left) as u32,
+      height: (rect.bottom - rect.top) as u32,
     }
   }
 
   #[inline]
   pub fn position(&self) -> PhysicalPosition<i32> {
-    let monitor_info = get_monitor_info(self.hmonitor()).unwrap();
+    let rect = self.rect();
     PhysicalPosition {
-      x: monitor_info.monitorInfo.rcMonitor.left,
-      y: monitor_info.monitorInfo.rcMonitor.top,
+      x: rect.left,
+      y: rect.top,
     }
   }
 
   #[inline]
   pub fn scale_factor(&self) -> f64 {
-    dpi_to_scale_factor(get_monitor_dpi(self.hmonitor()).unwrap_or(96))
+    dpi_to_scale_factor(get_monitor_dpi(self.hmonitor()).unwrap_or(BASE_DPI))
   }
 
   #[inline]
@@ -204,7 +435,10 @@ impl MonitorHandle {
 
     loop {
       unsafe {
-        let mut monitor_info = get_monitor_info(self.hmonitor()).unwrap();
+        let mut monitor_info = match get_monitor_info(self.hmonitor()) {
+          Ok(monitor_info) => monitor_info,
+          Err(_) => break,
+        };
         let device_name = PWSTR(monitor_info.szDevice.as_mut_ptr());
         let mut mode: DEVMODEW = mem::zeroed();
         mode.dmSize = mem::size_of_val(&mode) as u16;
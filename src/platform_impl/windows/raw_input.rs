@@ -15,7 +15,7 @@ use windows::Win32::{
   },
 };
 
-use crate::{event::ElementState, platform_impl::platform::util};
+use crate::{event::ElementState, event_loop::DeviceEventFilter, platform_impl::platform::util};
 
 #[allow(dead_code)]
 pub fn get_raw_input_device_list() -> Option<Vec<RAWINPUTDEVICELIST>> {
@@ -131,10 +131,22 @@ pub fn register_raw_input_devices(devices: &[RAWINPUTDEVICE]) -> bool {
   success.as_bool()
 }
 
-pub fn register_all_mice_and_keyboards_for_raw_input(window_handle: HWND) -> bool {
+pub fn register_all_mice_and_keyboards_for_raw_input(
+  mut window_handle: HWND,
+  filter: DeviceEventFilter,
+) -> bool {
   // RIDEV_DEVNOTIFY: receive hotplug events
   // RIDEV_INPUTSINK: receive events even if we're not in the foreground
-  let flags = RIDEV_DEVNOTIFY | RIDEV_INPUTSINK;
+  // RIDEV_REMOVE: don't receive device events
+  let flags: RAWINPUTDEVICE_FLAGS = match filter {
+    DeviceEventFilter::Always => RIDEV_DEVNOTIFY | RIDEV_INPUTSINK,
+    DeviceEventFilter::WhenFocused => RIDEV_DEVNOTIFY,
+    DeviceEventFilter::Never => {
+      // `hwndTarget` must be null when removing a registration.
+      window_handle = HWND::default();
+      RIDEV_REMOVE
+    }
+  };
 
   let devices: [RAWINPUTDEVICE; 2] = [
     RAWINPUTDEVICE {
@@ -155,15 +167,31 @@ pub fn register_all_mice_and_keyboards_for_raw_input(window_handle: HWND) -> boo
 }
 
 pub fn get_raw_input_data(handle: HRAWINPUT) -> Option<RAWINPUT> {
-  let mut data: RAWINPUT = unsafe { mem::zeroed() };
-  let mut data_size = size_of::<RAWINPUT>() as u32;
   let header_size = size_of::<RAWINPUTHEADER>() as u32;
 
+  let mut data_size = 0;
   let status = unsafe {
     GetRawInputData(
       handle,
       RID_INPUT,
-      &mut data as *mut _ as _,
+      ptr::null_mut(),
+      &mut data_size,
+      header_size,
+    )
+  };
+  if status != 0 {
+    return None;
+  }
+
+  // The data of HID devices can be larger than `RAWINPUT`, in which case only its beginning is
+  // kept. The buffer is made of `u64`s to be aligned like `RAWINPUT`.
+  let buffer_size = (data_size as usize).max(size_of::<RAWINPUT>());
+  let mut buffer = vec![0u64; (buffer_size + 7) / 8];
+  let status = unsafe {
+    GetRawInputData(
+      handle,
+      RID_INPUT,
+      buffer.as_mut_ptr() as _,
       &mut data_size,
       header_size,
     )
@@ -173,7 +201,52 @@ pub fn get_raw_input_data(handle: HRAWINPUT) -> Option<RAWINPUT> {
     return None;
   }
 
-  Some(data)
+  Some(unsafe { ptr::read(buffer.as_ptr() as *const RAWINPUT) })
+}
+
+/// Reads the raw input queued for the thread all at once, removing the `WM_INPUT` messages it
+/// came with. This is cheaper than reading it message by message for high polling rate devices.
+pub fn get_raw_input_buffer() -> Vec<RAWINPUT> {
+  let header_size = size_of::<RAWINPUTHEADER>() as u32;
+  let mut inputs = Vec::new();
+
+  // The minimum size is enough for the largest queued input, the buffer holds a few of them.
+  let mut min_size = 0;
+  let status = unsafe { GetRawInputBuffer(ptr::null_mut(), &mut min_size, header_size) };
+  if status != 0 || min_size == 0 {
+    return inputs;
+  }
+  let mut buffer = vec![0u64; (min_size as usize * 16 + 7) / 8];
+
+  loop {
+    let mut size = (buffer.len() * 8) as u32;
+    let count =
+      unsafe { GetRawInputBuffer(buffer.as_mut_ptr() as *mut RAWINPUT, &mut size, header_size) };
+    if count == 0 || count == u32::max_value() {
+      break;
+    }
+
+    let mut offset = 0;
+    for _ in 0..count {
+      unsafe {
+        let input = (buffer.as_ptr() as *const u8).add(offset);
+        let input_size = (*(input as *const RAWINPUTHEADER)).dwSize as usize;
+
+        let mut data: RAWINPUT = mem::zeroed();
+        ptr::copy_nonoverlapping(
+          input,
+          &mut data as *mut _ as *mut u8,
+          input_size.min(size_of::<RAWINPUT>()),
+        );
+        inputs.push(data);
+
+        // Implementation of the `NEXTRAWINPUTBLOCK` macro, the inputs are aligned to 8 bytes.
+        offset += (input_size + 7) & !7;
+      }
+    }
+  }
+
+  inputs
 }
 
 fn button_flags_to_element_state(
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use std::{
+  cell::Cell,
   io, mem,
   ops::BitAnd,
   os::windows::prelude::OsStrExt,
@@ -14,9 +15,12 @@ use crate::{dpi::PhysicalSize, window::CursorIcon};
 use windows::{
   core::HRESULT,
   Win32::{
-    Foundation::{BOOL, FARPROC, HWND, LPARAM, LRESULT, POINT, PWSTR, RECT, WPARAM},
+    Foundation::{BOOL, FARPROC, HINSTANCE, HWND, LPARAM, LRESULT, POINT, PWSTR, RECT, WPARAM},
     Globalization::lstrlenW,
-    Graphics::Gdi::{ClientToScreen, InvalidateRgn, HMONITOR, HRGN},
+    Graphics::{
+      Dwm::{DwmEnableBlurBehindWindow, DWM_BB_BLURREGION, DWM_BB_ENABLE, DWM_BLURBEHIND},
+      Gdi::{ClientToScreen, CreateRectRgn, DeleteObject, InvalidateRgn, HMONITOR, HRGN},
+    },
     System::LibraryLoader::*,
     UI::{
       HiDpi::*,
@@ -176,6 +180,17 @@ pub fn get_cursor_clip() -> Result<RECT, io::Error> {
   }
 }
 
+pub fn flash_window(hwnd: HWND, flags: FLASHWINFO_FLAGS, count: u32) {
+  let flash_info = FLASHWINFO {
+    cbSize: mem::size_of::<FLASHWINFO>() as u32,
+    hwnd,
+    dwFlags: flags,
+    uCount: count,
+    dwTimeout: 0,
+  };
+  unsafe { FlashWindowEx(&flash_info) };
+}
+
 /// Sets the cursor's clip rect.
 ///
 /// Note that calling this will automatically dispatch a `WM_MOUSEMOVE` event.
@@ -185,10 +200,28 @@ pub fn set_cursor_clip(rect: Option<RECT>) -> Result<(), io::Error> {
       .as_ref()
       .map(|r| r as *const RECT)
       .unwrap_or(ptr::null());
-    win_to_err(|| ClipCursor(rect_ptr))
+    win_to_err(|| ClipCursor(rect_ptr))?;
+  }
+  CURSOR_CLIP.with(|clip| clip.set(rect));
+  Ok(())
+}
+
+/// Releases the cursor clip set by `set_cursor_clip` on this thread, unless it was replaced in
+/// the meantime, e.g. by another application.
+pub fn release_cursor_clip() {
+  if let Some(rect) = CURSOR_CLIP.with(|clip| clip.take()) {
+    let rect_to_tuple = |rect: RECT| (rect.left, rect.top, rect.right, rect.bottom);
+    if get_cursor_clip().map(rect_to_tuple).ok() == Some(rect_to_tuple(rect)) {
+      let _ = set_cursor_clip(None);
+    }
   }
 }
 
+thread_local! {
+  /// The clip rect last set by `set_cursor_clip` on this thread.
+  static CURSOR_CLIP: Cell<Option<RECT>> = Cell::new(None);
+}
+
 pub fn get_desktop_rect() -> RECT {
   unsafe {
     let left = GetSystemMetrics(SM_XVIRTUALSCREEN);
@@ -210,7 +243,7 @@ pub fn is_visible(window: HWND) -> bool {
   unsafe { IsWindowVisible(window).as_bool() }
 }
 
-pub fn is_maximized(window: HWND) -> bool {
+pub fn get_window_placement(window: HWND) -> WINDOWPLACEMENT {
   let mut placement = WINDOWPLACEMENT {
     length: mem::size_of::<WINDOWPLACEMENT>() as u32,
     ..WINDOWPLACEMENT::default()
@@ -218,7 +251,11 @@ pub fn is_maximized(window: HWND) -> bool {
   unsafe {
     GetWindowPlacement(window, &mut placement);
   }
-  placement.showCmd == SW_MAXIMIZE
+  placement
+}
+
+pub fn is_maximized(window: HWND) -> bool {
+  get_window_placement(window).showCmd == SW_MAXIMIZE
 }
 
 pub fn get_hicon_from_buffer(buffer: &[u8], width: i32, height: i32) -> Option<HICON> {
@@ -260,11 +297,19 @@ pub fn get_hicon_from_buffer(buffer: &[u8], width: i32, height: i32) -> Option<H
 }
 
 impl CursorIcon {
+  /// Loads the cursor to pass to `SetCursor`. `CursorIcon::None` is a null cursor, which hides it.
+  pub(crate) fn load(self) -> HCURSOR {
+    match self {
+      CursorIcon::None => HCURSOR::default(),
+      cursor => unsafe { LoadCursorW(HINSTANCE::default(), cursor.to_windows_cursor()) },
+    }
+  }
+
   pub(crate) fn to_windows_cursor(self) -> PWSTR {
     match self {
       CursorIcon::Arrow | CursorIcon::Default => IDC_ARROW,
       CursorIcon::Hand => IDC_HAND,
-      CursorIcon::Crosshair => IDC_CROSS,
+      CursorIcon::Crosshair | CursorIcon::Cell => IDC_CROSS,
       CursorIcon::Text | CursorIcon::VerticalText => IDC_IBEAM,
       CursorIcon::NotAllowed | CursorIcon::NoDrop => IDC_NO,
       CursorIcon::Grab | CursorIcon::Grabbing | CursorIcon::Move | CursorIcon::AllScroll => {
@@ -281,11 +326,32 @@ impl CursorIcon {
       CursorIcon::Wait => IDC_WAIT,
       CursorIcon::Progress => IDC_APPSTARTING,
       CursorIcon::Help => IDC_HELP,
-      _ => IDC_ARROW, // use arrow for the missing cases.
+      // The system has no cursor for the others, e.g. `ContextMenu`, `Alias`, `Copy`, `ZoomIn`
+      // and `ZoomOut`, the closest one is the arrow.
+      _ => IDC_ARROW,
     }
   }
 }
 
+/// Enables or disables DWM blur-behind for the whole client area, which is what makes the window
+/// transparent.
+pub fn set_blur_behind(hwnd: HWND, enabled: bool) {
+  unsafe {
+    // Empty region for the blur effect, so the window is fully transparent
+    let region = CreateRectRgn(0, 0, -1, -1);
+
+    let bb = DWM_BLURBEHIND {
+      dwFlags: DWM_BB_ENABLE | DWM_BB_BLURREGION,
+      fEnable: enabled.into(),
+      hRgnBlur: region,
+      fTransitionOnMaximized: false.into(),
+    };
+
+    let _ = DwmEnableBlurBehindWindow(hwnd, &bb);
+    DeleteObject(region);
+  }
+}
+
 // Helper function to dynamically load function pointer.
 // `library` and `function` must be zero-terminated.
 pub(super) fn get_function_impl(library: &str, function: &str) -> FARPROC {
@@ -316,7 +382,13 @@ pub type SetProcessDpiAwareness =
   unsafe extern "system" fn(value: PROCESS_DPI_AWARENESS) -> HRESULT;
 pub type SetProcessDpiAwarenessContext =
   unsafe extern "system" fn(value: DPI_AWARENESS_CONTEXT) -> BOOL;
+pub type SetThreadDpiAwarenessContext =
+  unsafe extern "system" fn(value: DPI_AWARENESS_CONTEXT) -> DPI_AWARENESS_CONTEXT;
 pub type GetDpiForWindow = unsafe extern "system" fn(hwnd: HWND) -> u32;
+pub type GetWindowDpiAwarenessContext =
+  unsafe extern "system" fn(hwnd: HWND) -> DPI_AWARENESS_CONTEXT;
+pub type GetAwarenessFromDpiAwarenessContext =
+  unsafe extern "system" fn(value: DPI_AWARENESS_CONTEXT) -> DPI_AWARENESS;
 pub type GetDpiForMonitor = unsafe extern "system" fn(
   hmonitor: HMONITOR,
   dpi_type: MONITOR_DPI_TYPE,
@@ -335,6 +407,10 @@ pub type AdjustWindowRectExForDpi = unsafe extern "system" fn(
 lazy_static! {
   pub static ref GET_DPI_FOR_WINDOW: Option<GetDpiForWindow> =
     get_function!("user32.dll", GetDpiForWindow);
+  pub static ref GET_WINDOW_DPI_AWARENESS_CONTEXT: Option<GetWindowDpiAwarenessContext> =
+    get_function!("user32.dll", GetWindowDpiAwarenessContext);
+  pub static ref GET_AWARENESS_FROM_DPI_AWARENESS_CONTEXT: Option<GetAwarenessFromDpiAwarenessContext> =
+    get_function!("user32.dll", GetAwarenessFromDpiAwarenessContext);
   pub static ref ADJUST_WINDOW_RECT_EX_FOR_DPI: Option<AdjustWindowRectExForDpi> =
     get_function!("user32.dll", AdjustWindowRectExForDpi);
   pub static ref GET_DPI_FOR_MONITOR: Option<GetDpiForMonitor> =
@@ -343,6 +419,8 @@ lazy_static! {
     get_function!("user32.dll", EnableNonClientDpiScaling);
   pub static ref SET_PROCESS_DPI_AWARENESS_CONTEXT: Option<SetProcessDpiAwarenessContext> =
     get_function!("user32.dll", SetProcessDpiAwarenessContext);
+  pub static ref SET_THREAD_DPI_AWARENESS_CONTEXT: Option<SetThreadDpiAwarenessContext> =
+    get_function!("user32.dll", SetThreadDpiAwarenessContext);
   pub static ref SET_PROCESS_DPI_AWARENESS: Option<SetProcessDpiAwareness> =
     get_function!("shcore.dll", SetProcessDpiAwareness);
   pub static ref SET_PROCESS_DPI_AWARE: Option<SetProcessDPIAware> =
@@ -439,3 +517,33 @@ pub unsafe extern "system" fn call_default_window_proc(
 ) -> LRESULT {
   DefWindowProcW(hwnd, msg, wparam, lparam)
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn cursor_clip_set_before_a_panic_is_released() {
+    let rect = RECT {
+      left: 0,
+      top: 0,
+      right: 100,
+      bottom: 100,
+    };
+    // The clip can't be set without an interactive desktop, so there is nothing to release.
+    if set_cursor_clip(Some(rect)).is_err() {
+      return;
+    }
+    let clip = get_cursor_clip().unwrap();
+
+    // What `release_system_mouse` does when a callback panicked while a window grabbed the cursor.
+    release_cursor_clip();
+    assert_ne!(get_cursor_clip().unwrap(), clip);
+
+    // A clip set by another application is left alone.
+    unsafe { ClipCursor(&clip) };
+    release_cursor_clip();
+    assert_eq!(get_cursor_clip().unwrap(), clip);
+    set_cursor_clip(None).unwrap();
+  }
+}
@@ -8,9 +8,11 @@ use parking_lot::Mutex;
 use raw_window_handle::{RawWindowHandle, Win32Handle};
 use std::{
   cell::{Cell, RefCell},
+  collections::HashSet,
   ffi::OsStr,
   io, mem,
   os::windows::ffi::OsStrExt,
+  path::PathBuf,
   ptr,
   sync::Arc,
 };
@@ -18,10 +20,8 @@ use std::{
 use crossbeam_channel as channel;
 use windows::Win32::{
   Foundation::{self as win32f, HINSTANCE, HWND, LPARAM, LRESULT, POINT, PWSTR, RECT, WPARAM},
-  Graphics::{
-    Dwm::{DwmEnableBlurBehindWindow, DWM_BB_BLURREGION, DWM_BB_ENABLE, DWM_BLURBEHIND},
-    Gdi::*,
-  },
+  Globalization::HIMC,
+  Graphics::Gdi::*,
   System::{Com::*, LibraryLoader::*, Ole::*},
   UI::{
     Input::{Ime::*, KeyboardAndMouse::*, Touch::*},
@@ -33,22 +33,30 @@ use windows::Win32::{
 use crate::{
   dpi::{PhysicalPosition, PhysicalSize, Position, Size},
   error::{ExternalError, NotSupportedError, OsError as RootOsError},
-  icon::Icon,
+  event::MouseButtons,
+  icon::{CustomCursor, CustomCursorSource, Icon},
+  keyboard::KeyCode,
   menu::MenuType,
   monitor::MonitorHandle as RootMonitorHandle,
   platform_impl::platform::{
     dark_mode::try_theme,
-    dpi::{dpi_to_scale_factor, hwnd_dpi},
+    dpi::{
+      dpi_to_scale_factor, hwnd_dpi, is_per_monitor_dpi_aware, set_thread_dpi_awareness_context,
+    },
+    drag_source::{self, DropEffect},
     drop_handler::FileDropHandler,
-    event_loop::{self, EventLoopWindowTarget, DESTROY_MSG_ID},
+    event_loop::{
+      self, EventLoopWindowTarget, DECORATIONS_CHANGED_MSG_ID, DESTROY_MSG_ID,
+      IME_ALLOWED_CHANGED_MSG_ID, REPORT_INITIAL_FOCUS_MSG_ID, SET_FORCE_DEF_WINDOW_PROC_MSG_ID,
+    },
     icon::{self, IconType},
     menu, monitor, util,
     window_state::{CursorFlags, SavedWindow, WindowFlags, WindowState},
-    OsError, Parent, PlatformSpecificWindowBuilderAttributes, WindowId,
+    MaximizeMode, OsError, Parent, PlatformSpecificWindowBuilderAttributes, WindowId,
   },
   window::{
-    CursorIcon, Fullscreen, Theme, UserAttentionType, WindowAttributes, WindowId as RootWindowId,
-    BORDERLESS_RESIZE_INSET,
+    CursorIcon, Fullscreen, ProgressBarState, ProgressState, ResizeDirection, Theme,
+    UserAttentionType, WindowAttributes, WindowId as RootWindowId, BORDERLESS_RESIZE_INSET,
   },
 };
 
@@ -120,6 +128,7 @@ impl Window {
 
         let subclass_input = event_loop::SubclassInput {
           window_state: win.window_state.clone(),
+          forced_def_window_proc: Default::default(),
           event_loop_runner: event_loop.runner_shared.clone(),
           _file_drop_handler: file_drop_handler,
           subclass_removed: Cell::new(false),
@@ -133,9 +142,19 @@ impl Window {
   }
 
   pub fn set_title(&self, text: &str) {
-    unsafe {
-      SetWindowTextW(self.window.0, text);
-    }
+    let window = self.window.clone();
+    let text = text.to_owned();
+    self.window_state.lock().title = text.clone();
+    // `SetWindowTextW` sends `WM_SETTEXT`, which blocks another thread until the event loop
+    // thread handles it.
+    self.thread_executor.execute_in_thread(move || unsafe {
+      SetWindowTextW(window.0, text.as_str());
+    });
+  }
+
+  #[inline]
+  pub fn title(&self) -> String {
+    self.window_state.lock().title.clone()
   }
 
   // TODO (lemarier): allow menu update
@@ -149,6 +168,13 @@ impl Window {
       WindowState::set_window_flags(window_state.lock(), window.0, |f| {
         f.set(WindowFlags::VISIBLE, visible)
       });
+      // A window created with `WindowBuilder::with_focused(false)` is only shown without being
+      // activated the first time, afterwards it's activated like any other window.
+      if visible {
+        WindowState::set_window_flags(window_state.lock(), window.0, |f| {
+          f.remove(WindowFlags::NO_ACTIVATE)
+        });
+      }
     });
   }
 
@@ -168,14 +194,16 @@ impl Window {
 
   #[inline]
   pub fn request_redraw(&self) {
-    unsafe {
-      RedrawWindow(
-        self.window.0,
-        ptr::null(),
-        HRGN::default(),
-        RDW_INTERNALPAINT,
-      );
+    if !self.window_state.lock().request_redraw() {
+      return;
     }
+
+    let window = self.window.clone();
+    // Posted to the event loop thread when called from another thread, which also wakes up the
+    // event loop so the redraw isn't delayed until the next message.
+    self.thread_executor.execute_in_thread(move || unsafe {
+      RedrawWindow(window.0, ptr::null(), HRGN::default(), RDW_INTERNALPAINT);
+    });
   }
 
   #[inline]
@@ -260,6 +288,65 @@ impl Window {
     util::set_inner_size_physical(self.window.0, width, height);
   }
 
+  #[inline]
+  pub fn content_size(&self) -> PhysicalSize<u32> {
+    // The menu bar is in the non-client area, the client area is the content area.
+    self.inner_size()
+  }
+
+  #[inline]
+  pub fn set_content_size(&self, size: Size) {
+    let scale_factor = self.scale_factor();
+    let (width, height): (u32, u32) = size.to_physical::<u32>(scale_factor).into();
+
+    let window_state = Arc::clone(&self.window_state);
+    let window = self.window.clone();
+    self.thread_executor.execute_in_thread(move || unsafe {
+      WindowState::set_window_flags(window_state.lock(), window.0, |f| {
+        f.set(WindowFlags::MAXIMIZED, false)
+      });
+
+      let content_rect = RECT {
+        left: 0,
+        top: 0,
+        right: width as i32,
+        bottom: height as i32,
+      };
+      let rect = match util::adjust_window_rect(window.0, content_rect) {
+        Some(rect) => rect,
+        None => return,
+      };
+      let (outer_width, outer_height) = (rect.right - rect.left, rect.bottom - rect.top);
+      let flags = SWP_NOZORDER | SWP_NOMOVE | SWP_NOACTIVATE;
+      SetWindowPos(
+        window.0,
+        HWND::default(),
+        0,
+        0,
+        outer_width,
+        outer_height,
+        flags,
+      );
+
+      // `AdjustWindowRectExForDpi` assumes the menu bar fits on a single line, a menu bar
+      // wrapped at the new width takes the height of its other lines from the client area.
+      if let Ok(client_rect) = util::get_client_rect(window.0) {
+        let missing_height = height as i32 - (client_rect.bottom - client_rect.top);
+        if missing_height > 0 {
+          SetWindowPos(
+            window.0,
+            HWND::default(),
+            0,
+            0,
+            outer_width,
+            outer_height + missing_height,
+            flags,
+          );
+        }
+      }
+    });
+  }
+
   #[inline]
   pub fn set_min_inner_size(&self, size: Option<Size>) {
     self.window_state.lock().min_size = size;
@@ -288,6 +375,11 @@ impl Window {
     });
   }
 
+  #[inline]
+  pub fn set_resize_border_width(&self, width: u32) {
+    self.window_state.lock().resize_border_width = Some(width);
+  }
+
   /// Returns the `hwnd` of this window.
   #[inline]
   pub fn hwnd(&self) -> HWND {
@@ -309,13 +401,74 @@ impl Window {
 
   #[inline]
   pub fn set_cursor_icon(&self, cursor: CursorIcon) {
-    self.window_state.lock().mouse.cursor = cursor;
-    self.thread_executor.execute_in_thread(move || unsafe {
-      let cursor = LoadCursorW(HINSTANCE::default(), cursor.to_windows_cursor());
-      SetCursor(cursor);
+    let previous_cursor = {
+      let mut window_state = self.window_state.lock();
+      window_state.mouse.cursor = cursor;
+      window_state.mouse.custom_cursor.take()
+    };
+    self.refresh_cursor(previous_cursor);
+  }
+
+  #[inline]
+  pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+    let cursor = match &cursor.inner {
+      CustomCursorSource::Rgba {
+        image,
+        hotspot_x,
+        hotspot_y,
+      } => match icon::WinCursor::from_rgba(image, *hotspot_x, *hotspot_y) {
+        Ok(cursor) => cursor,
+        Err(e) => {
+          warn!("Failed to create the custom cursor: {}", e);
+          return;
+        }
+      },
+      CustomCursorSource::Platform(cursor) => cursor.clone(),
+    };
+    let previous_cursor = self.window_state.lock().mouse.custom_cursor.replace(cursor);
+    self.refresh_cursor(previous_cursor);
+  }
+
+  #[inline]
+  pub fn set_busy_cursor(&self, busy: bool) {
+    self.window_state.lock().mouse.busy = busy;
+    self.refresh_cursor(None);
+  }
+
+  /// Applies the current cursor right away if the cursor is in the client area, rather than on
+  /// the next `WM_SETCURSOR`. The busy and custom cursors take precedence over the cursor icon.
+  ///
+  /// The replaced custom cursor may still be the one shown, it's only destroyed once it was
+  /// replaced with `SetCursor`.
+  fn refresh_cursor(&self, previous_cursor: Option<icon::WinCursor>) {
+    let window_state = Arc::clone(&self.window_state);
+    self.thread_executor.execute_in_thread(move || {
+      let window_state = window_state.lock();
+      if window_state
+        .mouse
+        .cursor_flags()
+        .contains(CursorFlags::IN_WINDOW)
+      {
+        unsafe {
+          SetCursor(window_state.mouse.current_cursor());
+        }
+      }
+      drop(previous_cursor);
     });
   }
 
+  #[inline]
+  pub fn start_drag(&self, paths: Vec<PathBuf>) -> Result<DropEffect, ExternalError> {
+    let (tx, rx) = channel::unbounded();
+
+    self.thread_executor.execute_in_thread(move || {
+      let result = drag_source::start_drag(&paths)
+        .map_err(|e| ExternalError::Os(os_error!(OsError::IoError(e))));
+      let _ = tx.send(result);
+    });
+    rx.recv().unwrap()
+  }
+
   #[inline]
   pub fn set_cursor_grab(&self, grab: bool) -> Result<(), ExternalError> {
     let window = self.window.clone();
@@ -350,6 +503,18 @@ impl Window {
     rx.recv().unwrap().ok();
   }
 
+  #[inline]
+  pub fn set_ignore_cursor_events(&self, ignore: bool) -> Result<(), ExternalError> {
+    let window = self.window.clone();
+    let window_state = Arc::clone(&self.window_state);
+    self.thread_executor.execute_in_thread(move || {
+      WindowState::set_window_flags(window_state.lock(), window.0, |f| {
+        f.set(WindowFlags::IGNORE_CURSOR_EVENT, ignore)
+      });
+    });
+    Ok(())
+  }
+
   #[inline]
   pub fn scale_factor(&self) -> f64 {
     self.window_state.lock().scale_factor
@@ -393,6 +558,33 @@ impl Window {
     Ok(())
   }
 
+  #[inline]
+  pub fn drag_resize_window(&self, direction: ResizeDirection) -> Result<(), ExternalError> {
+    let hit_test = match direction {
+      ResizeDirection::North => HTTOP,
+      ResizeDirection::NorthEast => HTTOPRIGHT,
+      ResizeDirection::East => HTRIGHT,
+      ResizeDirection::SouthEast => HTBOTTOMRIGHT,
+      ResizeDirection::South => HTBOTTOM,
+      ResizeDirection::SouthWest => HTBOTTOMLEFT,
+      ResizeDirection::West => HTLEFT,
+      ResizeDirection::NorthWest => HTTOPLEFT,
+    };
+    let mut pos = POINT::default();
+    unsafe {
+      GetCursorPos(&mut pos);
+      ReleaseCapture();
+      PostMessageW(
+        self.window.0,
+        WM_NCLBUTTONDOWN,
+        WPARAM(hit_test as _),
+        util::MAKELPARAM(pos.x as i16, pos.y as i16),
+      );
+    }
+
+    Ok(())
+  }
+
   #[inline]
   pub fn id(&self) -> WindowId {
     WindowId(self.window.0 .0)
@@ -422,6 +614,39 @@ impl Window {
     });
   }
 
+  #[inline]
+  pub fn set_maximize_mode(&self, mode: MaximizeMode) {
+    let window = self.window.clone();
+    let window_state = Arc::clone(&self.window_state);
+
+    self.thread_executor.execute_in_thread(move || {
+      let decorations = {
+        let mut window_state = window_state.lock();
+        window_state.maximize_mode = mode;
+        window_state
+          .window_flags()
+          .contains(WindowFlags::DECORATIONS)
+      };
+
+      // Move an already maximized borderless window to its new area.
+      if !decorations && util::is_maximized(window.0) {
+        if let Some((rect, _)) = monitor::maximized_rect(window.0, mode) {
+          unsafe {
+            SetWindowPos(
+              window.0,
+              HWND::default(),
+              rect.left,
+              rect.top,
+              rect.right - rect.left,
+              rect.bottom - rect.top,
+              SWP_NOZORDER | SWP_NOACTIVATE | SWP_FRAMECHANGED,
+            );
+          }
+        }
+      }
+    });
+  }
+
   #[inline]
   pub fn is_maximized(&self) -> bool {
     let window_state = self.window_state.lock();
@@ -465,6 +690,17 @@ impl Window {
     drop(window_state_lock);
 
     self.thread_executor.execute_in_thread(move || {
+      // Save the windowed placement before anything touches the window's styles, position or
+      // the display mode. Switching between fullscreen modes keeps the originally saved one.
+      if fullscreen.is_some() {
+        let mut window_state_lock = window_state.lock();
+        if window_state_lock.saved_window.is_none() {
+          window_state_lock.saved_window = Some(SavedWindow {
+            placement: util::get_window_placement(window.0),
+          });
+        }
+      }
+
       // Change video mode if we're transitioning to or from exclusive
       // fullscreen
       match (&old_fullscreen, &fullscreen) {
@@ -533,6 +769,14 @@ impl Window {
         PeekMessageW(&mut msg, HWND::default(), 0, 0, PM_NOREMOVE);
       }
 
+      // When leaving fullscreen, the style change resizes the client area before the windowed
+      // placement is restored. Only the restored size is reported, once both are done.
+      if fullscreen.is_none() {
+        window_state
+          .lock()
+          .set_window_flags_in_place(|f| f.insert(WindowFlags::MARKER_IN_SIZE_TRANSITION));
+      }
+
       // Update window style
       WindowState::set_window_flags(window_state.lock(), window.0, |f| {
         f.set(
@@ -548,15 +792,6 @@ impl Window {
       // Update window bounds
       match &fullscreen {
         Some(fullscreen) => {
-          // Save window bounds before entering fullscreen
-          let placement = unsafe {
-            let mut placement = WINDOWPLACEMENT::default();
-            GetWindowPlacement(window.0, &mut placement);
-            placement
-          };
-
-          window_state.lock().saved_window = Some(SavedWindow { placement });
-
           let monitor = match &fullscreen {
             Fullscreen::Exclusive(video_mode) => video_mode.monitor(),
             Fullscreen::Borderless(Some(monitor)) => monitor.clone(),
@@ -590,12 +825,72 @@ impl Window {
               InvalidateRgn(window.0, HRGN::default(), false);
             }
           }
+
+          window_state
+            .lock()
+            .set_window_flags_in_place(|f| f.remove(WindowFlags::MARKER_IN_SIZE_TRANSITION));
+          // Let `WM_SIZE` report the restored size. A minimized window reports its size once
+          // it's restored, reporting it now would mark it as no longer minimized.
+          let minimized = unsafe { IsIconic(window.0) }.as_bool();
+          if let Some(rect) = util::get_client_rect(window.0).ok().filter(|_| !minimized) {
+            let size_kind = if util::is_maximized(window.0) {
+              SIZE_MAXIMIZED
+            } else {
+              SIZE_RESTORED
+            };
+            unsafe {
+              SendMessageW(
+                window.0,
+                WM_SIZE,
+                WPARAM(size_kind as _),
+                util::MAKELPARAM(
+                  (rect.right - rect.left) as i16,
+                  (rect.bottom - rect.top) as i16,
+                ),
+              );
+            }
+          }
         }
       }
 
       unsafe {
         taskbar_mark_fullscreen(window.0, fullscreen.is_some());
       }
+
+      // Changing the styles and the display mode can lose the topmost state, the display
+      // affinity, and makes the shell add the taskbar button back, so they are applied again
+      // from the stored state.
+      let (always_on_top, skip_taskbar, content_protected) = {
+        let window_state_lock = window_state.lock();
+        (
+          window_state_lock
+            .window_flags()
+            .intersects(WindowFlags::ALWAYS_ON_TOP | WindowFlags::MARKER_EXCLUSIVE_FULLSCREEN),
+          window_state_lock.skip_taskbar,
+          window_state_lock.content_protected,
+        )
+      };
+      unsafe {
+        SetWindowPos(
+          window.0,
+          if always_on_top {
+            HWND_TOPMOST
+          } else {
+            HWND_NOTOPMOST
+          },
+          0,
+          0,
+          0,
+          0,
+          SWP_ASYNCWINDOWPOS | SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+        );
+        if skip_taskbar {
+          taskbar_set_skip(window.0, true);
+        }
+        if content_protected {
+          set_display_affinity(window.0, true);
+        }
+      }
     });
   }
 
@@ -605,9 +900,24 @@ impl Window {
     let window_state = Arc::clone(&self.window_state);
 
     self.thread_executor.execute_in_thread(move || {
-      WindowState::set_window_flags(window_state.lock(), window.0, |f| {
+      let window_state = window_state.lock();
+      let changed = window_state
+        .window_flags()
+        .contains(WindowFlags::DECORATIONS)
+        != decorations;
+      WindowState::set_window_flags(window_state, window.0, |f| {
         f.set(WindowFlags::DECORATIONS, decorations)
       });
+      if changed {
+        unsafe {
+          SendMessageW(
+            window.0,
+            *DECORATIONS_CHANGED_MSG_ID,
+            WPARAM(decorations as _),
+            LPARAM(0),
+          );
+        }
+      }
     });
   }
 
@@ -623,6 +933,26 @@ impl Window {
     });
   }
 
+  #[inline]
+  pub fn set_activate_on_click(&self, activate_on_click: bool) {
+    self.window_state.lock().activate_on_click = activate_on_click;
+  }
+
+  #[inline]
+  pub fn set_transparent(&self, transparent: bool) {
+    let window = self.window.clone();
+    let window_state = Arc::clone(&self.window_state);
+
+    self.thread_executor.execute_in_thread(move || {
+      WindowState::set_window_flags(window_state.lock(), window.0, |f| {
+        f.set(WindowFlags::TRANSPARENT, transparent)
+      });
+      unsafe {
+        InvalidateRgn(window.0, HRGN::default(), false);
+      }
+    });
+  }
+
   #[inline]
   pub fn current_monitor(&self) -> Option<RootMonitorHandle> {
     Some(RootMonitorHandle {
@@ -661,9 +991,17 @@ impl Window {
         ptCurrentPos: POINT { x, y },
         rcArea: RECT::default(),
       };
+      // Some IMEs place the candidate list on their own rather than next to the composition.
+      let candidate_form = CANDIDATEFORM {
+        dwIndex: 0,
+        dwStyle: CFS_CANDIDATEPOS,
+        ptCurrentPos: POINT { x, y },
+        rcArea: RECT::default(),
+      };
       unsafe {
         let himc = ImmGetContext(self.window.0);
         ImmSetCompositionWindow(himc, &composition_form);
+        ImmSetCandidateWindow(himc, &candidate_form);
         ImmReleaseContext(self.window.0, himc);
       }
     }
@@ -676,14 +1014,64 @@ impl Window {
   }
 
   #[inline]
-  pub fn request_user_attention(&self, request_type: Option<UserAttentionType>) {
+  pub fn set_content_protected(&self, protected: bool) {
     let window = self.window.clone();
-    let active_window_handle = unsafe { GetActiveWindow() };
-    if window.0 == active_window_handle {
-      return;
-    }
+    let window_state = Arc::clone(&self.window_state);
+    self.thread_executor.execute_in_thread(move || unsafe {
+      window_state.lock().content_protected = protected;
+      set_display_affinity(window.0, protected);
+    });
+  }
+
+  #[inline]
+  pub fn set_ime_allowed(&self, allowed: bool) {
+    let window = self.window.clone();
+    let window_state = Arc::clone(&self.window_state);
 
     self.thread_executor.execute_in_thread(move || unsafe {
+      {
+        let mut window_state = window_state.lock();
+        if window_state.ime_allowed == allowed {
+          return;
+        }
+        window_state.ime_allowed = allowed;
+      }
+
+      if allowed {
+        let ime_context = mem::take(&mut window_state.lock().disallowed_ime_context);
+        ImmAssociateContext(window.0, ime_context);
+      } else {
+        // The messages of the cancelled composition are ignored, the preedit is cleared along
+        // with `Ime::Disabled` below.
+        let himc = ImmGetContext(window.0);
+        if !himc.is_invalid() {
+          ImmNotifyIME(himc, NI_COMPOSITIONSTR, CPS_CANCEL, 0);
+          ImmReleaseContext(window.0, himc);
+        }
+        let ime_context = ImmAssociateContext(window.0, HIMC::default());
+        window_state.lock().disallowed_ime_context = ime_context;
+      }
+
+      SendMessageW(
+        window.0,
+        *IME_ALLOWED_CHANGED_MSG_ID,
+        WPARAM(allowed as _),
+        LPARAM(0),
+      );
+    });
+  }
+
+  #[inline]
+  pub fn request_user_attention(&self, request_type: Option<UserAttentionType>) {
+    let window = self.window.clone();
+    let window_state = Arc::clone(&self.window_state);
+    self.thread_executor.execute_in_thread(move || {
+      let mut window_state = window_state.lock();
+      // The active window is only known on the thread of the event loop.
+      if request_type.is_some() && window_state.has_focus == Some(true) {
+        return;
+      }
+
       let (flags, count) = request_type
         .map(|ty| match ty {
           UserAttentionType::Critical => (FLASHW_ALL | FLASHW_TIMERNOFG, u32::MAX),
@@ -691,14 +1079,56 @@ impl Window {
         })
         .unwrap_or((FLASHW_STOP, 0));
 
-      let flash_info = FLASHWINFO {
-        cbSize: mem::size_of::<FLASHWINFO>() as u32,
-        hwnd: window.0,
-        dwFlags: flags,
-        uCount: count,
-        dwTimeout: 0,
-      };
-      FlashWindowEx(&flash_info);
+      // `WM_SETFOCUS` stops the flashing, even when the window is focused without being
+      // brought to the foreground.
+      window_state.flashing = request_type.is_some();
+      util::flash_window(window.0, flags, count);
+    });
+  }
+
+  #[inline]
+  pub fn flash_until_focused(&self) {
+    let window = self.window.clone();
+    let window_state = Arc::clone(&self.window_state);
+    self.thread_executor.execute_in_thread(move || {
+      let mut window_state = window_state.lock();
+      if window_state.has_focus == Some(true) {
+        return;
+      }
+      window_state.flashing = true;
+      util::flash_window(window.0, FLASHW_ALL | FLASHW_TIMERNOFG, u32::MAX);
+    });
+  }
+
+  #[inline]
+  pub fn set_badge_count(&self, count: Option<i64>) {
+    let window = self.window.clone();
+    self.thread_executor.execute_in_thread(move || unsafe {
+      with_taskbar_list(|taskbar_list| match count.filter(|&count| count > 0) {
+        Some(count) => {
+          let text = if count > 99 {
+            "99+".to_string()
+          } else {
+            count.to_string()
+          };
+          let size = GetSystemMetrics(SM_CXSMICON);
+          if let Ok(badge) = icon::WinIcon::from_badge_text(&text, size) {
+            // The taskbar keeps its own copy of the overlay icon.
+            let _ = taskbar_list.SetOverlayIcon(window.0, badge.as_raw_handle(), text.as_str());
+          }
+        }
+        None => {
+          let _ = taskbar_list.SetOverlayIcon(window.0, HICON::default(), PWSTR::default());
+        }
+      });
+    });
+  }
+
+  #[inline]
+  pub fn set_progress_bar(&self, state: ProgressBarState) {
+    let window = self.window.clone();
+    self.thread_executor.execute_in_thread(move || unsafe {
+      taskbar_set_progress(window.0, state);
     });
   }
 
@@ -707,6 +1137,48 @@ impl Window {
     self.window_state.lock().current_theme
   }
 
+  pub fn pressed_mouse_buttons(&self) -> MouseButtons {
+    let is_pressed = |vk| unsafe { GetAsyncKeyState(i32::from(vk)) } < 0;
+    // `GetAsyncKeyState` reports the physical buttons, which are swapped for left-handed users.
+    let (left, right) = if unsafe { GetSystemMetrics(SM_SWAPBUTTON) } != 0 {
+      (VK_RBUTTON, VK_LBUTTON)
+    } else {
+      (VK_LBUTTON, VK_RBUTTON)
+    };
+
+    let mut buttons = MouseButtons::empty();
+    buttons.set(MouseButtons::LEFT, is_pressed(left));
+    buttons.set(MouseButtons::RIGHT, is_pressed(right));
+    buttons.set(MouseButtons::MIDDLE, is_pressed(VK_MBUTTON));
+    buttons.set(MouseButtons::BACK, is_pressed(VK_XBUTTON1));
+    buttons.set(MouseButtons::FORWARD, is_pressed(VK_XBUTTON2));
+    buttons
+  }
+
+  pub fn pressed_keys(&self) -> Vec<KeyCode> {
+    let mut keys = Vec::new();
+    for vk in 0..=u8::MAX as VIRTUAL_KEY {
+      match vk {
+        // Mouse buttons, and the modifiers which also have a left and a right variant.
+        VK_LBUTTON | VK_RBUTTON | VK_CANCEL | VK_MBUTTON | VK_XBUTTON1 | VK_XBUTTON2 | VK_SHIFT
+        | VK_CONTROL | VK_MENU => continue,
+        _ => (),
+      }
+      if unsafe { GetAsyncKeyState(i32::from(vk)) } >= 0 {
+        continue;
+      }
+      let scancode = unsafe { MapVirtualKeyW(u32::from(vk), MAPVK_VK_TO_VSC_EX) };
+      if scancode == 0 {
+        continue;
+      }
+      let key = KeyCode::from_scancode(scancode);
+      if !keys.contains(&key) {
+        keys.push(key);
+      }
+    }
+    keys
+  }
+
   #[inline]
   pub fn hide_menu(&self) {
     unsafe {
@@ -761,18 +1233,21 @@ impl Window {
 
   #[inline]
   pub(crate) fn set_skip_taskbar(&self, skip: bool) {
-    unsafe {
-      com_initialized();
-      let taskbar_list: ITaskbarList =
-        CoCreateInstance(&TaskbarList, None, CLSCTX_SERVER).expect("failed to create TaskBarList");
-      if skip {
-        taskbar_list
-          .DeleteTab(self.hwnd())
-          .expect("DeleteTab failed");
-      } else {
-        taskbar_list.AddTab(self.hwnd()).expect("AddTab failed");
-      }
-    }
+    self.window_state.lock().skip_taskbar = skip;
+    unsafe { taskbar_set_skip(self.hwnd(), skip) };
+  }
+
+  #[inline]
+  pub(crate) fn set_force_def_window_proc(&self, msg: u32, force: bool) {
+    let window = self.window.clone();
+    self.thread_executor.execute_in_thread(move || unsafe {
+      SendMessageW(
+        window.0,
+        *SET_FORCE_DEF_WINDOW_PROC_MSG_ID,
+        WPARAM(msg as _),
+        LPARAM(force as _),
+      );
+    });
   }
 }
 
@@ -805,7 +1280,11 @@ unsafe fn init<T: 'static>(
   event_loop: &EventLoopWindowTarget<T>,
 ) -> Result<Window, RootOsError> {
   // registering the window class
-  let mut class_name = register_window_class(&attributes.window_icon, &pl_attribs.taskbar_icon);
+  let mut class_name = register_window_class(
+    &pl_attribs.class_name,
+    &attributes.window_icon,
+    &pl_attribs.taskbar_icon,
+  );
 
   let mut window_flags = WindowFlags::empty();
   window_flags.set(WindowFlags::DECORATIONS, attributes.decorations);
@@ -814,6 +1293,8 @@ unsafe fn init<T: 'static>(
     WindowFlags::NO_BACK_BUFFER,
     pl_attribs.no_redirection_bitmap,
   );
+  window_flags.set(WindowFlags::TOOL_WINDOW, pl_attribs.tool_window);
+  window_flags.set(WindowFlags::NO_ACTIVATE, !attributes.focused);
   window_flags.set(WindowFlags::TRANSPARENT, attributes.transparent);
   // WindowFlags::VISIBLE and MAXIMIZED are set down below after the window has been configured.
   window_flags.set(WindowFlags::RESIZABLE, attributes.resizable);
@@ -831,11 +1312,18 @@ unsafe fn init<T: 'static>(
       Some(parent)
     }
     Parent::None => {
-      window_flags.set(WindowFlags::ON_TASKBAR, true);
+      // `WS_EX_APPWINDOW` would show a tool window on the taskbar.
+      window_flags.set(WindowFlags::ON_TASKBAR, !pl_attribs.tool_window);
       None
     }
   };
 
+  // A window takes the DPI awareness of the thread that creates it, so switch the thread's
+  // awareness for the duration of `CreateWindowExW` and restore it right after.
+  let previous_dpi_awareness_context = pl_attribs
+    .dpi_awareness_context
+    .and_then(set_thread_dpi_awareness_context);
+
   // creating the real window this time, by using the functions in `extra_functions`
   let real_window = {
     let (style, ex_style) = window_flags.to_window_styles();
@@ -854,6 +1342,10 @@ unsafe fn init<T: 'static>(
       Box::into_raw(Box::new(window_flags)) as _,
     );
 
+    if let Some(context) = previous_dpi_awareness_context {
+      set_thread_dpi_awareness_context(context);
+    }
+
     if handle.is_invalid() {
       return Err(os_error!(OsError::IoError(io::Error::last_os_error())));
     }
@@ -861,32 +1353,23 @@ unsafe fn init<T: 'static>(
     WindowWrapper(handle)
   };
 
-  // Register for touch events if applicable
-  {
+  // Register for touch events if applicable. When pointer messages are available they already
+  // carry touch input, and registering would make the window receive it twice.
+  let legacy_touch = pl_attribs.legacy_touch || !event_loop::is_pointer_input_available();
+  if legacy_touch {
     let digitizer = GetSystemMetrics(SM_DIGITIZER) as u32;
     if digitizer & NID_READY != 0 {
       RegisterTouchWindow(real_window.0, TWF_WANTPALM);
     }
   }
 
-  let dpi = hwnd_dpi(real_window.0);
-  let scale_factor = dpi_to_scale_factor(dpi);
-
-  // making the window transparent
-  if attributes.transparent && !pl_attribs.no_redirection_bitmap {
-    // Empty region for the blur effect, so the window is fully transparent
-    let region = CreateRectRgn(0, 0, -1, -1);
-
-    let bb = DWM_BLURBEHIND {
-      dwFlags: DWM_BB_ENABLE | DWM_BB_BLURREGION,
-      fEnable: true.into(),
-      hRgnBlur: region,
-      fTransitionOnMaximized: false.into(),
-    };
-
-    let _ = DwmEnableBlurBehindWindow(real_window.0, &bb);
-    DeleteObject(region);
-  }
+  // `CW_USEDEFAULT` may have put the window on another monitor than the one it will be moved
+  // to below, so take the scale factor from the target monitor. Otherwise `scale_factor` would
+  // be wrong until the first `WM_DPICHANGED`.
+  let scale_factor = match attributes.position.and_then(monitor::monitor_from_position) {
+    Some(monitor) if is_per_monitor_dpi_aware(real_window.0) => monitor.scale_factor(),
+    _ => dpi_to_scale_factor(hwnd_dpi(real_window.0)),
+  };
 
   // If the system theme is dark, we need to set the window theme now
   // before we update the window flags (and possibly show the
@@ -894,13 +1377,16 @@ unsafe fn init<T: 'static>(
   let current_theme = try_theme(real_window.0, pl_attribs.preferred_theme);
 
   let window_state = {
-    let window_state = WindowState::new(
+    let mut window_state = WindowState::new(
       &attributes,
       pl_attribs.taskbar_icon,
       scale_factor,
       current_theme,
       pl_attribs.preferred_theme,
     );
+    window_state.legacy_touch = legacy_touch;
+    window_state.ime_context = ImmCreateContext();
+    ImmAssociateContext(real_window.0, window_state.ime_context);
     let window_state = Arc::new(Mutex::new(window_state));
     WindowState::set_window_flags(window_state.lock(), real_window.0, |f| *f = window_flags);
     window_state
@@ -913,7 +1399,28 @@ unsafe fn init<T: 'static>(
     menu: None,
   };
 
-  win.set_skip_taskbar(pl_attribs.skip_taskbar);
+  if !pl_attribs.tool_window {
+    win.set_skip_taskbar(pl_attribs.skip_taskbar);
+  }
+
+  if pl_attribs.executable_icon {
+    let (has_window_icon, has_taskbar_icon) = {
+      let window_state = win.window_state.lock();
+      (
+        window_state.window_icon.is_some(),
+        window_state.taskbar_icon.is_some(),
+      )
+    };
+    if !has_window_icon || !has_taskbar_icon {
+      let (large, small) = icon::WinIcon::from_executable();
+      if let Some(inner) = small.filter(|_| !has_window_icon) {
+        win.set_window_icon(Some(Icon { inner }));
+      }
+      if let Some(inner) = large.filter(|_| !has_taskbar_icon) {
+        win.set_taskbar_icon(Some(Icon { inner }));
+      }
+    }
+  }
 
   let dimensions = attributes
     .inner_size
@@ -924,15 +1431,22 @@ unsafe fn init<T: 'static>(
     // `Window::set_inner_size` changes MAXIMIZED to false.
     win.set_maximized(true);
   }
-  win.set_visible(attributes.visible);
 
-  if attributes.fullscreen.is_some() {
+  if let Some(position) = attributes.position {
+    win.set_outer_position(position);
+  }
+
+  // The window is still hidden, so making it fullscreen before showing it means it is
+  // first shown covering the monitor rather than with its windowed frame.
+  let fullscreen = attributes.fullscreen.is_some();
+  if fullscreen {
     win.set_fullscreen(attributes.fullscreen);
-    force_window_active(win.window.0);
   }
 
-  if let Some(position) = attributes.position {
-    win.set_outer_position(position);
+  win.set_visible(attributes.visible);
+
+  if fullscreen && attributes.focused {
+    force_window_active(win.window.0);
   }
 
   if let Some(window_menu) = attributes.window_menu {
@@ -955,14 +1469,32 @@ unsafe fn init<T: 'static>(
     )));
   }
 
+  // Posted rather than sent, so `Focused` follows the `Resized` and `Moved` events above.
+  PostMessageW(
+    win.window.0,
+    *REPORT_INITIAL_FOCUS_MSG_ID,
+    WPARAM(0),
+    LPARAM(0),
+  );
+
   Ok(win)
 }
 
+lazy_static! {
+  static ref REGISTERED_WINDOW_CLASSES: Mutex<HashSet<String>> = Default::default();
+}
+
 unsafe fn register_window_class(
+  name: &str,
   window_icon: &Option<Icon>,
   taskbar_icon: &Option<Icon>,
 ) -> Vec<u16> {
-  let mut class_name = util::to_wstring("Window Class");
+  let mut class_name = util::to_wstring(name);
+
+  // Each class is only registered once, by the first window using it.
+  if !REGISTERED_WINDOW_CLASSES.lock().insert(name.to_owned()) {
+    return class_name;
+  }
 
   let h_icon = taskbar_icon
     .as_ref()
@@ -975,7 +1507,8 @@ unsafe fn register_window_class(
 
   let class = WNDCLASSEXW {
     cbSize: mem::size_of::<WNDCLASSEXW>() as u32,
-    style: CS_HREDRAW | CS_VREDRAW | CS_OWNDC,
+    // `CS_DBLCLKS` makes the system send `WM_*BUTTONDBLCLK`, e.g. for custom title bars.
+    style: CS_HREDRAW | CS_VREDRAW | CS_OWNDC | CS_DBLCLKS,
     lpfnWndProc: Some(window_proc),
     cbClsExtra: 0,
     cbWndExtra: 0,
@@ -988,8 +1521,8 @@ unsafe fn register_window_class(
     hIconSm: h_icon_small,
   };
 
-  // We ignore errors because registering the same window class twice would trigger
-  //  an error, and because errors here are detected during CreateWindowEx anyway.
+  // We ignore errors because registering a window class another module already registered
+  //  would trigger an error, and because errors here are detected during CreateWindowEx anyway.
   // Also since there is no weird element in the struct, there is no reason for this
   //  call to fail.
   RegisterClassExW(&class);
@@ -1058,29 +1591,23 @@ thread_local! {
         }
     };
 
-    static TASKBAR_LIST: RefCell<Option<ITaskbarList2>> = RefCell::new(None);
+    static TASKBAR_LIST: RefCell<Option<ITaskbarList3>> = RefCell::new(None);
 }
 
 pub fn com_initialized() {
   COM_INITIALIZED.with(|_| {});
 }
 
-// Reference Implementation:
-// https://github.com/chromium/chromium/blob/f18e79d901f56154f80eea1e2218544285e62623/ui/views/win/fullscreen_handler.cc
-//
-// As per MSDN marking the window as fullscreen should ensure that the
-// taskbar is moved to the bottom of the Z-order when the fullscreen window
-// is activated. If the window is not fullscreen, the Shell falls back to
-// heuristics to determine how the window should be treated, which means
-// that it could still consider the window as fullscreen. :(
-unsafe fn taskbar_mark_fullscreen(handle: HWND, fullscreen: bool) {
+/// Runs `f` with this thread's taskbar list, creating it on first use. Does nothing if the
+/// taskbar list can't be created.
+unsafe fn with_taskbar_list(f: impl FnOnce(&ITaskbarList3)) {
   com_initialized();
 
   TASKBAR_LIST.with(|task_bar_list_ptr| {
     let mut task_bar_list = task_bar_list_ptr.borrow().clone();
 
     if task_bar_list.is_none() {
-      let result: windows::core::Result<ITaskbarList2> =
+      let result: windows::core::Result<ITaskbarList3> =
         CoCreateInstance(&TaskbarList, None, CLSCTX_ALL);
       if let Ok(created) = result {
         if let Ok(()) = created.HrInit() {
@@ -1095,9 +1622,62 @@ unsafe fn taskbar_mark_fullscreen(handle: HWND, fullscreen: bool) {
       *task_bar_list_ptr.borrow_mut() = task_bar_list.clone();
     }
 
-    let _ = task_bar_list
-      .unwrap()
-      .MarkFullscreenWindow(handle, fullscreen);
+    f(&task_bar_list.unwrap());
+  })
+}
+
+// Reference Implementation:
+// https://github.com/chromium/chromium/blob/f18e79d901f56154f80eea1e2218544285e62623/ui/views/win/fullscreen_handler.cc
+//
+// As per MSDN marking the window as fullscreen should ensure that the
+// taskbar is moved to the bottom of the Z-order when the fullscreen window
+// is activated. If the window is not fullscreen, the Shell falls back to
+// heuristics to determine how the window should be treated, which means
+// that it could still consider the window as fullscreen. :(
+unsafe fn taskbar_mark_fullscreen(handle: HWND, fullscreen: bool) {
+  with_taskbar_list(|task_bar_list| {
+    let _ = task_bar_list.MarkFullscreenWindow(handle, fullscreen);
+  })
+}
+
+unsafe fn taskbar_set_progress(handle: HWND, state: ProgressBarState) {
+  with_taskbar_list(|task_bar_list| {
+    let flags = match state.state {
+      ProgressState::None => TBPF_NOPROGRESS,
+      ProgressState::Normal => TBPF_NORMAL,
+      ProgressState::Indeterminate => TBPF_INDETERMINATE,
+      ProgressState::Paused => TBPF_PAUSED,
+      ProgressState::Error => TBPF_ERROR,
+    };
+    let _ = task_bar_list.SetProgressState(handle, flags);
+    // Setting a value turns an indeterminate or hidden progress bar into a normal one.
+    let shows_value = !matches!(
+      state.state,
+      ProgressState::None | ProgressState::Indeterminate
+    );
+    if let Some(progress) = state.progress.filter(|_| shows_value) {
+      let _ = task_bar_list.SetProgressValue(handle, progress.min(100), 100);
+    }
+  })
+}
+
+unsafe fn set_display_affinity(handle: HWND, protected: bool) {
+  if !protected {
+    SetWindowDisplayAffinity(handle, WDA_NONE);
+  } else if !SetWindowDisplayAffinity(handle, WDA_EXCLUDEFROMCAPTURE).as_bool() {
+    // `WDA_EXCLUDEFROMCAPTURE` requires Windows 10 version 2004, older versions can only show
+    // the window as black in captures.
+    SetWindowDisplayAffinity(handle, WDA_MONITOR);
+  }
+}
+
+unsafe fn taskbar_set_skip(handle: HWND, skip: bool) {
+  with_taskbar_list(|task_bar_list| {
+    let _ = if skip {
+      task_bar_list.DeleteTab(handle)
+    } else {
+      task_bar_list.AddTab(handle)
+    };
   })
 }
 
@@ -1130,6 +1710,11 @@ unsafe fn force_window_active(handle: HWND) {
 }
 
 pub fn hit_test(hwnd: HWND, cx: i32, cy: i32) -> LRESULT {
+  hit_test_with_inset(hwnd, cx, cy, BORDERLESS_RESIZE_INSET)
+}
+
+/// Like [`hit_test`], with the resize borders `inset` physical pixels wide.
+pub(crate) fn hit_test_with_inset(hwnd: HWND, cx: i32, cy: i32, inset: i32) -> LRESULT {
   let mut window_rect = RECT::default();
   unsafe {
     if GetWindowRect(hwnd, <*mut _>::cast(&mut window_rect)).as_bool() {
@@ -1151,10 +1736,10 @@ pub fn hit_test(hwnd: HWND, cx: i32, cy: i32) -> LRESULT {
       } = window_rect;
 
       #[rustfmt::skip]
-      let result = (LEFT * (if cx < (left + BORDERLESS_RESIZE_INSET) { 1 } else { 0 }))
-        | (RIGHT * (if cx >= (right - BORDERLESS_RESIZE_INSET) { 1 } else { 0 }))
-        | (TOP * (if cy < (top + BORDERLESS_RESIZE_INSET) { 1 } else { 0 }))
-        | (BOTTOM * (if cy >= (bottom - BORDERLESS_RESIZE_INSET) { 1 } else { 0 }));
+      let result = (LEFT * (if cx < (left + inset) { 1 } else { 0 }))
+        | (RIGHT * (if cx >= (right - inset) { 1 } else { 0 }))
+        | (TOP * (if cy < (top + inset) { 1 } else { 0 }))
+        | (BOTTOM * (if cy >= (bottom - inset) { 1 } else { 0 }));
 
       LRESULT(match result {
         CLIENT => HTCLIENT,
@@ -2,18 +2,23 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-  dpi::{PhysicalPosition, Size},
+  dpi::{PhysicalPosition, PhysicalSize, Size},
+  event::{MouseButton, TouchPhase},
   icon::Icon,
   keyboard::ModifiersState,
-  platform_impl::platform::{event_loop, keyboard::KeyEventBuilder, minimal_ime::MinimalIme, util},
+  platform_impl::platform::{
+    event_loop, icon::WinCursor, keyboard::KeyEventBuilder, minimal_ime::MinimalIme, monitor, util,
+    MaximizeMode,
+  },
   window::{CursorIcon, Fullscreen, Theme, WindowAttributes},
 };
 use parking_lot::MutexGuard;
-use std::io;
+use std::{collections::HashMap, io, mem};
 use windows::Win32::{
-  Foundation::{HWND, LPARAM, RECT, WPARAM},
-  Graphics::Gdi::{InvalidateRgn, HRGN},
-  UI::WindowsAndMessaging::*,
+  Foundation::{HWND, LPARAM, POINT, RECT, WPARAM},
+  Globalization::HIMC,
+  Graphics::Gdi::{InflateRect, InvalidateRgn, HRGN},
+  UI::{Input::KeyboardAndMouse::GetDoubleClickTime, WindowsAndMessaging::*},
 };
 
 /// Contains information about states and the window that the callback is going to use.
@@ -24,24 +29,122 @@ pub struct WindowState {
   pub min_size: Option<Size>,
   pub max_size: Option<Size>,
 
+  /// The title passed to `Window::set_title`, which sets it asynchronously.
+  pub title: String,
+
   pub window_icon: Option<Icon>,
   pub taskbar_icon: Option<Icon>,
 
   pub saved_window: Option<SavedWindow>,
   pub scale_factor: f64,
+  /// Used by `WM_GETMINMAXINFO` and `WM_NCCALCSIZE` for borderless windows.
+  pub maximize_mode: MaximizeMode,
+  /// Used by `WM_MOUSEACTIVATE`.
+  pub activate_on_click: bool,
+  /// Used by `WM_SETFOCUS` and `WM_KILLFOCUS` to only report actual focus changes. `None`
+  /// until the initial focus state has been reported, see `REPORT_INITIAL_FOCUS_MSG_ID`.
+  pub has_focus: Option<bool>,
+  /// Set by `Window::request_redraw` until the `RedrawRequested` event is delivered, so that
+  /// several requests in a row result in a single event. Hidden and minimized windows don't
+  /// receive `WM_PAINT`, so it's also cleared when the window is hidden or minimized.
+  pub redraw_pending: bool,
+  /// Set by `WindowExtWindows::flash_until_focused`, the flashing is stopped by `WM_SETFOCUS`.
+  pub flashing: bool,
+  /// The position of the last `Moved` event, used by `WM_WINDOWPOSCHANGED` to skip the ones that
+  /// don't actually move the window.
+  pub last_moved_position: Option<PhysicalPosition<i32>>,
+  /// Whether the last `Minimized` event reported the window as minimized, used by `WM_SIZE` to
+  /// only report actual changes.
+  pub minimized: bool,
+  /// Set by `WindowExtWindows::set_skip_taskbar`. The taskbar button is added back by the shell
+  /// when the styles change, so it is removed again after switching fullscreen.
+  pub skip_taskbar: bool,
+  /// Set by `Window::set_content_protected`, and applied again after switching fullscreen so
+  /// that the contents don't leak in captures while the styles change.
+  pub content_protected: bool,
+  /// The size of the last `SurfaceResized` event, used to only report actual changes.
+  pub surface_size: Option<PhysicalSize<u32>>,
+  /// Set by `Window::set_resize_border_width`, in logical pixels. Without it, the resize borders
+  /// of undecorated windows are `BORDERLESS_RESIZE_INSET` physical pixels wide.
+  pub resize_border_width: Option<u32>,
 
   pub modifiers_state: ModifiersState,
   pub fullscreen: Option<Fullscreen>,
   pub current_theme: Theme,
   pub preferred_theme: Option<Theme>,
-  pub high_surrogate: Option<u16>,
+  pub touch_ids: TouchIds,
+  /// Whether touch is received through `WM_TOUCH` rather than `WM_POINTER*`.
+  pub legacy_touch: bool,
 
   pub key_event_builder: KeyEventBuilder,
   pub ime_handler: MinimalIme,
+  /// The input context created for the window. By default the windows of a thread share one,
+  /// which lets a composition started in a window continue in another.
+  pub ime_context: HIMC,
+  /// Set by `Window::set_ime_allowed`, no `Ime` events are sent while it is `false`.
+  pub ime_allowed: bool,
+  /// The input context associated to the window when the input method was disallowed, which is
+  /// associated again once it's allowed.
+  pub disallowed_ime_context: HIMC,
 
   pub window_flags: WindowFlags,
 }
 
+/// The message a raw contact id comes from. `WM_TOUCH` and `WM_POINTER*` number their contacts
+/// independently, the same raw id may refer to different contacts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TouchSource {
+  /// `WM_TOUCH`.
+  Touch,
+  /// `WM_POINTER*`.
+  Pointer,
+}
+
+/// Maps the contact ids reported by `WM_TOUCH` and `WM_POINTER*` to the ids exposed in
+/// `Touch::id`, so a contact keeps the same id from `Started` to `Ended`.
+#[derive(Default)]
+pub struct TouchIds {
+  /// The id and last location of the contacts which are down.
+  active: HashMap<(TouchSource, u32), (u64, PhysicalPosition<f64>)>,
+  next_id: u64,
+}
+
+impl TouchIds {
+  pub fn contact_id(
+    &mut self,
+    source: TouchSource,
+    raw_id: u32,
+    phase: TouchPhase,
+    location: PhysicalPosition<f64>,
+  ) -> u64 {
+    let key = (source, raw_id);
+    let id = match self.active.get(&key) {
+      Some((id, _)) => *id,
+      None => {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        id
+      }
+    };
+    if matches!(phase, TouchPhase::Ended | TouchPhase::Cancelled) {
+      self.active.remove(&key);
+    } else {
+      self.active.insert(key, (id, location));
+    }
+    id
+  }
+
+  /// Forgets the contact `raw_id` if it is down, returning its id and last location to report
+  /// it as cancelled.
+  pub fn cancel(
+    &mut self,
+    source: TouchSource,
+    raw_id: u32,
+  ) -> Option<(u64, PhysicalPosition<f64>)> {
+    self.active.remove(&(source, raw_id))
+  }
+}
+
 #[derive(Clone)]
 pub struct SavedWindow {
   pub placement: WINDOWPLACEMENT,
@@ -50,9 +153,44 @@ pub struct SavedWindow {
 #[derive(Clone)]
 pub struct MouseProperties {
   pub cursor: CursorIcon,
-  pub capture_count: u32,
+  /// Takes precedence over `cursor` when set.
+  pub custom_cursor: Option<WinCursor>,
+  pub busy: bool,
+  capture_count: u32,
   cursor_flags: CursorFlags,
   pub last_position: Option<PhysicalPosition<f64>>,
+  last_click: Option<Click>,
+}
+
+/// The last button press, used to count consecutive clicks.
+#[derive(Clone, Copy)]
+struct Click {
+  button: MouseButton,
+  /// Message time of the press, in milliseconds.
+  time: u32,
+  /// Position of the press, in client coordinates.
+  position: POINT,
+  count: u32,
+}
+
+/// The cursor `WM_SETCURSOR` shows over the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitTestCursor {
+  /// The cursor of the client area, see `MouseProperties::current_cursor`.
+  Current,
+  /// A resize cursor, over the resize edges of a borderless window.
+  Resize(CursorIcon),
+}
+
+/// What to do with the cursor clip, which applies to the whole system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorClip {
+  /// Leave the clip as it is.
+  Keep,
+  /// Confine the cursor to the rect, in screen coordinates.
+  Confine(RECT),
+  /// Release the clip set by this thread.
+  Release,
 }
 
 bitflags! {
@@ -74,6 +212,11 @@ bitflags! {
         const CHILD          = 1 << 7;
         const MAXIMIZED      = 1 << 8;
         const POPUP          = 1 << 14;
+        const TOOL_WINDOW    = 1 << 16;
+        /// Set while a window created with `WindowBuilder::with_focused(false)` is first shown.
+        const NO_ACTIVATE    = 1 << 17;
+        /// Lets the mouse input go through the window, to the window below it.
+        const IGNORE_CURSOR_EVENT = 1 << 18;
 
         /// Marker flag for fullscreen. Should always match `WindowState::fullscreen`, but is
         /// included here to make masking easier.
@@ -91,6 +234,11 @@ bitflags! {
 
         const MINIMIZED = 1 << 12;
 
+        /// Set while the default window procedure maximizes or restores the window. The
+        /// transition can be animated, so `WM_SIZE` doesn't emit `Resized` events meanwhile and
+        /// only the final size is reported.
+        const MARKER_IN_SIZE_TRANSITION = 1 << 15;
+
         const EXCLUSIVE_FULLSCREEN_OR_MASK = WindowFlags::ALWAYS_ON_TOP.bits;
         const INVISIBLE_AND_MASK = !WindowFlags::MAXIMIZED.bits;
     }
@@ -107,31 +255,98 @@ impl WindowState {
     WindowState {
       mouse: MouseProperties {
         cursor: CursorIcon::default(),
+        custom_cursor: None,
+        busy: false,
         capture_count: 0,
         cursor_flags: CursorFlags::empty(),
         last_position: None,
+        last_click: None,
       },
 
       min_size: attributes.min_inner_size,
       max_size: attributes.max_inner_size,
 
+      title: attributes.title.clone(),
+
       window_icon: attributes.window_icon.clone(),
       taskbar_icon,
 
       saved_window: None,
       scale_factor,
+      maximize_mode: MaximizeMode::default(),
+      activate_on_click: true,
+      has_focus: None,
+      redraw_pending: false,
+      flashing: false,
+      last_moved_position: None,
+      minimized: false,
+      skip_taskbar: false,
+      content_protected: false,
+      surface_size: None,
+      resize_border_width: None,
 
       modifiers_state: ModifiersState::default(),
       fullscreen: None,
       current_theme,
       preferred_theme,
-      high_surrogate: None,
+      touch_ids: TouchIds::default(),
+      legacy_touch: false,
       key_event_builder: KeyEventBuilder::default(),
       ime_handler: MinimalIme::default(),
+      ime_context: HIMC::default(),
+      ime_allowed: true,
+      disallowed_ime_context: HIMC::default(),
       window_flags: WindowFlags::empty(),
     }
   }
 
+  /// Records a redraw request, returning whether the window has to be invalidated, which isn't
+  /// the case while the `RedrawRequested` of a previous request is still pending.
+  pub fn request_redraw(&mut self) -> bool {
+    !mem::replace(&mut self.redraw_pending, true)
+  }
+
+  /// Clamps an inner size to the min/max inner sizes under the current scale factor. A physical
+  /// bound stays the same when the scale factor changes, while a logical one is scaled with it.
+  pub fn clamp_inner_size(&self, mut size: PhysicalSize<u32>) -> PhysicalSize<u32> {
+    if let Some(min_size) = self.min_size {
+      let min_size = min_size.to_physical::<u32>(self.scale_factor);
+      size.width = size.width.max(min_size.width);
+      size.height = size.height.max(min_size.height);
+    }
+    if let Some(max_size) = self.max_size {
+      let max_size = max_size.to_physical::<u32>(self.scale_factor);
+      size.width = size.width.min(max_size.width);
+      size.height = size.height.min(max_size.height);
+    }
+    size
+  }
+
+  /// Records a `WM_SETFOCUS` or `WM_KILLFOCUS`, returning whether it changed the focus state and
+  /// has to be reported. Nothing is reported before the initial focus state, which is left to
+  /// `REPORT_INITIAL_FOCUS_MSG_ID`.
+  pub fn set_focus(&mut self, focused: bool) -> bool {
+    match self.has_focus {
+      Some(has_focus) if has_focus != focused => {
+        self.has_focus = Some(focused);
+        true
+      }
+      _ => false,
+    }
+  }
+
+  /// Records the position of a `WM_WINDOWPOSCHANGED`, returning whether it moved the window and
+  /// has to be reported.
+  pub fn set_moved(&mut self, position: PhysicalPosition<i32>) -> bool {
+    self.last_moved_position.replace(position) != Some(position)
+  }
+
+  /// Records the modifiers, e.g. the ones read back from the physical key state on
+  /// `WM_SETFOCUS`, returning whether they changed and have to be reported.
+  pub fn set_modifiers(&mut self, modifiers: ModifiersState) -> bool {
+    mem::replace(&mut self.modifiers_state, modifiers) != modifiers
+  }
+
   pub fn window_flags(&self) -> WindowFlags {
     self.window_flags
   }
@@ -143,9 +358,10 @@ impl WindowState {
     let old_flags = this.window_flags;
     f(&mut this.window_flags);
     let new_flags = this.window_flags;
+    let maximize_mode = this.maximize_mode;
 
     drop(this);
-    old_flags.apply_diff(window, new_flags);
+    old_flags.apply_diff(window, new_flags, maximize_mode);
   }
 
   pub fn set_window_flags_in_place<F>(&mut self, f: F)
@@ -157,26 +373,160 @@ impl WindowState {
 }
 
 impl MouseProperties {
+  /// Returns the cursor to show over the client area: the busy cursor, the custom cursor or
+  /// `cursor`, in this order of precedence.
+  pub fn current_cursor(&self) -> HCURSOR {
+    if self.busy {
+      CursorIcon::Wait.load()
+    } else if let Some(custom_cursor) = &self.custom_cursor {
+      custom_cursor.as_raw_handle()
+    } else {
+      self.cursor.load()
+    }
+  }
+
   pub fn cursor_flags(&self) -> CursorFlags {
     self.cursor_flags
   }
 
+  /// Returns the cursor `WM_SETCURSOR` shows for the result of the preceding `WM_NCHITTEST`, or
+  /// `None` to leave it to the default procedure.
+  pub fn hit_test_cursor(&self, hit_test: u32, window_flags: WindowFlags) -> Option<HitTestCursor> {
+    let borderless_resizable = window_flags.contains(WindowFlags::RESIZABLE)
+      && !window_flags.contains(WindowFlags::DECORATIONS);
+    // The busy cursor is shown over the whole window, including the non-client area.
+    if self.busy || hit_test == HTCLIENT {
+      Some(HitTestCursor::Current)
+    } else if borderless_resizable {
+      // The resize edges of borderless windows come from our own `WM_NCHITTEST` handling,
+      // so show the matching resize cursors over them.
+      match hit_test {
+        HTLEFT | HTRIGHT => Some(HitTestCursor::Resize(CursorIcon::EwResize)),
+        HTTOP | HTBOTTOM => Some(HitTestCursor::Resize(CursorIcon::NsResize)),
+        HTTOPLEFT | HTBOTTOMRIGHT => Some(HitTestCursor::Resize(CursorIcon::NwseResize)),
+        HTTOPRIGHT | HTBOTTOMLEFT => Some(HitTestCursor::Resize(CursorIcon::NeswResize)),
+        _ => None,
+      }
+    } else {
+      None
+    }
+  }
+
+  /// Returns whether `WM_MOUSEMOVE` has to apply the cursor. `WM_SETCURSOR` isn't sent while the
+  /// mouse is captured, so a cursor changed meanwhile, e.g. by a child window or another library,
+  /// would stay until the capture is released.
+  pub fn reapply_cursor_on_move(&self) -> bool {
+    self.capture_count > 0
+  }
+
+  /// Records a button press that captures the mouse.
+  pub fn capture(&mut self) {
+    self.capture_count += 1;
+  }
+
+  /// Records a button release, returning whether it was the last captured button and the
+  /// capture has to be released.
+  pub fn release_capture(&mut self) -> bool {
+    self.capture_count = self.capture_count.saturating_sub(1);
+    self.capture_count == 0
+  }
+
+  /// Records a `WM_CAPTURECHANGED`. The buttons aren't captured anymore once another window
+  /// takes the capture.
+  pub fn capture_changed(&mut self, kept_by_window: bool) {
+    if !kept_by_window {
+      self.capture_count = 0;
+    }
+  }
+
   pub fn set_cursor_flags<F>(&mut self, window: HWND, f: F) -> Result<(), io::Error>
   where
     F: FnOnce(&mut CursorFlags),
   {
     let old_flags = self.cursor_flags;
     f(&mut self.cursor_flags);
-    match self.cursor_flags.refresh_os_cursor(window) {
-      Ok(()) => (),
-      Err(e) => {
-        self.cursor_flags = old_flags;
-        return Err(e);
+    let focused = util::is_focused(window);
+    if let Err(e) = self.refresh_os_cursor(window, |flags, rect| flags.cursor_clip(rect, focused)) {
+      self.cursor_flags = old_flags;
+      return Err(e);
+    }
+
+    if old_flags.reapplies_cursor(self.cursor_flags) {
+      unsafe {
+        SetCursor(self.current_cursor());
       }
     }
 
     Ok(())
   }
+
+  /// Updates the cursor clip on `WM_SETFOCUS` and `WM_KILLFOCUS`, see `CursorFlags::focus_clip`.
+  /// The focus is passed along since the active window may not be updated yet.
+  pub fn set_focus(&self, window: HWND, focused: bool) -> Result<(), io::Error> {
+    self.refresh_os_cursor(window, |flags, rect| flags.focus_clip(rect, focused))
+  }
+
+  fn refresh_os_cursor<F>(&self, window: HWND, clip: F) -> Result<(), io::Error>
+  where
+    F: FnOnce(CursorFlags, RECT) -> CursorClip,
+  {
+    let client_rect = util::get_client_rect(window)?;
+
+    match clip(self.cursor_flags, client_rect) {
+      CursorClip::Keep => (),
+      CursorClip::Release => util::release_cursor_clip(),
+      CursorClip::Confine(rect) => {
+        // We do this check because calling `set_cursor_clip` incessantly will flood the event
+        // loop with `WM_MOUSEMOVE` events, and `refresh_os_cursor` is called by
+        // `set_cursor_flags` which at times gets called once every iteration of the eventloop.
+        if util::get_cursor_clip()? != rect {
+          util::set_cursor_clip(Some(rect))?;
+        }
+      }
+    }
+
+    util::set_cursor_hidden(self.cursor_flags.cursor_hidden());
+
+    Ok(())
+  }
+
+  /// Records a press of `button` and returns the number of consecutive clicks it is part of,
+  /// according to the system double-click time and rectangle.
+  pub fn register_press(&mut self, button: MouseButton, position: POINT, time: u32) -> u32 {
+    let (max_time, max_dx, max_dy) = unsafe {
+      (
+        GetDoubleClickTime(),
+        GetSystemMetrics(SM_CXDOUBLECLK) / 2,
+        GetSystemMetrics(SM_CYDOUBLECLK) / 2,
+      )
+    };
+    let count = match self.last_click {
+      Some(click)
+        if click.button == button
+          && time.wrapping_sub(click.time) <= max_time
+          && (position.x - click.position.x).abs() <= max_dx
+          && (position.y - click.position.y).abs() <= max_dy =>
+      {
+        click.count + 1
+      }
+      _ => 1,
+    };
+    self.last_click = Some(Click {
+      button,
+      time,
+      position,
+      count,
+    });
+    count
+  }
+
+  /// Returns the click count of the last press of `button`, to be reported with its release.
+  pub fn release_click_count(&self, button: MouseButton) -> u32 {
+    match self.last_click {
+      Some(click) if click.button == button => click.count,
+      _ => 1,
+    }
+  }
 }
 
 impl WindowFlags {
@@ -216,6 +566,19 @@ impl WindowFlags {
     if self.contains(WindowFlags::NO_BACK_BUFFER) {
       style_ex |= WS_EX_NOREDIRECTIONBITMAP;
     }
+    if self.contains(WindowFlags::TOOL_WINDOW) {
+      style_ex |= WS_EX_TOOLWINDOW;
+    }
+    if self.contains(WindowFlags::NO_ACTIVATE) {
+      style_ex |= WS_EX_NOACTIVATE;
+    }
+    if self.contains(WindowFlags::IGNORE_CURSOR_EVENT) {
+      style_ex |= WS_EX_TRANSPARENT | WS_EX_LAYERED;
+    }
+    // The system composes a layered window with what is below it.
+    if self.contains(WindowFlags::TRANSPARENT) && !self.contains(WindowFlags::NO_BACK_BUFFER) {
+      style_ex |= WS_EX_LAYERED;
+    }
     if self.contains(WindowFlags::CHILD) {
       style |= WS_CHILD; // This is incompatible with WS_POPUP if that gets added eventually.
     }
@@ -238,7 +601,7 @@ impl WindowFlags {
   }
 
   /// Adjust the window client rectangle to the return value, if present.
-  fn apply_diff(mut self, window: HWND, mut new: WindowFlags) {
+  fn apply_diff(mut self, window: HWND, mut new: WindowFlags, maximize_mode: MaximizeMode) {
     self = self.mask();
     new = new.mask();
 
@@ -252,6 +615,8 @@ impl WindowFlags {
         ShowWindow(
           window,
           match new.contains(WindowFlags::VISIBLE) {
+            // Unlike `SW_SHOWNOACTIVATE`, this doesn't restore a window maximized while hidden.
+            true if new.contains(WindowFlags::NO_ACTIVATE) => SW_SHOWNA,
             true => SW_SHOW,
             false => SW_HIDE,
           },
@@ -276,6 +641,12 @@ impl WindowFlags {
       }
     }
 
+    // The transparency is a DWM setting rather than a window style. There is nothing to
+    // blur when the window has no redirection bitmap.
+    if diff.contains(WindowFlags::TRANSPARENT) && !new.contains(WindowFlags::NO_BACK_BUFFER) {
+      util::set_blur_behind(window, new.contains(WindowFlags::TRANSPARENT));
+    }
+
     // Minimize operations should execute after maximize for proper window animations
     if diff.contains(WindowFlags::MINIMIZED) {
       unsafe {
@@ -289,16 +660,21 @@ impl WindowFlags {
       }
     }
 
-    if diff.contains(WindowFlags::MAXIMIZED) || new.contains(WindowFlags::MAXIMIZED) {
-      unsafe {
-        ShowWindow(
+    match maximize_command(diff, new) {
+      Some(MaximizeCommand::Maximize) => unsafe {
+        ShowWindow(window, SW_MAXIMIZE);
+      },
+      Some(MaximizeCommand::MaximizeWithoutActivation) => unsafe {
+        maximize_without_activation(
           window,
-          match new.contains(WindowFlags::MAXIMIZED) {
-            true => SW_MAXIMIZE,
-            false => SW_RESTORE,
-          },
+          maximize_mode,
+          new.contains(WindowFlags::DECORATIONS),
         );
-      }
+      },
+      Some(MaximizeCommand::Restore) => unsafe {
+        ShowWindow(window, SW_RESTORE);
+      },
+      None => (),
     }
 
     if diff != WindowFlags::empty() {
@@ -316,6 +692,13 @@ impl WindowFlags {
         if !new.contains(WindowFlags::MINIMIZED) {
           SetWindowLongW(window, GWL_STYLE, style as i32);
           SetWindowLongW(window, GWL_EXSTYLE, style_ex as i32);
+
+          // A layered window isn't drawn until its attributes are set, the window stays opaque.
+          if style_ex & WS_EX_LAYERED != 0
+            && diff.intersects(WindowFlags::TRANSPARENT | WindowFlags::IGNORE_CURSOR_EVENT)
+          {
+            SetLayeredWindowAttributes(window, 0, 255, LWA_ALPHA);
+          }
         }
 
         let mut flags = SWP_NOZORDER | SWP_NOMOVE | SWP_NOSIZE | SWP_FRAMECHANGED;
@@ -342,40 +725,359 @@ impl WindowFlags {
   }
 }
 
-impl CursorFlags {
-  fn refresh_os_cursor(self, window: HWND) -> Result<(), io::Error> {
-    let client_rect = util::get_client_rect(window)?;
+/// How `WindowFlags::apply_diff` maximizes or restores the window.
+#[derive(Debug, PartialEq)]
+enum MaximizeCommand {
+  Maximize,
+  MaximizeWithoutActivation,
+  Restore,
+}
 
-    if util::is_focused(window) {
-      let cursor_clip = match self.contains(CursorFlags::GRABBED) {
-        true => Some(client_rect),
-        false => None,
-      };
-
-      let rect_to_tuple = |rect: RECT| (rect.left, rect.top, rect.right, rect.bottom);
-      let active_cursor_clip = rect_to_tuple(util::get_cursor_clip()?);
-      let desktop_rect = rect_to_tuple(util::get_desktop_rect());
-
-      let active_cursor_clip = match desktop_rect == active_cursor_clip {
-        true => None,
-        false => Some(active_cursor_clip),
-      };
-
-      // We do this check because calling `set_cursor_clip` incessantly will flood the event
-      // loop with `WM_MOUSEMOVE` events, and `refresh_os_cursor` is called by `set_cursor_flags`
-      // which at times gets called once every iteration of the eventloop.
-      if active_cursor_clip != cursor_clip.map(rect_to_tuple) {
-        util::set_cursor_clip(cursor_clip)?;
-      }
+fn maximize_command(diff: WindowFlags, new: WindowFlags) -> Option<MaximizeCommand> {
+  // Allowing the activation again after the first show doesn't need the window to be
+  // maximized again, which would activate it.
+  let maximize = diff.contains(WindowFlags::MAXIMIZED)
+    || (new.contains(WindowFlags::MAXIMIZED) && diff != WindowFlags::NO_ACTIVATE);
+  if !maximize {
+    None
+  } else if !new.contains(WindowFlags::MAXIMIZED) {
+    Some(MaximizeCommand::Restore)
+  } else if new.contains(WindowFlags::NO_ACTIVATE) {
+    Some(MaximizeCommand::MaximizeWithoutActivation)
+  } else {
+    Some(MaximizeCommand::Maximize)
+  }
+}
+
+/// Maximizes the window like `ShowWindow(SW_MAXIMIZE)` does, without activating it, as every
+/// show command that maximizes also activates the window.
+unsafe fn maximize_without_activation(window: HWND, mode: MaximizeMode, decorated: bool) {
+  // The restored position is kept by the system as long as the window is maximized.
+  let style = GetWindowLongW(window, GWL_STYLE) as u32;
+  SetWindowLongW(window, GWL_STYLE, (style | WS_MAXIMIZE) as i32);
+
+  let (mut rect, _) = match monitor::maximized_rect(window, mode) {
+    Some(rects) => rects,
+    None => return,
+  };
+  // The frame of a maximized window lies outside of the monitor area.
+  if decorated {
+    let frame = GetSystemMetrics(SM_CXSIZEFRAME) + GetSystemMetrics(SM_CXPADDEDBORDER);
+    InflateRect(&mut rect, frame, frame);
+  }
+  SetWindowPos(
+    window,
+    HWND::default(),
+    rect.left,
+    rect.top,
+    rect.right - rect.left,
+    rect.bottom - rect.top,
+    SWP_NOZORDER | SWP_NOACTIVATE | SWP_FRAMECHANGED,
+  );
+}
+
+impl CursorFlags {
+  /// Returns what to do with the cursor clip, given the client area of the window in screen
+  /// coordinates. The clip applies to the whole system, so it's left to the focused window.
+  pub fn cursor_clip(self, client_rect: RECT, focused: bool) -> CursorClip {
+    match (focused, self.contains(CursorFlags::GRABBED)) {
+      (true, true) => CursorClip::Confine(client_rect),
+      (true, false) => CursorClip::Release,
+      (false, _) => CursorClip::Keep,
     }
+  }
 
-    let cursor_in_client = self.contains(CursorFlags::IN_WINDOW);
-    if cursor_in_client {
-      util::set_cursor_hidden(self.contains(CursorFlags::HIDDEN));
-    } else {
-      util::set_cursor_hidden(false);
+  /// Returns what to do with the cursor clip when the window gains or loses the focus. A grabbed
+  /// cursor is released when the focus is lost, and confined again to the current client area
+  /// when it comes back.
+  pub fn focus_clip(self, client_rect: RECT, focused: bool) -> CursorClip {
+    match (focused, self.contains(CursorFlags::GRABBED)) {
+      (false, true) => CursorClip::Release,
+      _ => self.cursor_clip(client_rect, focused),
     }
+  }
 
-    Ok(())
+  /// Returns whether the cursor is hidden. It's only hidden over the client area, and a grabbed
+  /// cursor is confined but stays visible.
+  pub fn cursor_hidden(self) -> bool {
+    self.contains(CursorFlags::IN_WINDOW | CursorFlags::HIDDEN)
+  }
+
+  /// Returns whether changing the flags from `self` to `new` has to apply the cursor right away.
+  /// Releasing a grab or showing the cursor again would otherwise leave it hidden or stale until
+  /// the next `WM_SETCURSOR`.
+  pub fn reapplies_cursor(self, new: CursorFlags) -> bool {
+    let released = self.contains(CursorFlags::GRABBED) && !new.contains(CursorFlags::GRABBED);
+    let shown = self.contains(CursorFlags::HIDDEN) && !new.contains(CursorFlags::HIDDEN);
+    (released || shown) && new.contains(CursorFlags::IN_WINDOW)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{dpi::LogicalSize, platform_impl::platform::keyboard_layout::mods_from_keys};
+  use windows::Win32::UI::Input::KeyboardAndMouse::{VK_CONTROL, VK_MENU, VK_RMENU, VK_SHIFT};
+
+  #[test]
+  fn touch_ids_keep_the_sources_apart() {
+    let mut touch_ids = TouchIds::default();
+    let location = PhysicalPosition::new(0.0, 0.0);
+
+    let touch = touch_ids.contact_id(TouchSource::Touch, 1, TouchPhase::Started, location);
+    let pointer = touch_ids.contact_id(TouchSource::Pointer, 1, TouchPhase::Started, location);
+    assert_ne!(touch, pointer);
+
+    assert_eq!(
+      touch_ids.contact_id(TouchSource::Touch, 1, TouchPhase::Moved, location),
+      touch
+    );
+    assert_eq!(
+      touch_ids.cancel(TouchSource::Pointer, 1),
+      Some((pointer, location))
+    );
+    // Cancelling the pointer contact doesn't end the touch one.
+    assert_eq!(
+      touch_ids.contact_id(TouchSource::Touch, 1, TouchPhase::Ended, location),
+      touch
+    );
+    assert_eq!(touch_ids.cancel(TouchSource::Touch, 1), None);
+  }
+
+  #[test]
+  fn touch_ids_are_not_reused_by_new_contacts() {
+    let mut touch_ids = TouchIds::default();
+    let location = PhysicalPosition::new(0.0, 0.0);
+
+    let first = touch_ids.contact_id(TouchSource::Pointer, 7, TouchPhase::Started, location);
+    touch_ids.contact_id(TouchSource::Pointer, 7, TouchPhase::Ended, location);
+    let second = touch_ids.contact_id(TouchSource::Pointer, 7, TouchPhase::Started, location);
+    assert_ne!(first, second);
+  }
+
+  #[test]
+  fn redraw_requests_are_coalesced_until_painted() {
+    let mut window_state =
+      WindowState::new(&WindowAttributes::default(), None, 1.0, Theme::Light, None);
+
+    assert!(window_state.request_redraw());
+    assert!(!window_state.request_redraw());
+    assert!(!window_state.request_redraw());
+
+    // `WM_PAINT`, hiding or minimizing the window clear the pending request.
+    window_state.redraw_pending = false;
+    assert!(window_state.request_redraw());
+  }
+
+  #[test]
+  fn focus_is_only_reported_on_changes() {
+    let mut window_state =
+      WindowState::new(&WindowAttributes::default(), None, 1.0, Theme::Light, None);
+
+    // Nothing is reported before the initial focus state.
+    assert!(!window_state.set_focus(true));
+    assert_eq!(window_state.has_focus, None);
+
+    window_state.has_focus = Some(false);
+    // Two consecutive `WM_SETFOCUS` yield a single `Focused(true)`.
+    assert!(window_state.set_focus(true));
+    assert!(!window_state.set_focus(true));
+    // Same for `WM_KILLFOCUS`.
+    assert!(window_state.set_focus(false));
+    assert!(!window_state.set_focus(false));
+  }
+
+  #[test]
+  fn modifiers_held_while_unfocused_are_reported_on_focus() {
+    let mut window_state =
+      WindowState::new(&WindowAttributes::default(), None, 1.0, Theme::Light, None);
+
+    // `WM_KILLFOCUS` resets the modifiers, then Shift is pressed while another window has the
+    // focus, so the window never sees the key.
+    assert!(!window_state.set_modifiers(ModifiersState::empty()));
+    let modifiers = mods_from_keys(false, |vk| vk == VK_SHIFT);
+    assert_eq!(modifiers, ModifiersState::SHIFT);
+    // `WM_SETFOCUS` reports it from the physical key state, once.
+    assert!(window_state.set_modifiers(modifiers));
+    assert!(!window_state.set_modifiers(modifiers));
+    // The `Ctrl+Alt` of AltGr isn't reported on layouts that have it.
+    let altgr = |vk| vk == VK_CONTROL || vk == VK_MENU || vk == VK_RMENU;
+    assert_eq!(mods_from_keys(true, altgr), ModifiersState::empty());
+    assert_eq!(
+      mods_from_keys(false, altgr),
+      ModifiersState::CONTROL | ModifiersState::ALT
+    );
+  }
+
+  #[test]
+  fn unfocused_window_is_maximized_without_activation() {
+    // `WindowBuilder::with_focused(false).with_maximized(true)`.
+    let new = WindowFlags::VISIBLE | WindowFlags::MAXIMIZED | WindowFlags::NO_ACTIVATE;
+    assert_eq!(
+      maximize_command(new, new),
+      Some(MaximizeCommand::MaximizeWithoutActivation)
+    );
+    let (_, style_ex) = new.to_window_styles();
+    assert_ne!(style_ex & WS_EX_NOACTIVATE, 0);
+
+    // `Window::set_visible(true)` then allows the activation without maximizing it again.
+    let visible = new - WindowFlags::NO_ACTIVATE;
+    assert_eq!(maximize_command(WindowFlags::NO_ACTIVATE, visible), None);
+    assert_eq!(
+      maximize_command(WindowFlags::MAXIMIZED, visible),
+      Some(MaximizeCommand::Maximize)
+    );
+  }
+
+  #[test]
+  fn unchanged_position_is_not_reported_as_moved() {
+    let mut window_state =
+      WindowState::new(&WindowAttributes::default(), None, 1.0, Theme::Light, None);
+
+    assert!(window_state.set_moved(PhysicalPosition::new(10, 20)));
+    // A `WINDOWPOS` without `SWP_NOMOVE` and with the same coordinates emits no `Moved`.
+    assert!(!window_state.set_moved(PhysicalPosition::new(10, 20)));
+    assert!(window_state.set_moved(PhysicalPosition::new(10, 21)));
+    assert!(window_state.set_moved(PhysicalPosition::new(10, 20)));
+  }
+
+  #[test]
+  fn hit_test_cursor_shows_the_busy_and_resize_cursors() {
+    let mut window_state =
+      WindowState::new(&WindowAttributes::default(), None, 1.0, Theme::Light, None);
+    let decorated = WindowFlags::RESIZABLE | WindowFlags::DECORATIONS;
+    let borderless = WindowFlags::RESIZABLE;
+    let mouse = &mut window_state.mouse;
+
+    assert_eq!(
+      mouse.hit_test_cursor(HTCLIENT, decorated),
+      Some(HitTestCursor::Current)
+    );
+    assert_eq!(mouse.hit_test_cursor(HTCAPTION, decorated), None);
+    assert_eq!(mouse.hit_test_cursor(HTLEFT, decorated), None);
+    assert_eq!(
+      mouse.hit_test_cursor(HTTOPLEFT, borderless),
+      Some(HitTestCursor::Resize(CursorIcon::NwseResize))
+    );
+    // A borderless window which isn't resizable has no resize edges.
+    assert_eq!(mouse.hit_test_cursor(HTLEFT, WindowFlags::empty()), None);
+
+    // The busy cursor is shown over the whole window.
+    mouse.busy = true;
+    assert_eq!(
+      mouse.hit_test_cursor(HTCAPTION, decorated),
+      Some(HitTestCursor::Current)
+    );
+    assert_eq!(
+      mouse.hit_test_cursor(HTLEFT, borderless),
+      Some(HitTestCursor::Current)
+    );
+  }
+
+  #[test]
+  fn grabbed_cursor_follows_the_focus() {
+    let client_rect = RECT {
+      left: 10,
+      top: 20,
+      right: 410,
+      bottom: 320,
+    };
+    let flags = CursorFlags::IN_WINDOW | CursorFlags::HIDDEN | CursorFlags::GRABBED;
+
+    assert_eq!(
+      flags.cursor_clip(client_rect, true),
+      CursorClip::Confine(client_rect)
+    );
+    assert!(flags.cursor_hidden());
+
+    // Losing the focus releases the cursor, and the clip is left to the focused window until the
+    // focus comes back.
+    assert_eq!(flags.focus_clip(client_rect, false), CursorClip::Release);
+    assert_eq!(flags.cursor_clip(client_rect, false), CursorClip::Keep);
+
+    // The cursor is confined again to the client area the window has when it regains the focus.
+    let moved_rect = RECT {
+      left: 70,
+      top: 80,
+      right: 470,
+      bottom: 380,
+    };
+    assert_eq!(
+      flags.focus_clip(moved_rect, true),
+      CursorClip::Confine(moved_rect)
+    );
+
+    // Without a grab, only the focused window releases the clip.
+    let flags = CursorFlags::IN_WINDOW;
+    assert_eq!(flags.cursor_clip(client_rect, true), CursorClip::Release);
+    assert_eq!(flags.focus_clip(client_rect, false), CursorClip::Keep);
+  }
+
+  #[test]
+  fn cursor_visibility_is_independent_of_the_grab() {
+    let hidden = CursorFlags::IN_WINDOW | CursorFlags::HIDDEN;
+    assert!(hidden.cursor_hidden());
+    assert!((hidden | CursorFlags::GRABBED).cursor_hidden());
+    assert!(!(CursorFlags::IN_WINDOW | CursorFlags::GRABBED).cursor_hidden());
+    // The cursor is only hidden over the client area.
+    assert!(!CursorFlags::HIDDEN.cursor_hidden());
+
+    // Releasing the grab or showing the cursor applies it right away.
+    let grabbed = CursorFlags::IN_WINDOW | CursorFlags::GRABBED;
+    assert!(grabbed.reapplies_cursor(CursorFlags::IN_WINDOW));
+    assert!(hidden.reapplies_cursor(CursorFlags::IN_WINDOW));
+    assert!(!CursorFlags::IN_WINDOW.reapplies_cursor(grabbed));
+    assert!(!CursorFlags::IN_WINDOW.reapplies_cursor(hidden));
+    // Unless the cursor is outside of the window, where `WM_SETCURSOR` isn't ours.
+    assert!(!CursorFlags::GRABBED.reapplies_cursor(CursorFlags::empty()));
+  }
+
+  #[test]
+  fn mouse_capture_is_released_with_the_last_button() {
+    let mut window_state =
+      WindowState::new(&WindowAttributes::default(), None, 1.0, Theme::Light, None);
+    let mouse = &mut window_state.mouse;
+
+    mouse.capture();
+    mouse.capture();
+    assert!(mouse.reapply_cursor_on_move());
+    assert!(!mouse.release_capture());
+    assert!(mouse.release_capture());
+    assert!(!mouse.reapply_cursor_on_move());
+    // A release without a press doesn't underflow.
+    assert!(mouse.release_capture());
+
+    mouse.capture();
+    mouse.capture_changed(true);
+    assert!(mouse.reapply_cursor_on_move());
+    // Another window taking the capture releases every button.
+    mouse.capture_changed(false);
+    assert!(!mouse.reapply_cursor_on_move());
+  }
+
+  #[test]
+  fn physical_min_size_is_not_rescaled() {
+    let mut window_state =
+      WindowState::new(&WindowAttributes::default(), None, 2.0, Theme::Light, None);
+
+    window_state.min_size = Some(PhysicalSize::<u32>::new(800, 800).into());
+    assert_eq!(
+      window_state.clamp_inner_size(PhysicalSize::new(600, 900)),
+      PhysicalSize::new(800, 900)
+    );
+
+    // A logical min size is scaled to 200%.
+    window_state.min_size = Some(LogicalSize::<f64>::new(800.0, 800.0).into());
+    assert_eq!(
+      window_state.clamp_inner_size(PhysicalSize::new(600, 900)),
+      PhysicalSize::new(1600, 1600)
+    );
+
+    window_state.min_size = None;
+    window_state.max_size = Some(PhysicalSize::<u32>::new(800, 800).into());
+    assert_eq!(
+      window_state.clamp_inner_size(PhysicalSize::new(600, 900)),
+      PhysicalSize::new(600, 800)
+    );
   }
 }
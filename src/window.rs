@@ -2,18 +2,23 @@
 // SPDX-License-Identifier: Apache-2.0
 
 //! The `Window` struct and associated types.
-use std::fmt;
+use std::{
+  fmt,
+  sync::{Arc, Mutex},
+};
 
 use crate::{
   dpi::{PhysicalPosition, PhysicalSize, Position, Size},
   error::{ExternalError, NotSupportedError, OsError},
-  event_loop::EventLoopWindowTarget,
+  event::MouseButtons,
+  event_loop::{ControlFlow, EventLoopWindowTarget},
+  keyboard::KeyCode,
   menu::MenuBar,
   monitor::{MonitorHandle, VideoMode},
   platform_impl,
 };
 
-pub use crate::icon::{BadIcon, Icon};
+pub use crate::icon::{BadIcon, CustomCursor, Icon};
 
 /// Represents a window.
 ///
@@ -43,6 +48,7 @@ pub use crate::icon::{BadIcon, Icon};
 /// ```
 pub struct Window {
   pub(crate) window: platform_impl::Window,
+  pub(crate) pending_control_flow: Arc<Mutex<Option<ControlFlow>>>,
 }
 
 impl fmt::Debug for Window {
@@ -172,6 +178,11 @@ pub struct WindowAttributes {
   /// The default is `true`.
   pub visible: bool,
 
+  /// Whether the window should be focused when it is first shown.
+  ///
+  /// The default is `true`.
+  pub focused: bool,
+
   /// Whether the the window should be transparent. If this is true, writing colors
   /// with alpha values different than `1.0` will produce a transparent window.
   ///
@@ -212,6 +223,7 @@ impl Default for WindowAttributes {
       maximized: false,
       fullscreen: None,
       visible: true,
+      focused: true,
       transparent: false,
       decorations: true,
       always_on_top: false,
@@ -272,6 +284,26 @@ impl WindowBuilder {
     self
   }
 
+  /// Sets a desired initial position for the window, relative to the top left corner of
+  /// `monitor`.
+  ///
+  /// A logical position is converted using the scale factor of `monitor`, so e.g. a logical
+  /// position of `(0, 0)` places the window at the top left corner of the monitor.
+  ///
+  /// See [`WindowAttributes::position`] for details.
+  ///
+  /// [`WindowAttributes::position`]: crate::window::WindowAttributes::position
+  #[inline]
+  pub fn with_position_on_monitor<P: Into<Position>>(
+    mut self,
+    monitor: &MonitorHandle,
+    position: P,
+  ) -> Self {
+    self.window.position =
+      Some(position_on_monitor(monitor.position(), monitor.scale_factor(), position.into()).into());
+    self
+  }
+
   /// Sets whether the window is resizable or not.
   ///
   /// See [`Window::set_resizable`] for details.
@@ -338,6 +370,20 @@ impl WindowBuilder {
     self
   }
 
+  /// Sets whether the window will be focused when it is first shown, e.g. `false` for a
+  /// notification that shouldn't take the focus from the application the user is typing in.
+  ///
+  /// This only affects how the window is first shown, it can still be focused afterwards.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn with_focused(mut self, focused: bool) -> Self {
+    self.window.focused = focused;
+    self
+  }
+
   /// Sets whether the background of the window should be transparent.
   #[inline]
   pub fn with_transparent(mut self, transparent: bool) -> Self {
@@ -389,7 +435,10 @@ impl WindowBuilder {
     platform_impl::Window::new(&window_target.p, self.window, self.platform_specific).map(
       |window| {
         window.request_redraw();
-        Window { window }
+        Window {
+          window,
+          pending_control_flow: window_target.pending_control_flow.clone(),
+        }
       },
     )
   }
@@ -449,6 +498,15 @@ impl Window {
   /// * While processing a `RedrawRequested` event that was sent during `MainEventsCleared` or any
   ///   directly subsequent `RedrawRequested` event.
   ///
+  /// It can be called from any thread, e.g. when a worker thread has a new frame ready.
+  ///
+  /// Calling it again before the `RedrawRequested` event is delivered has no effect, so a batch
+  /// of requests results in a single event.
+  ///
+  /// The event loop is woken up to deliver the event, so under
+  /// [`ControlFlow::Wait`](crate::event_loop::ControlFlow::Wait) it isn't delayed until the next
+  /// input event.
+  ///
   /// ## Platform-specific
   ///
   /// - **iOS:** Can only be called on the main thread.
@@ -457,6 +515,18 @@ impl Window {
   pub fn request_redraw(&self) {
     self.window.request_redraw()
   }
+
+  /// Sets the control flow of the event loop this window was created with, like
+  /// [`EventLoopWindowTarget::set_control_flow`] does.
+  ///
+  /// This is meant for code that holds a window but not the event loop target. It can be called
+  /// from any thread, the control flow is applied the next time the event handler returns. A loop
+  /// that is waiting isn't woken up by it, so under [`ControlFlow::Wait`] pair it with e.g.
+  /// [`Window::request_redraw`] when it has to take effect right away.
+  #[inline]
+  pub fn set_control_flow(&self, control_flow: ControlFlow) {
+    *self.pending_control_flow.lock().unwrap() = Some(control_flow);
+  }
 }
 
 /// Position and size functions.
@@ -541,6 +611,38 @@ impl Window {
     self.window.set_inner_size(size.into())
   }
 
+  /// Returns the physical size of the window's content area, which is the client area without
+  /// the menu bar when tao draws the menu bar inside the client area.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** The menu bar is part of the client area, so this is the inner size minus the
+  ///   height of the menu bar while it is visible.
+  /// - **Windows:** Same as [`Window::inner_size`], the menu bar isn't part of the client area.
+  /// - **macOS:** The content rect of the window, which excludes the title bar even when the
+  ///   view extends below a transparent one.
+  /// - **iOS / Android:** Same as [`Window::inner_size`].
+  #[inline]
+  pub fn content_size(&self) -> PhysicalSize<u32> {
+    self.window.content_size()
+  }
+
+  /// Resizes the window so that its content area, see [`Window::content_size`], has the given
+  /// size with the current decorations, menu bar and scale factor.
+  ///
+  /// This automatically un-maximizes the window if it's maximized.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Unlike [`Window::set_inner_size`], this accounts for a menu bar wrapped on
+  ///   several lines at the new width.
+  /// - **macOS:** Sets the size of the content rect, see [`Window::content_size`].
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_content_size<S: Into<Size>>(&self, size: S) {
+    self.window.set_content_size(size.into())
+  }
+
   /// Returns the physical size of the entire window.
   ///
   /// These dimensions include the title bar and borders. If you don't want that (and you usually don't),
@@ -557,6 +659,9 @@ impl Window {
 
   /// Sets a minimum dimension size for the window.
   ///
+  /// A [`PhysicalSize`] is kept in physical pixels when the scale factor changes, while a
+  /// [`LogicalSize`](crate::dpi::LogicalSize) is scaled with it.
+  ///
   /// ## Platform-specific
   ///
   /// - **iOS / Android:** Unsupported.
@@ -567,6 +672,9 @@ impl Window {
 
   /// Sets a maximum dimension size for the window.
   ///
+  /// A [`PhysicalSize`] is kept in physical pixels when the scale factor changes, while a
+  /// [`LogicalSize`](crate::dpi::LogicalSize) is scaled with it.
+  ///
   /// ## Platform-specific
   ///
   /// - **iOS / Android:** Unsupported.
@@ -582,12 +690,24 @@ impl Window {
   ///
   /// ## Platform-specific
   ///
+  /// - **Windows:** Can be called from any thread, the title is set on the event loop thread.
   /// - **iOS / Android:** Unsupported.
   #[inline]
   pub fn set_title(&self, title: &str) {
     self.window.set_title(title)
   }
 
+  /// Gets the title of the window, which is the last one passed to [`Window::set_title`] even
+  /// while it's still being set asynchronously.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **iOS / Android:** Unsupported, returns an empty string.
+  #[inline]
+  pub fn title(&self) -> String {
+    self.window.title()
+  }
+
   /// Modifies the menu of the window.
   ///
   /// ## Platform-specific
@@ -636,14 +756,25 @@ impl Window {
   ///
   /// Due to a bug in XFCE, this has no effect on Xfwm.
   ///
-  /// ## Platform-specific
-  ///
+  /// - **Windows:** The resize borders of undecorated windows follow this setting as well.
   /// - **iOS / Android:** Unsupported.
   #[inline]
   pub fn set_resizable(&self, resizable: bool) {
     self.window.set_resizable(resizable)
   }
 
+  /// Sets the width, in logical pixels, of the area along the edges of an undecorated window
+  /// where it can be resized. The default is [`BORDERLESS_RESIZE_INSET`].
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The default is in physical pixels, so it doesn't scale with the scale factor.
+  /// - **macOS / iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_resize_border_width(&self, width: u32) {
+    self.window.set_resize_border_width(width)
+  }
+
   /// Sets the window to minimized or back
   ///
   /// ## Platform-specific
@@ -761,6 +892,34 @@ impl Window {
     self.window.set_always_on_top(always_on_top)
   }
 
+  /// Change whether or not clicking the window activates it. Defaults to `true`.
+  ///
+  /// When `false`, the window still receives mouse input but the previously active window keeps
+  /// the activation and the keyboard focus, which suits floating toolbars and overlays.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** The window manager is asked not to give the keyboard focus to the window, so it
+  ///   doesn't take the focus when it's activated otherwise either, e.g. by [`Window::set_focus`].
+  ///   Some window managers ignore it.
+  /// - **macOS / iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_activate_on_click(&self, activate_on_click: bool) {
+    self.window.set_activate_on_click(activate_on_click)
+  }
+
+  /// Change whether or not the window background is transparent.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Has no effect on windows created with `WindowBuilderExtWindows::with_no_redirection_bitmap`.
+  /// - **Linux / iOS / Android:** Unsupported. On Linux transparency can only be set at creation
+  ///   with [`WindowBuilder::with_transparent`].
+  #[inline]
+  pub fn set_transparent(&self, transparent: bool) {
+    self.window.set_transparent(transparent)
+  }
+
   /// Sets the window icon. On Windows and Linux, this is typically the small icon in the top-left
   /// corner of the title bar.
   ///
@@ -777,14 +936,56 @@ impl Window {
 
   /// Sets location of IME candidate box in client area coordinates relative to the top left.
   ///
+  /// This is usually the position of the text cursor, so the candidates are shown next to it.
+  ///
   /// ## Platform-specific
   ///
+  /// - **Windows:** Both the composition and the candidate windows are moved.
+  /// - **Linux:** Moves the candidate window of the system input method, e.g. IBus or Fcitx.
   /// - **iOS / Android:** Unsupported.
   #[inline]
   pub fn set_ime_position<P: Into<Position>>(&self, position: P) {
     self.window.set_ime_position(position.into())
   }
 
+  /// Sets whether the window accepts input from the input method, which is the default.
+  ///
+  /// While it isn't allowed, key presses aren't processed by the input method and no
+  /// [`WindowEvent::Ime`](crate::event::WindowEvent::Ime) events are sent. Disallowing it during
+  /// a composition cancels the composition. Toggling it sends
+  /// [`Ime::Enabled`](crate::event::Ime::Enabled) or [`Ime::Disabled`](crate::event::Ime::Disabled).
+  ///
+  /// This is useful to turn the input method off e.g. during gameplay, and back on when a text
+  /// field is focused.
+  ///
+  /// Dead keys are composed by the keyboard layout rather than the input method, they keep
+  /// working while it isn't allowed.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS / iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_ime_allowed(&self, allowed: bool) {
+    self.window.set_ime_allowed(allowed)
+  }
+
+  /// Prevents the window contents from being captured by other applications, e.g. in
+  /// screenshots and screen recordings.
+  ///
+  /// The window stays visible on the local display, which suits presenter overlays, e.g. a
+  /// teleprompter shown with [`Window::set_always_on_top`] that shouldn't be seen by the audience
+  /// of a screen share.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Before Windows 10 version 2004, the window shows as a black rectangle in
+  ///   captures instead of being left out.
+  /// - **Linux / iOS / Android:** Unsupported, X11 has no way to protect the contents.
+  #[inline]
+  pub fn set_content_protected(&self, protected: bool) {
+    self.window.set_content_protected(protected)
+  }
+
   /// Requests user attention to the window, this has no effect if the application
   /// is already focused. How requesting for user attention manifests is platform dependent,
   /// see `UserAttentionType` for details.
@@ -802,6 +1003,38 @@ impl Window {
     self.window.request_user_attention(request_type)
   }
 
+  /// Sets the number shown in a badge on the application icon, or clears it with `None`.
+  ///
+  /// A count of zero or less clears the badge as well.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Shown as an overlay on the taskbar button of this window. Counts above 99
+  ///   are shown as `99+`.
+  /// - **macOS:** Shown on the dock tile of the application, not of the window.
+  /// - **Linux:** Sent to launchers implementing the Unity launcher API, such as the Ubuntu
+  ///   dock, for the desktop entry named after the executable.
+  /// - **iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_badge_count(&self, count: Option<i64>) {
+    self.window.set_badge_count(count)
+  }
+
+  /// Shows a progress bar on the application icon, e.g. for a download.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** Shown on the taskbar button of this window.
+  /// - **Linux:** Sent to launchers implementing the Unity launcher API, such as the Ubuntu
+  ///   dock, for the desktop entry named after the executable. [`ProgressState::Paused`] and
+  ///   [`ProgressState::Error`] are shown like [`ProgressState::Normal`], and
+  ///   [`ProgressState::Indeterminate`] keeps the current progress.
+  /// - **macOS / iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_progress_bar(&self, state: ProgressBarState) {
+    self.window.set_progress_bar(state)
+  }
+
   /// Hides the menu associated with the window
   ///
   /// ## Platform-specific
@@ -831,37 +1064,139 @@ impl Window {
   pub fn is_menu_visible(&self) -> bool {
     self.window.is_menu_visible()
   }
+
+  /// Returns the current window theme.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** The theme preferred by the desktop, from the `color-scheme` setting of the
+  ///   desktop portal, or from the GTK settings when there is no portal.
+  /// - **iOS / Android:** Unsupported, always returns [`Theme::Light`].
+  #[inline]
+  pub fn theme(&self) -> Theme {
+    self.window.theme()
+  }
+
+  /// Returns the mouse buttons which are currently pressed, whether or not the window has the
+  /// focus.
+  ///
+  /// This is a snapshot of the state of the devices, meant to re-synchronize the input state,
+  /// e.g. when the window regains the focus, rather than to replace `MouseInput` events.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** The side buttons aren't reported.
+  /// - **iOS / Android:** Unsupported, always returns no buttons.
+  #[inline]
+  pub fn pressed_mouse_buttons(&self) -> MouseButtons {
+    self.window.pressed_mouse_buttons()
+  }
+
+  /// Returns the physical keys which are currently pressed, whether or not the window has the
+  /// focus.
+  ///
+  /// Like [`Window::pressed_mouse_buttons`], this is a snapshot of the state of the devices.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS / Linux / iOS / Android:** Unsupported, always returns no keys.
+  #[inline]
+  pub fn pressed_keys(&self) -> Vec<KeyCode> {
+    self.window.pressed_keys()
+  }
 }
 
 /// Cursor functions.
 impl Window {
   /// Modifies the cursor icon of the window.
   ///
+  /// This replaces the cursor set with [`Window::set_custom_cursor`].
+  ///
   /// ## Platform-specific
   ///
+  /// - **Windows:** Can be called from any thread. The cursor changes right away when it is over
+  ///   the client area, without waiting for the mouse to move.
   /// - **iOS / Android:** Unsupported.
   #[inline]
   pub fn set_cursor_icon(&self, cursor: CursorIcon) {
     self.window.set_cursor_icon(cursor);
   }
 
+  /// Shows `cursor` over the window instead of a [`CursorIcon`], until
+  /// [`Window::set_cursor_icon`] is called.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The busy cursor of [`Window::set_busy_cursor`] still takes precedence.
+  ///   Cursors loaded with `IconExtWindows::cursor_from_path` are supported as well.
+  /// - **macOS / iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+    self.window.set_custom_cursor(cursor);
+  }
+
+  /// Shows the busy cursor over the whole window, e.g. during a blocking operation.
+  ///
+  /// While busy, the busy cursor takes precedence over the icon set by
+  /// [`Window::set_cursor_icon`], which is restored once the busy state is cleared.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS / iOS / Android:** Unsupported.
+  #[inline]
+  pub fn set_busy_cursor(&self, busy: bool) {
+    self.window.set_busy_cursor(busy);
+  }
+
   /// Changes the position of the cursor in window coordinates.
   ///
   /// ## Platform-specific
   ///
+  /// - **Linux:** Returns an [`ExternalError::NotSupported`] if the window isn't realized yet or
+  ///   there's no pointer. Wayland compositors usually ignore the request.
   /// - **iOS / Android:** Always returns an [`ExternalError::NotSupported`].
   #[inline]
   pub fn set_cursor_position<P: Into<Position>>(&self, position: P) -> Result<(), ExternalError> {
     self.window.set_cursor_position(position.into())
   }
 
+  /// Changes the position of the cursor in window coordinates, clamped to the client area of
+  /// the window.
+  ///
+  /// This is useful to recenter the cursor without risking to move it outside of the window,
+  /// e.g. when the window is partially off-screen.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Linux:** See [`Window::set_cursor_position`].
+  /// - **iOS / Android:** Always returns an [`ExternalError::NotSupported`].
+  #[inline]
+  pub fn set_cursor_position_clamped<P: Into<Position>>(
+    &self,
+    position: P,
+  ) -> Result<(), ExternalError> {
+    let (x, y): (i32, i32) = position
+      .into()
+      .to_physical::<i32>(self.scale_factor())
+      .into();
+    let size = self.inner_size();
+    let x = x.clamp(0, (size.width as i32 - 1).max(0));
+    let y = y.clamp(0, (size.height as i32 - 1).max(0));
+    self
+      .window
+      .set_cursor_position(PhysicalPosition::new(x, y).into())
+  }
+
   /// Grabs the cursor, preventing it from leaving the window.
   ///
-  /// There's no guarantee that the cursor will be hidden. You should
-  /// hide it by yourself if you want so.
+  /// Grabbing the cursor doesn't change its visibility, which is controlled separately by
+  /// [`Window::set_cursor_visible`]. This way a visible cursor can be confined during a drag, and
+  /// the cursor can be hidden as well for e.g. a mouse-look camera.
   ///
   /// ## Platform-specific
   ///
+  /// - **Windows:** The cursor is confined to the client area, which follows the window when it
+  ///   is moved, resized or maximized. It can still move within it and stays visible.
   /// - **macOS:** This locks the cursor in a fixed location, which looks visually awkward.
   /// - **iOS / Android:** Always returns an [`ExternalError::NotSupported`].
   #[inline]
@@ -884,6 +1219,21 @@ impl Window {
     self.window.set_cursor_visible(visible)
   }
 
+  /// Makes the window ignore the cursor, so that the mouse input goes to the window below it.
+  ///
+  /// This is meant for click-through overlays. Calling it again with `false` makes the window
+  /// receive the mouse input again. The keyboard input isn't affected.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The window gets the `WS_EX_TRANSPARENT` and `WS_EX_LAYERED` extended styles.
+  /// - **Linux:** The input shape of the window is emptied.
+  /// - **iOS / Android:** Always returns an [`ExternalError::NotSupported`].
+  #[inline]
+  pub fn set_ignore_cursor_events(&self, ignore: bool) -> Result<(), ExternalError> {
+    self.window.set_ignore_cursor_events(ignore)
+  }
+
   /// Moves the window with the left mouse button until the button is released.
   ///
   /// There's no guarantee that this will work unless the left mouse button was pressed
@@ -897,6 +1247,20 @@ impl Window {
   pub fn drag_window(&self) -> Result<(), ExternalError> {
     self.window.drag_window()
   }
+
+  /// Resizes the window from the given edge or corner with the left mouse button until the
+  /// button is released, e.g. for the resize handles of an undecorated window.
+  ///
+  /// There's no guarantee that this will work unless the left mouse button was pressed
+  /// immediately before this function is called.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **macOS / iOS / Android:** Always returns an [`ExternalError::NotSupported`].
+  #[inline]
+  pub fn drag_resize_window(&self, direction: ResizeDirection) -> Result<(), ExternalError> {
+    self.window.drag_resize_window(direction)
+  }
 }
 
 /// Monitor info functions.
@@ -931,7 +1295,8 @@ impl Window {
 
   /// Returns the primary monitor of the system.
   ///
-  /// Returns `None` if it can't identify any monitor as a primary one.
+  /// Returns `None` if it can't identify any monitor as a primary one, e.g. in a headless
+  /// session, so it shouldn't be unwrapped.
   ///
   /// This is the same as `EventLoopWindowTarget::primary_monitor`, and is provided for convenience.
   ///
@@ -958,6 +1323,13 @@ unsafe impl raw_window_handle::HasRawWindowHandle for Window {
 }
 
 /// Describes the appearance of the mouse cursor.
+///
+/// ## Platform-specific
+///
+/// - **Windows:** `ContextMenu`, `Alias`, `Copy`, `ZoomIn` and `ZoomOut` have no system
+///   equivalent and show the arrow.
+/// - **Linux:** The cursors are looked up by their CSS name in the cursor theme, the default
+///   cursor is shown when the theme doesn't have one.
 #[non_exhaustive]
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -1015,6 +1387,16 @@ pub enum CursorIcon {
   NwseResize,
   ColResize,
   RowResize,
+
+  /// Hides the cursor while it is over the window.
+  ///
+  /// Unlike [`Window::set_cursor_visible`], this is just another cursor icon and is replaced
+  /// by the next one set with [`Window::set_cursor_icon`].
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **iOS / Android:** Unsupported.
+  None,
 }
 
 impl Default for CursorIcon {
@@ -1059,5 +1441,94 @@ impl Default for UserAttentionType {
   }
 }
 
+/// The progress shown on the application icon, see [`Window::set_progress_bar`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProgressBarState {
+  /// The progress in percent, from 0 to 100. `None` keeps the current progress.
+  pub progress: Option<u64>,
+  /// The state of the progress bar.
+  pub state: ProgressState,
+}
+
+/// The state of a progress bar, see [`ProgressBarState`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressState {
+  /// No progress bar is shown.
+  None,
+  /// The progress bar shows the progress.
+  Normal,
+  /// The progress bar shows an ongoing operation without a known progress.
+  Indeterminate,
+  /// The progress bar shows the progress of a paused operation.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The progress bar is yellow.
+  Paused,
+  /// The progress bar shows the progress of a failed operation.
+  ///
+  /// ## Platform-specific
+  ///
+  /// - **Windows:** The progress bar is red.
+  Error,
+}
+
+impl Default for ProgressState {
+  fn default() -> Self {
+    ProgressState::None
+  }
+}
+
+/// The edge or corner of the window to resize from, see [`Window::drag_resize_window`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResizeDirection {
+  North,
+  NorthEast,
+  East,
+  SouthEast,
+  South,
+  SouthWest,
+  West,
+  NorthWest,
+}
+
 /// A constant used to determine how much inside the window, the resize handler should appear (only used in Linux(gtk) and Windows).
 pub const BORDERLESS_RESIZE_INSET: i32 = 5;
+
+/// Converts `position` to physical desktop coordinates, relative to the top left corner of a
+/// monitor at `origin` with the given scale factor.
+fn position_on_monitor(
+  origin: PhysicalPosition<i32>,
+  scale_factor: f64,
+  position: Position,
+) -> PhysicalPosition<i32> {
+  let offset = position.to_physical::<i32>(scale_factor);
+  PhysicalPosition::new(origin.x + offset.x, origin.y + offset.y)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dpi::LogicalPosition;
+
+  #[test]
+  fn logical_position_on_a_scaled_monitor() {
+    // A 150% monitor to the right of a 1920px wide primary monitor.
+    let origin = PhysicalPosition::new(1920, 0);
+
+    assert_eq!(
+      position_on_monitor(origin, 1.5, LogicalPosition::new(0.0, 0.0).into()),
+      origin
+    );
+    assert_eq!(
+      position_on_monitor(origin, 1.5, LogicalPosition::new(100.0, 100.0).into()),
+      PhysicalPosition::new(2070, 150)
+    );
+    // A physical position isn't scaled.
+    assert_eq!(
+      position_on_monitor(origin, 1.5, PhysicalPosition::new(100, 100).into()),
+      PhysicalPosition::new(2020, 100)
+    );
+  }
+}
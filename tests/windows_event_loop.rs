@@ -0,0 +1,161 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(target_os = "windows")]
+
+use std::{
+  panic::{self, AssertUnwindSafe},
+  sync::Arc,
+  thread,
+  time::{Duration, Instant},
+};
+
+use tao::{
+  event::{Event, StartCause},
+  event_loop::{ControlFlow, EventLoop},
+  platform::{
+    run_return::EventLoopExtRunReturn,
+    windows::{EventLoopExtWindows, WindowExtWindows},
+  },
+  window::WindowBuilder,
+};
+use windows::Win32::{
+  Foundation::{HWND, RECT},
+  UI::WindowsAndMessaging::{
+    GetClipCursor, GetSystemMetrics, GetWindowTextW, SM_CXVIRTUALSCREEN, SM_CYVIRTUALSCREEN,
+    SM_XVIRTUALSCREEN, SM_YVIRTUALSCREEN,
+  },
+};
+
+fn window_text(hwnd: HWND) -> String {
+  let mut text = [0u16; 64];
+  let len = unsafe { GetWindowTextW(hwnd, text.as_mut_ptr(), text.len() as i32) };
+  String::from_utf16_lossy(&text[..len as usize])
+}
+
+fn cursor_clip() -> RECT {
+  let mut rect = RECT::default();
+  unsafe { GetClipCursor(&mut rect) };
+  rect
+}
+
+fn virtual_screen() -> RECT {
+  unsafe {
+    let left = GetSystemMetrics(SM_XVIRTUALSCREEN);
+    let top = GetSystemMetrics(SM_YVIRTUALSCREEN);
+    RECT {
+      left,
+      top,
+      right: left + GetSystemMetrics(SM_CXVIRTUALSCREEN),
+      bottom: top + GetSystemMetrics(SM_CYVIRTUALSCREEN),
+    }
+  }
+}
+
+#[test]
+fn user_events_are_ordered_with_window_calls() {
+  let mut event_loop = EventLoop::<u32>::new_any_thread();
+  let window = Arc::new(
+    WindowBuilder::new()
+      .with_visible(false)
+      .build(&event_loop)
+      .unwrap(),
+  );
+  let hwnd = HWND(window.hwnd() as _);
+  let proxy = event_loop.create_proxy();
+
+  let window_ = window.clone();
+  let sender = thread::spawn(move || {
+    for i in 1..=20 {
+      window_.set_title(&i.to_string());
+      window_.request_redraw();
+      proxy.send_event(i).unwrap();
+    }
+  });
+
+  let mut received = Vec::new();
+  event_loop.run_return(|event, _, control_flow| {
+    *control_flow = ControlFlow::Wait;
+    if let Event::UserEvent(i) = event {
+      // The title set before sending the event is already applied.
+      assert_eq!(window_text(hwnd), i.to_string());
+      received.push(i);
+      if i == 20 {
+        *control_flow = ControlFlow::Exit;
+      }
+    }
+  });
+
+  sender.join().unwrap();
+  assert_eq!(received, (1..=20).collect::<Vec<_>>());
+}
+
+#[test]
+fn send_event_after_exit_returns_err() {
+  let mut event_loop = EventLoop::<()>::new_any_thread();
+  let proxy = event_loop.create_proxy();
+
+  event_loop.run_return(|_, _, control_flow| *control_flow = ControlFlow::Exit);
+
+  assert!(proxy.send_event(()).is_err());
+}
+
+#[test]
+fn redraw_requested_from_another_thread_is_delivered() {
+  let mut event_loop = EventLoop::<()>::new_any_thread();
+  let window = Arc::new(WindowBuilder::new().build(&event_loop).unwrap());
+  let window_id = window.id();
+  let request_redraw = || {
+    let window = window.clone();
+    thread::spawn(move || window.request_redraw())
+      .join()
+      .unwrap();
+  };
+
+  // Each request is made once the previous `RedrawRequested` was delivered, so the later ones
+  // are only delivered if `WM_PAINT` cleared the pending request. The first one may be delivered
+  // along with the initial paint of the window.
+  let deadline = Instant::now() + Duration::from_secs(5);
+  let mut redraws = 0;
+  event_loop.run_return(|event, _, control_flow| {
+    *control_flow = ControlFlow::WaitUntil(deadline);
+    match event {
+      Event::NewEvents(StartCause::Init) => request_redraw(),
+      Event::NewEvents(StartCause::ResumeTimeReached { .. }) => *control_flow = ControlFlow::Exit,
+      Event::RedrawRequested(id) if id == window_id => {
+        redraws += 1;
+        if redraws == 3 {
+          *control_flow = ControlFlow::Exit;
+        } else {
+          request_redraw();
+        }
+      }
+      _ => (),
+    }
+  });
+
+  assert_eq!(redraws, 3);
+}
+
+// The release itself is covered by the unit test of `util::release_cursor_clip`.
+#[test]
+#[ignore = "the grab only confines the cursor while the test process is in the foreground"]
+fn panic_during_a_grab_releases_the_cursor_clip() {
+  let mut event_loop = EventLoop::<()>::new_any_thread();
+  let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+  let mut grabbed = false;
+  let result = panic::catch_unwind(AssertUnwindSafe(|| {
+    event_loop.run_return(|event, _, _| {
+      if let Event::NewEvents(StartCause::Init) = event {
+        window.set_cursor_grab(true).unwrap();
+        grabbed = cursor_clip() != virtual_screen();
+        panic!("panicking during a grab");
+      }
+    });
+  }));
+
+  assert!(result.is_err());
+  assert!(grabbed, "the cursor wasn't confined by the grab");
+  assert_eq!(cursor_clip(), virtual_screen());
+}
@@ -0,0 +1,407 @@
+// Copyright 2019-2021 Tauri Programme within The Commons Conservancy
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(target_os = "windows")]
+
+use std::{
+  mem, ptr,
+  sync::{Arc, Mutex, MutexGuard, PoisonError},
+  thread,
+  time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use tao::{
+  dpi::{PhysicalPosition, PhysicalSize},
+  event::{ElementState, Event, MouseButton, StartCause, WindowEvent},
+  event_loop::{ControlFlow, EventLoop},
+  platform::{
+    run_return::EventLoopExtRunReturn,
+    windows::{EventLoopExtWindows, WindowExtWindows},
+  },
+  window::{CursorIcon, Fullscreen, Window, WindowBuilder},
+};
+use windows::Win32::{
+  Foundation::{HINSTANCE, HWND, LPARAM, POINT, RECT, WPARAM},
+  Graphics::Gdi::{GetMonitorInfoW, MonitorFromPoint, MONITORINFO, MONITOR_DEFAULTTONEAREST},
+  UI::Input::Ime::{
+    ImmGetCompositionStringW, ImmGetContext, ImmReleaseContext, ImmSetCompositionStringW,
+    GCS_COMPSTR, SCS_SETSTR,
+  },
+  UI::WindowsAndMessaging::{
+    GetClassLongW, GetClipCursor, GetCursor, GetWindowLongW, GetWindowRect, LoadCursorW,
+    SendMessageW, SetCursor, SetCursorPos, CS_DBLCLKS, GCL_STYLE, GWL_EXSTYLE, HCURSOR, HTCAPTION,
+    HTCLIENT, HTLEFT, IDC_CROSS, MK_LBUTTON, WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP,
+    WM_NCHITTEST, WS_EX_TOPMOST,
+  },
+};
+
+lazy_static! {
+  /// Serializes the tests that move the cursor or change its clip, which the whole desktop shares.
+  static ref SYSTEM_CURSOR: Mutex<()> = Mutex::new(());
+}
+
+fn lock_system_cursor() -> MutexGuard<'static, ()> {
+  // A failed test poisons the lock, which doesn't matter to the other tests.
+  SYSTEM_CURSOR.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn hwnd(window: &Window) -> HWND {
+  HWND(window.hwnd() as _)
+}
+
+fn is_topmost(window: &Window) -> bool {
+  let ex_style = unsafe { GetWindowLongW(hwnd(window), GWL_EXSTYLE) } as u32;
+  ex_style & WS_EX_TOPMOST != 0
+}
+
+fn window_rect(window: &Window) -> RECT {
+  let mut rect = RECT::default();
+  unsafe { GetWindowRect(hwnd(window), &mut rect) };
+  rect
+}
+
+fn work_area(position: PhysicalPosition<i32>) -> RECT {
+  let mut monitor_info = MONITORINFO {
+    cbSize: mem::size_of::<MONITORINFO>() as u32,
+    ..Default::default()
+  };
+  unsafe {
+    let hmonitor = MonitorFromPoint(
+      POINT {
+        x: position.x,
+        y: position.y,
+      },
+      MONITOR_DEFAULTTONEAREST,
+    );
+    GetMonitorInfoW(hmonitor, &mut monitor_info);
+  }
+  monitor_info.rcWork
+}
+
+fn cursor_clip() -> RECT {
+  let mut rect = RECT::default();
+  unsafe { GetClipCursor(&mut rect) };
+  rect
+}
+
+/// Moves the mouse over `window`, then runs `f` once the window has seen the cursor enter, without
+/// any further mouse movement.
+fn with_cursor_inside(event_loop: &mut EventLoop<()>, window: &Window, f: impl FnOnce()) {
+  let rect = window_rect(window);
+  let window_id = window.id();
+  let deadline = Instant::now() + Duration::from_secs(5);
+  let mut f = Some(f);
+  event_loop.run_return(|event, _, control_flow| {
+    *control_flow = ControlFlow::Poll;
+    match event {
+      Event::NewEvents(StartCause::Init) => unsafe {
+        SetCursorPos((rect.left + rect.right) / 2, (rect.top + rect.bottom) / 2);
+      },
+      Event::WindowEvent {
+        window_id: id,
+        event: WindowEvent::CursorEntered { .. },
+        ..
+      } if id == window_id => {
+        if let Some(f) = f.take() {
+          f();
+        }
+        *control_flow = ControlFlow::Exit;
+      }
+      Event::MainEventsCleared if Instant::now() > deadline => *control_flow = ControlFlow::Exit,
+      _ => (),
+    }
+  });
+  assert!(f.is_none(), "the cursor never entered the window");
+}
+
+/// Hit tests the given screen coordinates like the system does when the mouse moves.
+fn hit_test(window: &Window, x: i32, y: i32) -> u32 {
+  let lparam = LPARAM(((y as u16 as isize) << 16) | x as u16 as isize);
+  unsafe { SendMessageW(hwnd(window), WM_NCHITTEST, WPARAM(0), lparam) }.0 as u32
+}
+
+#[test]
+fn exiting_fullscreen_only_reports_the_restored_size() {
+  let mut event_loop = EventLoop::<()>::new_any_thread();
+  let restored_size = PhysicalSize::new(400, 300);
+  let window = WindowBuilder::new()
+    .with_inner_size(restored_size)
+    .build(&event_loop)
+    .unwrap();
+  let fullscreen_size = window.current_monitor().unwrap().size();
+
+  let mut sizes = Vec::new();
+  event_loop.run_return(|event, _, control_flow| {
+    *control_flow = ControlFlow::Poll;
+    match event {
+      Event::NewEvents(StartCause::Init) => {
+        window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+        window.set_fullscreen(None);
+      }
+      Event::WindowEvent {
+        event: WindowEvent::Resized(size),
+        ..
+      } => sizes.push(size),
+      Event::MainEventsCleared => *control_flow = ControlFlow::Exit,
+      _ => (),
+    }
+  });
+
+  assert_eq!(sizes.last(), Some(&restored_size));
+  assert!(
+    sizes
+      .iter()
+      .all(|size| *size == fullscreen_size || *size == restored_size),
+    "unexpected sizes: {:?}",
+    sizes
+  );
+}
+
+#[test]
+fn exiting_fullscreen_restores_the_exact_window_rect() {
+  let event_loop = EventLoop::<()>::new_any_thread();
+  let window = WindowBuilder::new()
+    .with_position(PhysicalPosition::new(123, 45))
+    .with_inner_size(PhysicalSize::new(401, 299))
+    .build(&event_loop)
+    .unwrap();
+  let windowed_rect = window_rect(&window);
+
+  window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+  assert_ne!(window_rect(&window), windowed_rect);
+  window.set_fullscreen(None);
+  assert_eq!(window_rect(&window), windowed_rect);
+}
+
+#[test]
+fn always_on_top_survives_fullscreen() {
+  let event_loop = EventLoop::<()>::new_any_thread();
+  let window = WindowBuilder::new().build(&event_loop).unwrap();
+
+  window.set_always_on_top(true);
+  window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+  window.set_fullscreen(None);
+  assert!(is_topmost(&window));
+
+  window.set_always_on_top(false);
+  window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+  window.set_fullscreen(None);
+  assert!(!is_topmost(&window));
+}
+
+#[test]
+fn title_set_from_another_thread_is_read_back() {
+  let event_loop = EventLoop::<()>::new_any_thread();
+  let window = Arc::new(
+    WindowBuilder::new()
+      .with_title("before")
+      .build(&event_loop)
+      .unwrap(),
+  );
+
+  let sender = Arc::clone(&window);
+  thread::spawn(move || sender.set_title("after"))
+    .join()
+    .unwrap();
+  assert_eq!(window.title(), "after");
+}
+
+#[test]
+fn borderless_window_maximized_on_a_secondary_monitor_fills_its_work_area() {
+  let event_loop = EventLoop::<()>::new_any_thread();
+  let primary_monitor = event_loop.primary_monitor();
+  // There is nothing to test with a single monitor.
+  let monitor = match event_loop
+    .available_monitors()
+    .find(|monitor| Some(monitor) != primary_monitor.as_ref())
+  {
+    Some(monitor) => monitor,
+    None => return,
+  };
+  let position = monitor.position();
+  let window = WindowBuilder::new()
+    .with_decorations(false)
+    .with_position(PhysicalPosition::new(position.x + 100, position.y + 100))
+    .with_inner_size(PhysicalSize::new(400, 300))
+    .build(&event_loop)
+    .unwrap();
+
+  window.set_maximized(true);
+  assert_eq!(window_rect(&window), work_area(position));
+}
+
+#[test]
+fn each_window_has_its_own_ime_context() {
+  let mut event_loop = EventLoop::<()>::new_any_thread();
+  let window = WindowBuilder::new().build(&event_loop).unwrap();
+  let other_window = WindowBuilder::new().build(&event_loop).unwrap();
+
+  let himc = unsafe { ImmGetContext(hwnd(&window)) };
+  let other_himc = unsafe { ImmGetContext(hwnd(&other_window)) };
+  assert!(!himc.is_invalid());
+  assert_ne!(himc, other_himc);
+
+  let mut composing = false;
+  event_loop.run_return(|event, _, control_flow| {
+    *control_flow = ControlFlow::Poll;
+    match event {
+      Event::NewEvents(StartCause::Init) => {
+        window.set_focus();
+        let text: Vec<u16> = "tao".encode_utf16().collect();
+        composing = unsafe {
+          ImmSetCompositionStringW(
+            himc,
+            SCS_SETSTR,
+            text.as_ptr() as *const _,
+            (text.len() * mem::size_of::<u16>()) as u32,
+            ptr::null(),
+            0,
+          )
+        }
+        .as_bool();
+        other_window.set_focus();
+      }
+      Event::MainEventsCleared => *control_flow = ControlFlow::Exit,
+      _ => (),
+    }
+  });
+
+  // Without an input method installed there is no composition to end.
+  if composing {
+    let pending = unsafe { ImmGetCompositionStringW(himc, GCS_COMPSTR, ptr::null_mut(), 0) };
+    assert_eq!(pending, 0, "the composition outlived the focus");
+  }
+  unsafe {
+    ImmReleaseContext(hwnd(&window), himc);
+    ImmReleaseContext(hwnd(&other_window), other_himc);
+  }
+}
+
+#[test]
+fn borderless_window_edges_follow_set_resizable() {
+  let event_loop = EventLoop::<()>::new_any_thread();
+  let window = WindowBuilder::new()
+    .with_decorations(false)
+    .with_inner_size(PhysicalSize::new(400, 300))
+    .build(&event_loop)
+    .unwrap();
+  let rect = window_rect(&window);
+  let (x, y) = (rect.left + 1, (rect.top + rect.bottom) / 2);
+
+  assert_eq!(hit_test(&window, x, y), HTLEFT);
+  window.set_resizable(false);
+  let hit = hit_test(&window, x, y);
+  assert!(
+    hit == HTCLIENT || hit == HTCAPTION,
+    "unexpected hit test: {}",
+    hit
+  );
+}
+
+#[test]
+#[ignore = "the grab only confines the cursor while the test process is in the foreground"]
+fn grabbed_cursor_is_confined_to_the_work_area_of_a_maximized_borderless_window() {
+  let _system_cursor = lock_system_cursor();
+  let event_loop = EventLoop::<()>::new_any_thread();
+  let window = WindowBuilder::new()
+    .with_decorations(false)
+    .with_inner_size(PhysicalSize::new(400, 300))
+    .build(&event_loop)
+    .unwrap();
+  window.set_focus();
+
+  window.set_cursor_grab(true).unwrap();
+  window.set_maximized(true);
+  let clip = cursor_clip();
+  window.set_cursor_grab(false).unwrap();
+
+  assert_eq!(clip, work_area(window.outer_position().unwrap()));
+}
+
+#[test]
+fn cursor_icon_changes_without_moving_the_mouse() {
+  let _system_cursor = lock_system_cursor();
+  let mut event_loop = EventLoop::<()>::new_any_thread();
+  let window = WindowBuilder::new()
+    .with_inner_size(PhysicalSize::new(400, 300))
+    .build(&event_loop)
+    .unwrap();
+
+  let mut cursor = None;
+  with_cursor_inside(&mut event_loop, &window, || {
+    window.set_cursor_icon(CursorIcon::Crosshair);
+    cursor = Some(unsafe { GetCursor() });
+  });
+
+  let crosshair = unsafe { LoadCursorW(HINSTANCE::default(), IDC_CROSS) };
+  assert_eq!(cursor, Some(crosshair));
+}
+
+#[test]
+fn cursor_icon_is_reapplied_when_the_grab_is_released() {
+  let _system_cursor = lock_system_cursor();
+  let mut event_loop = EventLoop::<()>::new_any_thread();
+  let window = WindowBuilder::new()
+    .with_inner_size(PhysicalSize::new(400, 300))
+    .build(&event_loop)
+    .unwrap();
+  window.set_focus();
+
+  let mut cursor = None;
+  with_cursor_inside(&mut event_loop, &window, || {
+    window.set_cursor_icon(CursorIcon::Crosshair);
+    window.set_cursor_grab(true).unwrap();
+    // Something else changed the cursor while it was grabbed.
+    unsafe { SetCursor(HCURSOR::default()) };
+    window.set_cursor_grab(false).unwrap();
+    cursor = Some(unsafe { GetCursor() });
+  });
+
+  let crosshair = unsafe { LoadCursorW(HINSTANCE::default(), IDC_CROSS) };
+  assert_eq!(cursor, Some(crosshair));
+}
+
+#[test]
+fn double_click_message_is_reported_as_the_second_click() {
+  let mut event_loop = EventLoop::<()>::new_any_thread();
+  let window = WindowBuilder::new().build(&event_loop).unwrap();
+  let window_id = window.id();
+
+  let class_style = unsafe { GetClassLongW(hwnd(&window), GCL_STYLE) };
+  assert_ne!(class_style & CS_DBLCLKS, 0);
+
+  let mut click_counts = Vec::new();
+  event_loop.run_return(|event, _, control_flow| {
+    *control_flow = ControlFlow::Poll;
+    match event {
+      Event::NewEvents(StartCause::Init) => {
+        // What the system sends for a double click on a `CS_DBLCLKS` window, in client coordinates.
+        let lparam = LPARAM((20 << 16) | 10);
+        for (msg, wparam) in [
+          (WM_LBUTTONDOWN, MK_LBUTTON),
+          (WM_LBUTTONUP, 0),
+          (WM_LBUTTONDBLCLK, MK_LBUTTON),
+          (WM_LBUTTONUP, 0),
+        ] {
+          unsafe { SendMessageW(hwnd(&window), msg, WPARAM(wparam as usize), lparam) };
+        }
+      }
+      Event::WindowEvent {
+        window_id: id,
+        event:
+          WindowEvent::MouseInput {
+            state: ElementState::Pressed,
+            button: MouseButton::Left,
+            click_count,
+            ..
+          },
+        ..
+      } if id == window_id => click_counts.push(click_count),
+      Event::MainEventsCleared => *control_flow = ControlFlow::Exit,
+      _ => (),
+    }
+  });
+
+  assert_eq!(click_counts, [1, 2]);
+}